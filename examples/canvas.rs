@@ -1,9 +1,8 @@
 use canvas::math::Color;
 use canvas::{Context, Window};
-use canvas::context::Vert;
 
 pub fn main() {
-    let mut window = Window::new("Canvas Example", 640.0, 480.0);
+    let mut window = Window::builder().title("Canvas Example").size(640.0, 480.0).msaa(4).build();
     let mut context = Context::new();
 
     let width   = 64.0;
@@ -15,7 +14,7 @@ pub fn main() {
         let left = window.width() / 2.0 - width / 2.0;
         let top = window.height() / 2.0 - height / 2.0;
         window.handle_events();
-        context.set_display_size(window.width(), window.height());
+        window.sync_display_size(&mut context);
         context.set_origin(window.width() / 2.0, window.height() / 2.0);
         context.set_rotation_deg(rotation);
         context.clear();
@@ -26,12 +25,13 @@ pub fn main() {
             let mut d = rotation * 2.0 * ((i + 1) as f32);
             while d >= 360.0 { d -= 360.0; }
             context.set_rotation_deg(d);
-            context.rect(Color::WHITE, left + (width / 4.0), top - (height / 4.0), width / 2.0, height / 2.0);
+            context.rect(Color::WHITE, left + (width / 4.0), top + (height / 4.0), width / 2.0, height / 2.0);
         }
         context.flush_verts();
         window.flip();
 
-        rotation += 0.10;
+        // degrees per second, so the speed doesn't depend on the refresh rate.
+        rotation += 6.0 * window.frame_time();
         while rotation >= 360.0 {
             rotation -= 360.0;
         }
@@ -0,0 +1,59 @@
+use canvas::context::Vert;
+use canvas::math::{ Color, transform };
+use canvas::{ Context, Window };
+
+/// Stripes that scroll across the screen, drawn over a rect and a mesh alike.
+const STRIPES: &str = "
+uniform float Time;
+
+vec4 effect(vec4 color, vec2 uv, vec2 screen_pos) {
+    float stripe = step(0.5, fract((screen_pos.x + screen_pos.y) / 32.0 - Time));
+    return vec4(color.rgb * mix(0.5, 1.0, stripe), color.a);
+}
+";
+
+struct Stripes {
+    time: f32,
+}
+
+impl canvas::effect::Effect for Stripes {
+    fn source(&self) -> &str {
+        STRIPES
+    }
+
+    fn set_uniforms(&self, program: &canvas::opengl::Program) {
+        program.set_uniform("Time", self.time);
+    }
+}
+
+pub fn main() {
+    let mut window = Window::builder().title("Mesh Effect Example").size(640.0, 480.0).build();
+    let mut context = Context::new();
+
+    let triangle = context.create_mesh(&[
+        Vert::new(0.0, -80.0, 1.0, 0.5, 0.0, 1.0),
+        Vert::new(80.0, 60.0, 0.0, 0.5, 1.0, 1.0),
+        Vert::new(-80.0, 60.0, 0.5, 1.0, 0.0, 1.0),
+    ], &[0, 1, 2]);
+    let mut stripes = Stripes { time: 0.0 };
+
+    while window.running() {
+        window.handle_events();
+        window.sync_display_size(&mut context);
+        context.clear();
+
+        let (cx, cy) = (window.width() / 2.0, window.height() / 2.0);
+        context.with_effect(&stripes, |context| {
+            context.rect(Color::WHITE, cx - 240.0, cy - 40.0, 120.0, 80.0);
+            // meshes are drawn with their own draw call, which has to go through the effect
+            // too, with the context's opacity applied.
+            context.push_opacity(0.75);
+            context.draw_mesh(&triangle, transform::translate(cx, cy));
+            context.pop_opacity();
+        }).expect("failed to compile the stripes effect");
+
+        context.flush_verts();
+        window.flip();
+        stripes.time += window.frame_time();
+    }
+}
@@ -0,0 +1,84 @@
+/// How the colors of what is drawn are combined with what's already in the render target.
+///
+/// Besides the usual blend modes this has the Porter-Duff compositing operators, which combine
+/// the coverage (alpha) of the source and the target as well as their colors. They're only
+/// applied where something is drawn: unlike an HTML canvas, the parts of the target outside of
+/// the drawn primitives are never touched.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Draws over the target, letting it show through transparent parts. This is Porter-Duff
+    /// source-over.
+    Normal,
+    /// Adds colors to the target, which brightens it. Used for light and glow effects.
+    Additive,
+    /// Multiplies colors with the target, which darkens it. Used for shadows and tinting.
+    Multiply,
+    /// The inverse of multiplying the inverted colors, which lightens the target.
+    Screen,
+
+    /// Clears the target to transparent.
+    Clear,
+    /// Replaces the target with the source, including its alpha.
+    Source,
+    /// Keeps the target and ignores the source.
+    Destination,
+    /// Draws the source behind the target.
+    DestinationOver,
+    /// The source, but only where the target is opaque. The target is erased elsewhere.
+    SourceIn,
+    /// Keeps the target only where the source is opaque.
+    DestinationIn,
+    /// The source, but only where the target is transparent. The target is erased elsewhere.
+    SourceOut,
+    /// Erases the target where the source is opaque. Used to punch holes into a layer.
+    DestinationOut,
+    /// Draws the source on top of the target but only where the target is opaque.
+    SourceAtop,
+    /// Keeps the target where the source is opaque, on top of the source.
+    DestinationAtop,
+    /// Keeps the source where the target is transparent and the target where the source is
+    /// transparent.
+    Xor,
+}
+
+impl BlendMode {
+    /// Sets the blend function for fragments with premultiplied alpha, which is what every
+    /// `Context` shader outputs. For the blend modes alpha is always blended like `Normal` so
+    /// that the target's alpha stays the coverage of everything drawn into it. The Porter-Duff
+    /// operators use the same factors for color and alpha.
+    pub(crate) fn apply(self) {
+        let (src, dst, src_alpha, dst_alpha) = match self {
+            BlendMode::Normal => (gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (gl::ONE, gl::ONE, gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Multiply => (gl::DST_COLOR, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Screen => (gl::ONE, gl::ONE_MINUS_SRC_COLOR, gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            _ => {
+                let (src, dst) = self.porter_duff_factors();
+                (src, dst, src, dst)
+            }
+        };
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(src, dst, src_alpha, dst_alpha);
+        }
+    }
+
+    /// The source and destination factors of a Porter-Duff operator, or `Normal`'s for the
+    /// other blend modes.
+    fn porter_duff_factors(self) -> (gl::types::GLenum, gl::types::GLenum) {
+        match self {
+            BlendMode::Clear => (gl::ZERO, gl::ZERO),
+            BlendMode::Source => (gl::ONE, gl::ZERO),
+            BlendMode::Destination => (gl::ZERO, gl::ONE),
+            BlendMode::DestinationOver => (gl::ONE_MINUS_DST_ALPHA, gl::ONE),
+            BlendMode::SourceIn => (gl::DST_ALPHA, gl::ZERO),
+            BlendMode::DestinationIn => (gl::ZERO, gl::SRC_ALPHA),
+            BlendMode::SourceOut => (gl::ONE_MINUS_DST_ALPHA, gl::ZERO),
+            BlendMode::DestinationOut => (gl::ZERO, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::SourceAtop => (gl::DST_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::DestinationAtop => (gl::ONE_MINUS_DST_ALPHA, gl::SRC_ALPHA),
+            BlendMode::Xor => (gl::ONE_MINUS_DST_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+            _ => (gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+        }
+    }
+}
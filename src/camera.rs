@@ -0,0 +1,70 @@
+use super::math::{ Mat4f, Rect, Vec2f, SquareMatrix, transform };
+use super::context::{ Context, Projection };
+use super::view::View;
+
+/// A camera looking at a 2D world. `position` is the world point shown at the center of
+/// `viewport`, which is in display coordinates. A `zoom` of 2 makes the world twice as large
+/// on screen and `rotation` (in radians) turns the camera, which turns the world the other way.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    pub position:   Vec2f,
+    pub zoom:       f32,
+    pub rotation:   f32,
+    pub viewport:   Rect,
+}
+
+impl Camera2D {
+    /// A camera that shows the world at its natural size with the world's origin at the center
+    /// of the viewport.
+    pub fn new(viewport: Rect) -> Camera2D {
+        Camera2D {
+            position: Vec2f::new(0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport,
+        }
+    }
+
+    /// Maps world coordinates to coordinates relative to the top left corner of the viewport.
+    pub fn view_matrix(&self) -> Mat4f {
+        transform::merge_all(&[
+            transform::translate(-self.position.x, -self.position.y),
+            transform::rotation(-self.rotation),
+            transform::scale(self.zoom, self.zoom),
+            transform::translate(self.viewport.width / 2.0, self.viewport.height / 2.0),
+        ])
+    }
+
+    /// Maps world coordinates to display coordinates.
+    pub fn matrix(&self) -> Mat4f {
+        transform::merge(self.view_matrix(), transform::translate(self.viewport.x, self.viewport.y))
+    }
+
+    /// Maps world coordinates to device coordinates through `projection` for a display of the
+    /// given size.
+    pub fn view_projection_matrix(&self, projection: &Projection, display_width: f32, display_height: f32) -> Mat4f {
+        transform::merge(self.matrix(), projection.matrix(display_width, display_height))
+    }
+
+    /// Makes everything drawn on `context` from now on be positioned in world coordinates. This
+    /// replaces the context's current transform; see `Context::set_transform_matrix`.
+    pub fn apply(&self, context: &mut Context) {
+        context.set_transform_matrix(self.matrix());
+    }
+
+    /// A view of the world through this camera for `Context::draw_views`. Unlike `apply` this
+    /// also keeps what's drawn inside of the viewport.
+    pub fn view(&self) -> View {
+        View::new(self.viewport, self.view_matrix())
+    }
+
+    pub fn world_to_screen(&self, point: Vec2f) -> Vec2f {
+        transform::apply2(self.matrix(), point)
+    }
+
+    /// The world point under a point in display coordinates, such as the mouse cursor.
+    pub fn screen_to_world(&self, point: Vec2f) -> Vec2f {
+        let inverse = self.matrix().invert().unwrap_or_else(transform::identity);
+        transform::apply2(inverse, point)
+    }
+}
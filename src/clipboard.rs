@@ -0,0 +1,51 @@
+use std::cell::RefCell;
+use copypasta::ClipboardProvider;
+use glutin::EventsLoop;
+
+/// The system clipboard, reached through the platform's own API: Win32 on Windows,
+/// `NSPasteboard` on macOS, and the Wayland data device or X11 selections on Linux.
+pub(crate) struct Clipboard {
+    /// None if the clipboard couldn't be opened, e.g. without a display server.
+    provider: RefCell<Option<Box<dyn ClipboardProvider>>>,
+}
+
+impl Clipboard {
+    /// Opens the clipboard of the display server that `events_loop` is connected to. On Wayland
+    /// the clipboard talks through the loop's connection, so it has to be dropped first.
+    pub fn new(events_loop: &EventsLoop) -> Clipboard {
+        Clipboard {
+            provider: RefCell::new(open(events_loop)),
+        }
+    }
+
+    /// The text on the clipboard, or None if it's empty or holds something else.
+    pub fn text(&self) -> Option<String> {
+        let text = self.provider.borrow_mut().as_mut()?.get_contents().ok()?;
+        if text.is_empty() { None } else { Some(text) }
+    }
+
+    /// Puts `text` on the clipboard. Returns false if the clipboard isn't available.
+    pub fn set_text(&self, text: &str) -> bool {
+        match self.provider.borrow_mut().as_mut() {
+            Some(provider) => provider.set_contents(text.to_string()).is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))))]
+fn open(events_loop: &EventsLoop) -> Option<Box<dyn ClipboardProvider>> {
+    use glutin::os::unix::EventsLoopExt;
+
+    if let Some(display) = events_loop.get_wayland_display() {
+        // the display lives as long as the events loop, which outlives the clipboard.
+        let (_, clipboard) = unsafe { copypasta::wayland_clipboard::create_clipboards_from_external(display) };
+        return Some(Box::new(clipboard));
+    }
+    copypasta::ClipboardContext::new().ok().map(|context| Box::new(context) as Box<dyn ClipboardProvider>)
+}
+
+#[cfg(not(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten")))))]
+fn open(_events_loop: &EventsLoop) -> Option<Box<dyn ClipboardProvider>> {
+    copypasta::ClipboardContext::new().ok().map(|context| Box::new(context) as Box<dyn ClipboardProvider>)
+}
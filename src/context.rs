@@ -1,230 +1,2620 @@
 use super::math::*;
+use std::cell::{ Cell, RefCell };
+use super::frame_log::{ FrameLog, FlushCause, StateChange };
+use super::marker::{ MarkerStyle, MarkerUniforms, MARKER_FRAGMENT_SHADER };
+use super::grid::{ GridStyle, GRID_FRAGMENT_SHADER };
+use super::pipeline::Pipeline;
+use super::surface::Surface;
+use super::headless::HeadlessGl;
+use super::image::{ Image, PendingReadback };
+use super::view::View;
+use super::sprite::SpriteTransform;
+use super::palette::{ Palette, PALETTE_FRAGMENT_SHADER };
+use super::presenter::IntegerScaling;
+use super::blend::BlendMode;
+use super::mesh::Mesh;
+use super::draw_list::DrawList;
+use super::vertex_layout::{ VertexFormat, VertexLayout, CustomPipeline };
+use super::instancing::{ RectInstance, InstanceVert, INSTANCED_VERTEX_SHADER, UNIT_QUAD, UNIT_QUAD_ELEMS, bind_instance_attributes };
+use super::gl_debug::{ report_gl_errors, report_error };
+use super::effect::{ Effect, fragment_source };
+use super::hot_reload::{ FileWatcher, ReloadError, read_optional };
+use std::borrow::Cow;
+use std::path::{ Path, PathBuf };
 use super::opengl::{
-    VertexArray,
     Buffer,
     BufferType,
     BufferUsage,
-    Shader,
-    ShaderType,
-    Program,
-    Texture,
     BufferDataType,
-    check_gl_errors,
+    Fence,
+    ContextId,
+    ShaderError,
+    GlCapabilities,
+    TextureUnit,
+    Texture,
+    TextureId,
+    TextureFilter,
+    TextureWrap,
+    SamplerParams,
+    SamplerCache,
+    Framebuffer,
+    Query,
+    InternalPixelFormat,
+    read_pixels_rgba,
+    read_pixels_rgba_into,
+    flip_rows_rgba,
+    PixelDataFormat,
+    PixelDataType,
+    GlInfo,
+    StateGuard,
+    bind_texture_unit,
 };
 
-const MAX_VERTS: usize = 40;
-const MAX_ELEMS: usize = 64;
+/// The default number of vertices that are batched before they have to be drawn.
+pub const DEFAULT_MAX_VERTS: usize = 4096;
+/// The default number of elements that are batched before they have to be drawn.
+pub const DEFAULT_MAX_ELEMS: usize = 6144;
+
+/// The number of sets of buffers that batches are streamed through in turn.
+const STREAM_BUFFERS: usize = 3;
+
+/// The file names that `Context::watch_shaders` looks for, along with the built-in shaders
+/// they replace, in the order `create_pipelines` takes them.
+const SHADER_FILES: [(&str, &str); 6] = [
+    ("default.vert", VERTEX_SHADER),
+    ("default.frag", FRAGMENT_SHADER),
+    ("marker.frag", MARKER_FRAGMENT_SHADER),
+    ("grid.frag", GRID_FRAGMENT_SHADER),
+    ("palette.frag", PALETTE_FRAGMENT_SHADER),
+    ("instanced.vert", INSTANCED_VERTEX_SHADER),
+];
+
+pub struct Context {
+    /// Each batch is uploaded into the next of these, so the buffers the GPU may still be
+    /// reading from are only written to again after `STREAM_BUFFERS - 1` other draw calls.
+    streams: Vec<StreamBuffers>,
+    next_stream: Cell<usize>,
+
+    pipeline: Pipeline,
+    marker_pipeline: Pipeline,
+    grid_pipeline: Pipeline,
+    palette_pipeline: Pipeline,
+    instanced_pipeline: Pipeline,
+
+    vertices: Vec<Vert>,
+    elements: Vec<u32>,
+    /// A batch is drawn before it grows past this many vertices or elements, even if the state
+    /// hasn't changed.
+    max_verts: usize,
+    max_elems: usize,
+
+    /// Bound while drawing untextured primitives so that they can share the textured shader.
+    white_texture: Texture,
+    /// Handle of the texture used by the pending vertices.
+    current_texture: gl::types::GLuint,
+    /// How the current texture is sampled.
+    current_sampler: SamplerParams,
+    /// Sets the sampling parameters of batches without touching the textures' own parameters,
+    /// if sampler objects are available.
+    samplers: Option<SamplerCache>,
+    /// Used for textures that don't have their own filter set.
+    default_filter: TextureFilter,
+    /// Multiplied with the texels of every image that is drawn.
+    image_tint: Color,
+    /// Multiplied into the alpha of every vertex that is pushed.
+    opacity: f32,
+    /// The opacities that were current before each call to `push_opacity` that has not been
+    /// popped yet.
+    opacity_stack: Vec<f32>,
+    /// Selects the pipeline (and its parameters) used to draw the pending vertices.
+    current_shading: Shading,
+    current_blend: BlendMode,
+    /// Set when the target converts linear colors to sRGB, so colors (which are given in
+    /// sRGB) have to be converted to linear before they're blended.
+    linear_colors: bool,
+    /// Where primitives drawn inside of `draw_sorted` are drawn relative to each other.
+    z_index: i32,
+    batch_order: BatchOrder,
+    /// Handle of the palette texture that indexed images are drawn with.
+    palette: Option<gl::types::GLuint>,
+
+    /// Final transform applied to each vertex to convert from the screen's coordinates to
+    /// device coordinates.
+    projection_matrix: Mat4f,
+    projection: Projection,
+    display_size: (f32, f32),
+    /// Set while drawing into a surface so that the top row of the canvas ends up in the first
+    /// row of the surface's texture.
+    flip_y: bool,
+    transform: ViewTransform,
+    /// The transforms that were current before each call to `push_transform` that has not
+    /// been popped yet.
+    transform_stack: Vec<ViewTransform>,
+
+    /// A rectangle in display coordinates and a corner radius that every fragment is clipped to.
+    rounded_clip: Option<(Rect, f32)>,
+    /// Clip rects pushed with `push_clip_rect`, each already intersected with the ones before
+    /// it, in display coordinates.
+    clip_stack: Vec<Rect>,
+    /// The top of `clip_stack` in framebuffer pixels, as passed to `glScissor`.
+    scissor: Option<[gl::types::GLint; 4]>,
+    /// The batches that drew each clip mask, so that they can be drawn again to erase the mask
+    /// from the stencil buffer. The last one is still being drawn while `stencil` is
+    /// `StencilMode::Increment`.
+    clip_masks: Vec<Vec<Batch>>,
+    stencil: StencilMode,
+
+    /// The state saved by each call to `save` that hasn't been restored yet.
+    saved_states: Vec<SavedState>,
+
+    /// Batches are collected here instead of being drawn while recording.
+    recording: Option<Vec<Batch>>,
+
+    /// The render targets that were active before each call to `begin_target` that has not been
+    /// ended yet.
+    target_stack: Vec<TargetState>,
+    /// Layers that have been begun but not ended yet.
+    layers: Vec<Layer>,
+    /// Surfaces of layers that have ended, kept around for the next layers.
+    layer_pool: Vec<Surface>,
+
+    /// None while logging is off. Draw calls are logged from `&self` methods, hence the cell.
+    frame_log: RefCell<Option<FrameLog>>,
+
+    gl_info: GlInfo,
+    /// Set when every draw call should leave the GL state the way it found it.
+    preserve_gl_state: bool,
+    /// Set when primitives outside of the display should be dropped in `push_verts`.
+    culling: bool,
+
+    /// The directory that shaders are reloaded from, if `watch_shaders` was called.
+    shader_dir: Option<PathBuf>,
+    shader_watcher: FileWatcher,
+
+    /// The pipelines of the effects used so far along with their sources, indexed by
+    /// `Shading::Effect`.
+    effects: Vec<(String, Pipeline)>,
+    /// The index of the effect that default shading is replaced with inside of `with_effect`.
+    current_effect: Option<u32>,
+
+    /// The GL context that the context's objects were created in, if it was registered.
+    gl_context: Option<ContextId>,
+    /// Set once drawing after `gl_context` was destroyed has been reported.
+    gl_context_lost: Cell<bool>,
+    /// Set once drawing while another window's GL context was current has been reported.
+    gl_context_inactive: Cell<bool>,
+    /// The GL context of a context created with `new_headless`. Declared last so everything
+    /// else is deleted before it is destroyed.
+    headless: Option<HeadlessGl>,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context::with_capacity(DEFAULT_MAX_VERTS, DEFAULT_MAX_ELEMS)
+    }
+
+    /// Creates a context that batches up to `max_verts` vertices and `max_elems` elements
+    /// before drawing them. Batches are otherwise only drawn when the state they're drawn with
+    /// changes or they're flushed, so larger batches mean fewer draw calls at the cost of
+    /// memory. A single primitive with more vertices or elements is still drawn in one batch.
+    pub fn with_capacity(max_verts: usize, max_elems: usize) -> Context {
+        Context::try_with_capacity(max_verts, max_elems).unwrap_or_else(|e| panic!("failed to create GL pipeline: {}", e))
+    }
+
+    /// Like `new`, but returns the error if the GL version is too old or one of the built-in
+    /// shaders can't be compiled by the GL driver instead of panicking.
+    pub fn try_new() -> Result<Context, ShaderError> {
+        Context::try_with_capacity(DEFAULT_MAX_VERTS, DEFAULT_MAX_ELEMS)
+    }
+
+    /// Like `with_capacity`, but returns the error if the GL version is too old or one of the
+    /// built-in shaders can't be compiled by the GL driver instead of panicking.
+    pub fn try_with_capacity(max_verts: usize, max_elems: usize) -> Result<Context, ShaderError> {
+        let (max_verts, max_elems) = (max_verts.max(1), max_elems.max(3));
+        let gl_info = GlInfo::query();
+        let caps = gl_info.capabilities;
+        if caps.glsl_version_line().is_none() || !caps.vertex_arrays {
+            return Err(ShaderError::UnsupportedVersion(gl_info.version));
+        }
+
+        let streams: Vec<StreamBuffers> = (0..STREAM_BUFFERS).map(|index| StreamBuffers::new(index, caps.sync_objects)).collect();
+        let builtin = SHADER_FILES.map(|(_, source)| source);
+        let (pipeline, marker_pipeline, grid_pipeline, palette_pipeline, instanced_pipeline) = create_pipelines(&streams, &caps, &builtin)?;
+
+        let white_pixel: &[u32] = &[0xFFFFFFFF];
+        let white_texture = Texture::new(1, 1, InternalPixelFormat::RGBA, PixelDataFormat::RGBA, PixelDataType::UnsignedByte, Some(white_pixel));
+        white_texture.set_label("canvas.white_texture");
+        let current_texture = white_texture.handle();
+
+        if !caps.es {
+            unsafe {
+                // no-op unless the window has a multisampled framebuffer.
+                gl::Enable(gl::MULTISAMPLE);
+            }
+        }
+
+        report_gl_errors();
+
+        Ok(Context {
+            streams,
+            next_stream:    Cell::new(0),
+
+            pipeline,
+            marker_pipeline,
+            grid_pipeline,
+            palette_pipeline,
+            instanced_pipeline,
+
+            vertices:       Vec::with_capacity(max_verts),
+            elements:       Vec::with_capacity(max_elems),
+            max_verts,
+            max_elems,
+
+            white_texture,
+            current_texture,
+            current_sampler: SamplerParams::new(TextureFilter::Linear, TextureWrap::ClampToEdge),
+            samplers: if caps.sampler_objects { Some(SamplerCache::new()) } else { None },
+            default_filter: TextureFilter::Linear,
+            image_tint:     Color::WHITE,
+            opacity:        1.0,
+            opacity_stack:  Vec::new(),
+            current_shading: Shading::Default,
+            current_blend:  BlendMode::Normal,
+            linear_colors:  caps.srgb_framebuffer && unsafe { gl::IsEnabled(gl::FRAMEBUFFER_SRGB) == gl::TRUE },
+            z_index:        0,
+            batch_order:    BatchOrder::ZIndex,
+            palette:        None,
+
+            projection_matrix:   cgmath::ortho(-1.0, 1.0, -1.0, 1.0, 0.0, 1.0),
+            projection:     Projection::default(),
+            display_size:   (2.0, 2.0),
+            flip_y:         false,
+            transform:      ViewTransform::zero(),
+            transform_stack: Vec::new(),
+
+            rounded_clip:   None,
+            clip_stack:     Vec::new(),
+            scissor:        None,
+            clip_masks:     Vec::new(),
+            stencil:        StencilMode::Disabled,
+
+            saved_states:   Vec::new(),
+
+            recording:      None,
+
+            target_stack:   Vec::new(),
+            layers:         Vec::new(),
+            layer_pool:     Vec::new(),
+
+            frame_log:      RefCell::new(None),
+
+            gl_info,
+            preserve_gl_state: false,
+            culling:        false,
+
+            shader_dir:     None,
+            shader_watcher: FileWatcher::new(),
+
+            effects:        Vec::new(),
+            current_effect: None,
+
+            gl_context:     ContextId::current(),
+            gl_context_lost: Cell::new(false),
+            gl_context_inactive: Cell::new(false),
+            headless: None,
+        })
+    }
+
+    /// Creates a context with its own GL context and no visible window, which draws into a
+    /// `width` by `height` surface, e.g. for generating thumbnails on a server or rendering in
+    /// tests. `read_pixels` reads from the surface. Most platforms still need a display server
+    /// to create the GL context; on Linux CI something like Xvfb works.
+    ///
+    /// The surface is the context's outermost target, so `end_target` must not be called
+    /// more often than `begin_target`.
+    pub fn new_headless(width: u32, height: u32) -> Result<Context, String> {
+        let headless = HeadlessGl::new(width, height)?;
+        let mut context = Context::try_new().map_err(|err| err.to_string())?;
+        context.begin_target(headless.surface());
+        context.headless = Some(headless);
+        Ok(context)
+    }
+
+    /// The surface that a context created with `new_headless` draws into.
+    pub fn headless_surface(&self) -> Option<&Surface> {
+        self.headless.as_ref().map(|headless| headless.surface())
+    }
+
+    pub fn push_verts(&mut self, verts: &[Vert], elems: &[u32]) {
+        if self.culling && self.recording.is_none() && !verts.is_empty() {
+            let first = verts[0].pos;
+            let (mut min_x, mut min_y, mut max_x, mut max_y) = (first.x, first.y, first.x, first.y);
+            for v in verts.iter().skip(1) {
+                let (x, y) = (v.pos.x, v.pos.y);
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+            if !self.is_rect_visible(Rect::new(min_x, min_y, max_x - min_x, max_y - min_y)) {
+                return;
+            }
+        }
+
+        if self.vertices.len() + verts.len() > self.max_verts || self.elements.len() + elems.len() > self.max_elems {
+            self.flush(FlushCause::BatchFull);
+        }
+
+        debug_assert!(elems.len() % 3 == 0, "number of elements must be a multiple of 3");
+        let elem_delta = self.vertices.len() as u32;
+
+        for v in verts.iter() {
+            // self.vertices.push(v.transform(&self.display_transform));
+            let mut v = *v;
+            if self.opacity < 1.0 {
+                v.col.a *= self.opacity;
+            }
+            self.vertices.push(v);
+        }
+
+        for e in elems.iter() {
+            debug_assert!(*e < verts.len() as u32, "element is larger than the number of vertices provided");
+            self.elements.push((*e) + elem_delta);
+        }
+    }
+
+    /// Turns culling on or off. While it's on, primitives whose bounding box ends up entirely
+    /// outside of the display after the current transform are dropped instead of being
+    /// batched. Nothing is culled while recording for `record`, `draw_views` or `draw_sorted`,
+    /// since the recorded batches may be drawn with other transforms.
+    ///
+    /// It's off by default because the test uses the context's own projection and display
+    /// size, so it drops geometry that a custom projection or a target of another size would
+    /// have shown.
+    pub fn set_culling(&mut self, enabled: bool) {
+        self.culling = enabled;
+    }
+
+    pub fn culling(&self) -> bool {
+        self.culling
+    }
+
+    /// Whether any part of `rect` in canvas coordinates may end up on the display with the
+    /// current transform and projection. Useful to skip generating the geometry of expensive
+    /// content that's scrolled out of view. This is conservative: it can return true for rects
+    /// that aren't visible, e.g. if they're behind the camera of a perspective projection.
+    pub fn is_rect_visible(&self, rect: Rect) -> bool {
+        let m = self.canvas_to_device();
+        let corners = [
+            vec2f(rect.x, rect.y),
+            vec2f(rect.right(), rect.y),
+            vec2f(rect.x, rect.bottom()),
+            vec2f(rect.right(), rect.bottom()),
+        ];
+
+        let (mut left, mut right, mut below, mut above) = (true, true, true, true);
+        for corner in corners.iter() {
+            let device = m * vec4f(corner.x, corner.y, 0.0, 1.0);
+            if device.w <= 0.0 { return true }
+            let (x, y) = (device.x / device.w, device.y / device.w);
+            left &= x < -1.0;
+            right &= x > 1.0;
+            below &= y < -1.0;
+            above &= y > 1.0;
+        }
+        !(left || right || below || above)
+    }
+
+    pub fn flush_verts(&mut self) {
+        self.flush(FlushCause::Explicit);
+    }
+
+    fn flush(&mut self, cause: FlushCause) {
+        if self.vertices.is_empty() { return }
+
+        let state = self.batch_state();
+        if let StencilMode::Increment(_) = state.stencil {
+            if let Some(mask) = self.clip_masks.last_mut() {
+                mask.push(Batch { state, vertices: self.vertices.clone(), elements: self.elements.clone() });
+            }
+        }
+        if let Some(ref mut recorded) = self.recording {
+            recorded.push(Batch {
+                state,
+                vertices: std::mem::take(&mut self.vertices),
+                elements: std::mem::take(&mut self.elements),
+            });
+            return;
+        }
+
+        if self.is_gl_context_alive() && self.is_gl_context_current() {
+            self.submit(cause, &state, &self.projection_matrix, &self.vertices, &self.elements);
+        }
+        self.vertices.clear();
+        self.elements.clear();
+    }
+
+    /// Whether the GL context that this context was created in still exists. Once its `Window`
+    /// is dropped nothing is drawn anymore, and the first draw after that is reported as an
+    /// error through `gl_debug`.
+    pub fn is_gl_context_alive(&self) -> bool {
+        let alive = self.gl_context.is_none_or(|id| id.is_alive());
+        if !alive && !self.gl_context_lost.replace(true) {
+            report_error("Context was used after the GL context it was created in was destroyed; its Window must outlive it");
+        }
+        alive
+    }
+
+    /// With several windows, whether this context's window is the one that was made current.
+    /// Drawing while another one is current is skipped and reported once.
+    fn is_gl_context_current(&self) -> bool {
+        let current = self.gl_context.is_none_or(|id| id.is_current());
+        if !current && !self.gl_context_inactive.replace(true) {
+            report_error("Context was used while another window's GL context was current; call Window::make_current first");
+        }
+        current
+    }
+
+    /// The state that the pending vertices will be drawn with.
+    fn batch_state(&self) -> BatchState {
+        let shading = match (self.current_effect, self.current_shading) {
+            (Some(index), Shading::Default) => Shading::Effect(index, false),
+            (Some(index), Shading::Premultiplied) => Shading::Effect(index, true),
+            (_, shading) => shading,
+        };
+        BatchState {
+            texture: Some(self.current_texture),
+            sampler: self.current_sampler,
+            shading,
+            blend: self.current_blend,
+            linear_colors: self.linear_colors,
+            transform: self.transform.matrix(),
+            rounded_clip: self.rounded_clip,
+            scissor: self.scissor,
+            stencil: self.stencil,
+            z_index: self.z_index,
+        }
+    }
+
+    /// Makes every draw call restore the GL bindings and blend, stencil and scissor state that
+    /// were current before it (see `GlState`), so the context can draw in between the GL
+    /// rendering of an application that doesn't expect its state to change. This is off by
+    /// default since reading the state back can stall. The viewport and framebuffer are only
+    /// changed by `begin_target`, `end_target` and `draw_views`, which restore them anyway.
+    ///
+    /// Without this, call `opengl::invalidate_binding_cache` after the application's own GL
+    /// rendering so the context doesn't skip binding objects it thinks are still bound. Where
+    /// sampler objects are available, one is also left bound to texture unit 0, which overrides
+    /// the parameters of textures the application samples from that unit until it binds 0.
+    pub fn set_preserve_gl_state(&mut self, preserve: bool) {
+        self.preserve_gl_state = preserve;
+    }
+
+    pub fn preserves_gl_state(&self) -> bool {
+        self.preserve_gl_state
+    }
+
+    /// Draws vertices with the given state. Every batch of vertices that is drawn goes through
+    /// here, which logs it as a draw call.
+    fn submit(&self, cause: FlushCause, state: &BatchState, projection: &Mat4f, vertices: &[Vert], elements: &[u32]) {
+        if state.stencil == StencilMode::Clear {
+            clear_stencil();
+            return;
+        }
+        if elements.is_empty() { return }
+        self.log_draw_call(cause, vertices.len(), elements.len());
+        let _guard = if self.preserve_gl_state { Some(StateGuard::new()) } else { None };
+        let (index, stream) = self.next_stream();
+        self.apply_state(index, state, projection);
+
+        // the pipeline's vertex array already has the element buffer, so binding it is skipped.
+        let idle = stream.is_idle();
+        stream.vertices.bind();
+        stream.elements.bind();
+        stream.upload(&stream.vertices, vertices, idle);
+        stream.upload(&stream.elements, elements, idle);
+
+        unsafe {
+            gl::DrawElements(gl::TRIANGLES, elements.len() as _, gl::UNSIGNED_INT, std::ptr::null());
+        }
+        stream.fence();
+    }
+
+    /// Draws the unit quad once for each instance with the given state.
+    fn submit_instances(&self, state: &BatchState, projection: &Mat4f, instances: &[InstanceVert]) {
+        self.log_draw_call(FlushCause::Instanced, UNIT_QUAD.len() * instances.len(), UNIT_QUAD_ELEMS.len() * instances.len());
+        let _guard = if self.preserve_gl_state { Some(StateGuard::new()) } else { None };
+        let (index, stream) = self.next_stream();
+        self.apply_state(index, state, projection);
+
+        let idle = stream.is_idle();
+        stream.vertices.bind();
+        stream.elements.bind();
+        stream.upload(&stream.vertices, &UNIT_QUAD, idle);
+        stream.upload(&stream.elements, &UNIT_QUAD_ELEMS, idle);
+        stream.instances.bind();
+        stream.upload(&stream.instances, instances, idle);
+
+        unsafe {
+            gl::DrawElementsInstanced(gl::TRIANGLES, UNIT_QUAD_ELEMS.len() as _, gl::UNSIGNED_INT, std::ptr::null(), instances.len() as _);
+        }
+        stream.fence();
+    }
+
+    /// The stream buffers that the next batch is uploaded into, along with their index.
+    fn next_stream(&self) -> (usize, &StreamBuffers) {
+        let index = self.next_stream.get();
+        self.next_stream.set((index + 1) % self.streams.len());
+        (index, &self.streams[index])
+    }
+
+    /// Draws a mesh with the given state. `opacity` is applied by the shader since the mesh's
+    /// vertices are already on the GPU.
+    fn submit_mesh(&self, state: &BatchState, projection: &Mat4f, mesh: &Mesh, opacity: f32) {
+        self.log_draw_call(FlushCause::Mesh, mesh.vertices().len(), mesh.elements().len());
+        let _guard = if self.preserve_gl_state { Some(StateGuard::new()) } else { None };
+        // the mesh brings its own vertex array, so any stream's will do.
+        self.apply_state(0, state, projection);
+        mesh.vertex_array().bind();
+
+        // the program that `apply_state` bound, which meshes are only drawn with by default
+        // shading and effects.
+        let program = match state.shading {
+            Shading::Effect(index, _) => &self.effects[index as usize].1.program,
+            _ => &self.pipeline.program,
+        };
+        program.set_uniform("Opacity", opacity);
+        unsafe {
+            gl::DrawElements(gl::TRIANGLES, mesh.elements().len() as _, gl::UNSIGNED_INT, std::ptr::null());
+        }
+        program.set_uniform("Opacity", 1.0);
+    }
+
+    /// Binds the pipeline and texture and sets the GL state that a batch is drawn with.
+    fn apply_state(&self, stream: usize, state: &BatchState, projection: &Mat4f) {
+        match state.shading {
+            Shading::Default | Shading::Premultiplied => {
+                self.pipeline.bind(stream, projection, &state.transform, state.rounded_clip, state.linear_colors);
+                let premultiplied = state.shading == Shading::Premultiplied;
+                self.pipeline.program.set_uniform("TexturePremultiplied", premultiplied);
+            },
+            Shading::Marker(marker) => {
+                self.marker_pipeline.bind(stream, projection, &state.transform, state.rounded_clip, state.linear_colors);
+                marker.apply(&self.marker_pipeline.program);
+            },
+            Shading::Grid(grid) => {
+                self.grid_pipeline.bind(stream, projection, &state.transform, state.rounded_clip, state.linear_colors);
+                grid.apply(&self.grid_pipeline.program);
+            },
+            Shading::Palette(palette) => {
+                self.palette_pipeline.bind(stream, projection, &state.transform, state.rounded_clip, state.linear_colors);
+                self.palette_pipeline.program.set_uniform("Palette", TextureUnit(1));
+                bind_texture_unit(1, palette);
+            },
+            Shading::Effect(index, premultiplied) => {
+                let pipeline = &self.effects[index as usize].1;
+                pipeline.bind(stream, projection, &state.transform, state.rounded_clip, state.linear_colors);
+                pipeline.program.set_uniform("TexturePremultiplied", premultiplied);
+            },
+            Shading::Instanced => {
+                self.instanced_pipeline.bind(stream, projection, &state.transform, state.rounded_clip, state.linear_colors);
+                self.instanced_pipeline.program.set_uniform("TexturePremultiplied", false);
+            },
+        }
+        self.apply_fixed_state(state);
+    }
+
+    /// Sets the texture, blend, stencil and scissor state of a batch, which doesn't depend on
+    /// the program it's drawn with.
+    fn apply_fixed_state(&self, state: &BatchState) {
+        bind_texture_unit(0, state.texture.unwrap_or_else(|| self.white_texture.handle()));
+        match self.samplers {
+            Some(ref samplers) => samplers.bind(0, state.sampler),
+            None => state.sampler.apply_to_bound_texture(),
+        }
+
+        unsafe {
+            state.blend.apply();
+
+            match state.stencil {
+                StencilMode::Disabled | StencilMode::Clear => {
+                    gl::Disable(gl::STENCIL_TEST);
+                },
+                StencilMode::Test(level) => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::EQUAL, level as _, 0xFF);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+                },
+                StencilMode::Increment(level) => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::EQUAL, level as _, 0xFF);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::INCR);
+                },
+                StencilMode::Decrement(level) => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::EQUAL, level as _, 0xFF);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::DECR);
+                },
+            }
+            let write_color = !state.stencil.writes_stencil();
+            gl::ColorMask(write_color as _, write_color as _, write_color as _, write_color as _);
+            gl::StencilMask(0xFF);
+
+            if let Some([x, y, w, h]) = state.scissor {
+                gl::Enable(gl::SCISSOR_TEST);
+                gl::Scissor(x, y, w, h);
+            } else {
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+        }
+    }
+
+    /// Runs `query` around everything drawn by `draw`. Pending vertices are flushed before and
+    /// after so that exactly the draw calls made by `draw` are measured.
+    ///
+    /// While recording, e.g. inside of `draw_views`, the draw calls only run later, so `draw`
+    /// is called without the query and an error is reported instead.
+    pub fn measure<F: FnOnce(&mut Context)>(&mut self, query: &mut Query, draw: F) {
+        if self.recording.is_some() {
+            report_error("Context::measure was called while recording, which can't be measured");
+            draw(self);
+            return;
+        }
+        self.flush(FlushCause::Query);
+        query.begin();
+        draw(self);
+        self.flush(FlushCause::Query);
+        query.end();
+    }
+
+    /// Calls `draw` once and then draws everything it drew into each of `views`, which is
+    /// cheaper than issuing the draw calls again for split-screen rendering. Each view's camera
+    /// matrix is applied on top of whatever transform was current for each primitive, and the
+    /// view's viewport (in display coordinates) is treated as a display of that size.
+    pub fn draw_views<F: FnOnce(&mut Context)>(&mut self, views: &[View], draw: F) {
+        self.flush(FlushCause::ViewsChanged);
+
+        let previous_recording = self.recording.replace(Vec::new());
+        draw(self);
+        self.flush(FlushCause::ViewsChanged);
+        let batches = std::mem::replace(&mut self.recording, previous_recording).unwrap_or_default();
+
+        if let Some(ref mut recorded) = self.recording {
+            // nested inside of another recording, which can't replay per view.
+            recorded.extend(batches);
+            self.log_state_change(StateChange::ViewsNested);
+            return;
+        }
+
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let (display_w, display_h) = self.display_size;
+        let (sx, sy) = (viewport[2] as f32 / display_w.max(1.0), viewport[3] as f32 / display_h.max(1.0));
+
+        for view in views.iter() {
+            let r = view.viewport;
+            let (x, w, h) = ((r.x * sx).round() as i32, (r.width * sx).round() as i32, (r.height * sy).round() as i32);
+            let y = if self.flip_y {
+                (r.y * sy).round() as i32
+            } else {
+                viewport[3] - (r.bottom() * sy).round() as i32
+            };
+            unsafe {
+                gl::Viewport(viewport[0] + x, viewport[1] + y, w, h);
+            }
+            self.log_state_change(StateChange::View(r));
+
+            let mut projection = self.projection.matrix(r.width, r.height);
+            if self.flip_y {
+                projection = transform::merge(projection, transform::scale(1.0, -1.0));
+            }
+
+            for batch in batches.iter() {
+                let mut state = batch.state;
+                state.transform = transform::merge(state.transform, view.camera);
+                self.submit(FlushCause::ViewsChanged, &state, &projection, &batch.vertices, &batch.elements);
+            }
+        }
+
+        unsafe {
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+    }
+
+    /// Sets where primitives drawn from now on inside of `draw_sorted` end up: primitives with
+    /// a higher z index are drawn on top of primitives with a lower one, no matter which was
+    /// drawn first. Primitives with the same z index keep the order they were drawn in. This is
+    /// 0 by default and has no effect outside of `draw_sorted`.
+    pub fn set_z_index(&mut self, z_index: i32) {
+        if self.z_index == z_index { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::ZIndexChanged);
+        }
+        self.log_state_change(StateChange::ZIndex(z_index));
+        self.z_index = z_index;
+    }
+
+    pub fn z_index(&self) -> i32 {
+        self.z_index
+    }
+
+    /// Sets how `draw_sorted` orders what's drawn inside of it. This is `BatchOrder::ZIndex` by
+    /// default.
+    pub fn set_batch_order(&mut self, order: BatchOrder) {
+        self.batch_order = order;
+    }
+
+    pub fn batch_order(&self) -> BatchOrder {
+        self.batch_order
+    }
+
+    /// Calls `draw` and then draws everything it drew sorted by z index (see `set_z_index`)
+    /// and, depending on the batch order, by state. Since primitives with the same z index and
+    /// state end up next to each other, this can also need fewer draw calls than drawing in
+    /// the original order. Clip masks still clip what was drawn while they were active.
+    pub fn draw_sorted<F: FnOnce(&mut Context)>(&mut self, draw: F) {
+        self.flush(FlushCause::ZIndexChanged);
+
+        let previous_recording = self.recording.replace(Vec::new());
+        draw(self);
+        self.flush(FlushCause::ZIndexChanged);
+        let batches = std::mem::replace(&mut self.recording, previous_recording).unwrap_or_default();
+        let batches = sort_batches(batches, self.batch_order, self.max_verts, self.max_elems);
+
+        if let Some(ref mut recorded) = self.recording {
+            // sorted among themselves, the outer recording decides when they're drawn.
+            recorded.extend(batches);
+            return;
+        }
+
+        for batch in batches.iter() {
+            self.submit(FlushCause::ZIndexChanged, &batch.state, &self.projection_matrix, &batch.vertices, &batch.elements);
+        }
+    }
+
+    /// Calls `draw` and returns everything it drew as a `DrawList` instead of drawing it. The
+    /// list can be drawn with `draw_list` as often as needed, which skips generating the
+    /// vertices again, e.g. for a part of the UI that hasn't changed since the last frame.
+    pub fn record<F: FnOnce(&mut Context)>(&mut self, draw: F) -> DrawList {
+        self.flush(FlushCause::DrawList);
+
+        let previous_recording = self.recording.replace(Vec::new());
+        draw(self);
+        self.flush(FlushCause::DrawList);
+        let batches = std::mem::replace(&mut self.recording, previous_recording).unwrap_or_default();
+        DrawList::from_batches(batches)
+    }
+
+    /// Draws a list recorded with `record`. The state that was current while recording is part
+    /// of the list, and the current state is applied on top of it: the current transform after
+    /// the recorded ones, clip rects and clip masks intersected with the recorded ones, the
+    /// opacity multiplied in and the z index added. A recorded rounded clip replaces the
+    /// current one.
+    ///
+    /// The textures that were drawn while recording must still be alive.
+    pub fn draw_list(&mut self, list: &DrawList) {
+        self.flush(FlushCause::DrawList);
+
+        let outer = self.batch_state();
+        for batch in list.batches.iter() {
+            let mut batch = batch.clone();
+            batch.state = batch.state.nested_in(&outer);
+            if self.opacity < 1.0 {
+                for v in batch.vertices.iter_mut() {
+                    v.col.a *= self.opacity;
+                }
+            }
+
+            if let StencilMode::Increment(_) = batch.state.stencil {
+                if let Some(mask) = self.clip_masks.last_mut() {
+                    mask.push(batch.clone());
+                }
+            }
+            if let Some(ref mut recorded) = self.recording {
+                recorded.push(batch);
+            } else {
+                self.submit(FlushCause::DrawList, &batch.state, &self.projection_matrix, &batch.vertices, &batch.elements);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn has_vertices(&self) -> bool {
+        !self.vertices.is_empty()
+    }
+
+    /// The number of vertices and elements a batch can hold, see `with_capacity`.
+    pub fn batch_capacity(&self) -> (usize, usize) {
+        (self.max_verts, self.max_elems)
+    }
+
+    /// Sets the color that `clear` fills the target with. Like everything else the context
+    /// draws it's stored with premultiplied alpha, which is what compositors expect from
+    /// transparent windows (see `WindowBuilder::transparent`).
+    pub fn set_clear_color(&self, color: Color) {
+        // clears are converted to sRGB too.
+        let color = if self.linear_colors { color.to_linear() } else { color };
+        unsafe {
+            gl::ClearColor(color.r * color.a, color.g * color.a, color.b * color.a, color.a);
+        }
+    }
+
+    pub fn clear(&self) {
+        unsafe {
+            // the last batch may have left a clip rect's scissor test enabled.
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+    }
+
+    /// Fills a rectangle whose top left corner is at `(x, y)`, like a `Rect`.
+    pub fn rect<P: Into<f32>, S: Into<f32>>(&mut self, color: Color, x: P, y: P, width: S, height: S) {
+        self.set_texture(self.white_texture.handle(), SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge));
+        self.set_shading(Shading::Default);
+        self.push_verts(&rect_verts(color, x.into(), y.into(), width.into(), height.into()), &UNIT_QUAD_ELEMS);
+    }
+
+    /// Draws many rectangles with a single instanced draw call. Only the per-instance data is
+    /// uploaded and the corners are computed on the GPU, which is much cheaper than pushing
+    /// the vertices of each rectangle for thousands of particles or tiles. The current
+    /// transform, clip, blend mode and opacity apply to every instance.
+    ///
+    /// Inside of `draw_views`, `draw_sorted` and clip masks, whose batches are replayed from
+    /// their vertices, the rectangles are pushed as ordinary vertices instead.
+    pub fn rect_instanced(&mut self, instances: &[RectInstance]) {
+        if instances.is_empty() { return }
+        self.set_texture(self.white_texture.handle(), SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge));
+
+        if self.recording.is_some() || self.stencil.writes_stencil() || !self.gl_info.capabilities.instancing {
+            self.set_shading(Shading::Default);
+            for instance in instances.iter() {
+                self.push_verts(&instance.vertices(), &UNIT_QUAD_ELEMS);
+            }
+            return;
+        }
+
+        self.set_shading(Shading::Instanced);
+        let opacity = self.opacity;
+        let data: Vec<InstanceVert> = instances.iter().map(|i| InstanceVert::new(i, opacity)).collect();
+        self.submit_instances(&self.batch_state(), &self.projection_matrix, &data);
+        self.set_shading(Shading::Default);
+    }
+
+    /// Uploads vertices and elements into a `Mesh` that can be drawn with `draw_mesh` without
+    /// uploading them again.
+    pub fn create_mesh(&self, vertices: &[Vert], elements: &[u32]) -> Mesh {
+        Mesh::new(&self.pipeline, vertices, elements)
+    }
+
+    /// Draws a mesh untextured with `transform` applied to its vertices before the current
+    /// transform. Pending vertices are flushed first, and the mesh is drawn right away with its
+    /// own draw call.
+    pub fn draw_mesh(&mut self, mesh: &Mesh, transform: Mat4f) {
+        if mesh.elements().is_empty() { return }
+        self.set_texture(self.white_texture.handle(), SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge));
+        self.set_shading(Shading::Default);
+
+        if self.recording.is_some() || self.stencil.writes_stencil() {
+            let saved = self.transform;
+            self.set_transform(saved.with_local(transform::merge(transform, saved.local)));
+            self.push_verts(mesh.vertices(), mesh.elements());
+            self.set_transform(saved);
+            return;
+        }
+
+        self.flush(FlushCause::Mesh);
+        let mut state = self.batch_state();
+        state.transform = transform::merge(transform, state.transform);
+        self.submit_mesh(&state, &self.projection_matrix, mesh, self.opacity);
+    }
+
+    /// Draws the vertices pending in a custom pipeline with the current transform, clips and
+    /// blend mode, then clears them. The current texture is bound to texture unit 0. Pending
+    /// vertices of the context are flushed first.
+    ///
+    /// Custom vertices are always drawn right away, so they aren't part of what is recorded by
+    /// `record`, `draw_views` or `draw_sorted`, and can't be used to draw clip masks.
+    pub fn draw_custom<V: VertexFormat>(&mut self, pipeline: &mut CustomPipeline<V>) {
+        if !pipeline.has_vertices() { return }
+        assert!(!self.stencil.writes_stencil(), "custom pipelines can't draw clip masks");
+        self.flush(FlushCause::Custom);
+
+        let state = self.batch_state();
+        let (vertices, elements) = pipeline.pending();
+        self.log_draw_call(FlushCause::Custom, vertices, elements);
+
+        let _guard = if self.preserve_gl_state { Some(StateGuard::new()) } else { None };
+        self.apply_fixed_state(&state);
+        pipeline.submit(&self.projection_matrix, &state.transform);
+    }
+
+    /// Draws a texture at its natural size with its top left corner at `(x, y)`.
+    ///
+    /// The texture must not be dropped until the pending vertices have been flushed.
+    pub fn draw_image(&mut self, texture: &Texture, x: f32, y: f32) {
+        let dest = Rect::new(x, y, texture.get_width() as f32, texture.get_height() as f32);
+        self.draw_image_scaled(texture, dest);
+    }
+
+    /// Draws a texture stretched to fill `dest`.
+    pub fn draw_image_scaled(&mut self, texture: &Texture, dest: Rect) {
+        let src = Rect::new(0.0, 0.0, texture.get_width() as f32, texture.get_height() as f32);
+        self.draw_image_region(texture, src, dest);
+    }
+
+    /// Draws the part of a texture inside of `src` (in pixels) stretched to fill `dest`. Used to
+    /// draw a single sprite from a sprite sheet.
+    pub fn draw_image_region(&mut self, texture: &Texture, src: Rect, dest: Rect) {
+        self.draw_image_with(texture, src, dest, &SpriteTransform::new());
+    }
+
+    /// Like `draw_image_region` but flips and rotates the image within `dest` as described by
+    /// `sprite`. Unlike `set_rotation` this doesn't flush, so it's cheap to use on every sprite.
+    pub fn draw_image_with(&mut self, texture: &Texture, src: Rect, dest: Rect, sprite: &SpriteTransform) {
+        self.set_texture(texture.handle(), self.sampler_for(texture, texture.wrap()));
+        self.set_shading(Shading::for_texture(texture));
+        self.push_image_quad(texture, src, dest, sprite);
+    }
+
+    /// Sets the palette that `draw_indexed_image` looks colors up in. Changing the palette's
+    /// colors afterwards is visible in everything drawn with it that hasn't been flushed yet.
+    pub fn set_palette(&mut self, palette: &Palette) {
+        self.palette = Some(palette.texture().handle());
+        self.log_state_change(StateChange::Palette(palette.texture().handle()));
+    }
+
+    /// Draws the part of an indexed texture inside of `src` (in pixels) stretched to fill
+    /// `dest`. The texture's red channel holds 8-bit palette indices, like the textures made by
+    /// `StreamingTexture::indexed`, and each index is drawn with its color in the palette set
+    /// with `set_palette`. Indices are never filtered.
+    ///
+    /// Panics if no palette has been set.
+    pub fn draw_indexed_image(&mut self, texture: &Texture, src: Rect, dest: Rect) {
+        let palette = self.palette.expect("set_palette must be called before drawing indexed images");
+        self.set_texture(texture.handle(), SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge));
+        self.set_shading(Shading::Palette(palette));
+        self.push_image_quad(texture, src, dest, &SpriteTransform::new());
+    }
+
+    /// Pushes a tinted quad that maps `src` of `texture` onto `dest`.
+    fn push_image_quad(&mut self, texture: &Texture, src: Rect, dest: Rect, sprite: &SpriteTransform) {
+        self.push_verts(&image_quad_verts(texture, src, dest, sprite, self.image_tint), &UNIT_QUAD_ELEMS);
+    }
+
+    /// Fills `dest` with copies of a texture. Each copy is drawn at `tile_scale` times the
+    /// texture's size, starting from the top left corner of `dest`. The texture is drawn with
+    /// repeat wrapping unless it was set to mirrored repeat.
+    pub fn fill_rect_tiled(&mut self, texture: &Texture, dest: Rect, tile_scale: f32) {
+        let wrap = match texture.wrap() {
+            TextureWrap::MirroredRepeat => TextureWrap::MirroredRepeat,
+            _ => TextureWrap::Repeat,
+        };
+        self.set_texture(texture.handle(), self.sampler_for(texture, wrap));
+        self.set_shading(Shading::for_texture(texture));
+
+        let tile_w = (texture.get_width() as f32 * tile_scale).max(f32::EPSILON);
+        let tile_h = (texture.get_height() as f32 * tile_scale).max(f32::EPSILON);
+        let (u1, v1) = (dest.width / tile_w, dest.height / tile_h);
+
+        let tint = self.image_tint;
+        let vcol = VertCol::new(tint.r, tint.g, tint.b, tint.a);
+        let tl = Vert::with_puc(VertPos::new(dest.x, dest.y), VertUV::new(0.0, 0.0), vcol);
+        let tr = Vert::with_puc(VertPos::new(dest.right(), dest.y), VertUV::new(u1, 0.0), vcol);
+        let bl = Vert::with_puc(VertPos::new(dest.x, dest.bottom()), VertUV::new(0.0, v1), vcol);
+        let br = Vert::with_puc(VertPos::new(dest.right(), dest.bottom()), VertUV::new(u1, v1), vcol);
+
+        self.push_verts(
+            &[tl, tr, bl, br],
+            &[0, 1, 2, 2, 3, 1],
+        );
+    }
+
+    /// Draws a nine-patch: `src` is split into a 3x3 grid by `margins` (in texture pixels) and
+    /// drawn into `dest` with the corners kept at their original size, the edges stretched along
+    /// one axis and the center stretched along both. If `dest` is too small for the margins the
+    /// corners are shrunk to fit.
+    pub fn draw_nine_patch(&mut self, texture: &Texture, src: Rect, margins: Margins, dest: Rect) {
+        let fit = |a: f32, b: f32, avail: f32| {
+            if a + b > avail && a + b > 0.0 {
+                let scale = avail.max(0.0) / (a + b);
+                (a * scale, b * scale)
+            } else {
+                (a, b)
+            }
+        };
+        let (dl, dr) = fit(margins.left, margins.right, dest.width);
+        let (dt, db) = fit(margins.top, margins.bottom, dest.height);
+
+        let src_xs = [src.x, src.x + margins.left, src.right() - margins.right, src.right()];
+        let src_ys = [src.y, src.y + margins.top, src.bottom() - margins.bottom, src.bottom()];
+        let dest_xs = [dest.x, dest.x + dl, dest.right() - dr, dest.right()];
+        let dest_ys = [dest.y, dest.y + dt, dest.bottom() - db, dest.bottom()];
+
+        for row in 0..3 {
+            for col in 0..3 {
+                let d = Rect::new(dest_xs[col], dest_ys[row], dest_xs[col + 1] - dest_xs[col], dest_ys[row + 1] - dest_ys[row]);
+                if d.width <= 0.0 || d.height <= 0.0 { continue }
+                let s = Rect::new(src_xs[col], src_ys[row], src_xs[col + 1] - src_xs[col], src_ys[row + 1] - src_ys[row]);
+                self.draw_image_region(texture, s, d);
+            }
+        }
+    }
+
+    /// Draws a plot marker centered on `(x, y)`. Markers are anti-aliased signed distance fields
+    /// so they stay smooth at any scale, and consecutive markers with the same shape and outline
+    /// are drawn together.
+    pub fn marker(&mut self, x: f32, y: f32, style: &MarkerStyle) {
+        let radius = style.size / 2.0;
+        if radius <= 0.0 { return }
+
+        self.set_texture(self.white_texture.handle(), SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge));
+        self.set_shading(Shading::Marker(MarkerUniforms::from_style(style)));
+
+        // pad the quad by a unit so that the anti-aliased edge isn't cut off.
+        let extent = radius + 1.0;
+        let uv = extent / radius;
+
+        let fill = style.fill;
+        let vcol = VertCol::new(fill.r, fill.g, fill.b, fill.a);
+        let tl = Vert::with_puc(VertPos::new(x - extent, y - extent), VertUV::new(-uv, -uv), vcol);
+        let tr = Vert::with_puc(VertPos::new(x + extent, y - extent), VertUV::new( uv, -uv), vcol);
+        let bl = Vert::with_puc(VertPos::new(x - extent, y + extent), VertUV::new(-uv,  uv), vcol);
+        let br = Vert::with_puc(VertPos::new(x + extent, y + extent), VertUV::new( uv,  uv), vcol);
+
+        self.push_verts(
+            &[tl, tr, bl, br],
+            &[0, 1, 2, 2, 3, 1],
+        );
+    }
+
+    /// Draws a marker at each point.
+    pub fn markers(&mut self, points: &[(f32, f32)], style: &MarkerStyle) {
+        for &(x, y) in points.iter() {
+            self.marker(x, y, style);
+        }
+    }
+
+    /// Fills the display with an infinite grid in canvas coordinates. Grid lines are drawn by a
+    /// shader rather than as geometry, and finer lines fade out as they get too close together
+    /// so the grid stays readable at any zoom level.
+    pub fn draw_grid(&mut self, style: &GridStyle) {
+        let (w, h) = self.display_size;
+        let inv = self.transform.matrix().invert().unwrap_or_else(transform::identity);
+        let corner = |x: f32, y: f32| {
+            let world = transform::apply2(inv, vec2f(x, y));
+            Vert::with_puc(VertPos::new(x, y), VertUV::new(world.x, world.y), VertCol::new(1.0, 1.0, 1.0, 1.0))
+        };
+        let quad = [corner(0.0, 0.0), corner(w, 0.0), corner(0.0, h), corner(w, h)];
+
+        self.with_screen_transform(|ctx| {
+            ctx.set_shading(Shading::Grid(*style));
+            ctx.push_verts(&quad, &[0, 1, 2, 2, 3, 1]);
+        });
+    }
+
+    /// Turns sRGB-correct rendering on or off. When it's on, the target converts the linear
+    /// colors that are blended to sRGB when they're written (`GL_FRAMEBUFFER_SRGB`), and colors
+    /// given to the context are converted from sRGB to linear, so blending and gradients happen
+    /// in linear space. Textures should then be created with an sRGB internal format, e.g. with
+    /// `Texture::from_image_srgb`, so that they're converted as well.
+    ///
+    /// This is on from the start if the window was created with `WindowBuilder::srgb`. It only
+    /// has an effect on targets with an sRGB framebuffer, and not at all on contexts that can't
+    /// toggle sRGB conversion, like GL ES ones. Call `set_clear_color` again after changing it.
+    pub fn set_srgb(&mut self, enabled: bool) {
+        let enabled = enabled && self.gl_info.capabilities.srgb_framebuffer;
+        if self.linear_colors == enabled { return }
+        self.flush(FlushCause::ShaderChanged);
+        unsafe {
+            if enabled {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            } else {
+                gl::Disable(gl::FRAMEBUFFER_SRGB);
+            }
+        }
+        self.log_state_change(StateChange::Srgb(enabled));
+        self.linear_colors = enabled;
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.linear_colors
+    }
+
+    /// The number of samples per pixel of the current target. 0 if it isn't multisampled.
+    pub fn samples(&self) -> u32 {
+        let mut samples: gl::types::GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::SAMPLES, &mut samples);
+        }
+        samples.max(0) as u32
+    }
+
+    /// Sets how everything drawn from now on is combined with what's already been drawn.
+    pub fn set_blend_mode(&mut self, blend: BlendMode) {
+        if self.current_blend == blend { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::BlendChanged);
+        }
+        self.log_state_change(StateChange::Blend(blend));
+        self.current_blend = blend;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.current_blend
+    }
+
+    /// Redirects drawing into an offscreen layer the size of the display until the matching
+    /// `end_layer`, which composites the layer back with `opacity` and `blend`. Unlike
+    /// `push_opacity`, the layer's contents are faded as a group so overlapping parts don't
+    /// show through each other. Layers can be nested, and their surfaces are reused.
+    ///
+    /// If the layer's surface can't be created, e.g. when the GPU is out of memory, the error is
+    /// reported through `gl_debug` and the layer is drawn straight onto the current target with
+    /// `opacity` and `blend` applied to each primitive instead.
+    pub fn begin_layer(&mut self, opacity: f32, blend: BlendMode) {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let (width, height) = (viewport[2].max(1) as u32, viewport[3].max(1) as u32);
+        let surface = match self.layer_pool.iter().position(|s| s.width() == width && s.height() == height) {
+            Some(index) => self.layer_pool.swap_remove(index),
+            None => match Surface::new(width, height) {
+                Ok(surface) => surface,
+                Err(err) => {
+                    report_error(&format!("failed to create a layer surface, drawing the layer without one: {}", err));
+                    self.layers.push(Layer {
+                        surface: None,
+                        opacity: opacity.clamp(0.0, 1.0),
+                        blend,
+                        outer_opacity: self.opacity,
+                        outer_blend: self.current_blend,
+                    });
+                    self.opacity *= opacity.clamp(0.0, 1.0);
+                    self.set_blend_mode(blend);
+                    return;
+                },
+            },
+        };
+
+        // the surface has the size of the viewport in pixels, which on HiDPI displays is larger
+        // than the display size that everything is positioned in.
+        let display_size = self.display_size;
+        self.begin_target(&surface);
+        self.display_size = display_size;
+        self.update_projection();
+
+        unsafe {
+            let mut clear_color = [0.0; 4];
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+        }
+
+        self.layers.push(Layer {
+            surface: Some(surface),
+            opacity: opacity.clamp(0.0, 1.0),
+            blend,
+            outer_opacity: self.opacity,
+            outer_blend: self.current_blend,
+        });
+        self.opacity = 1.0;
+        self.set_blend_mode(BlendMode::Normal);
+    }
+
+    /// Composites the layer started by the matching `begin_layer` onto the target that was
+    /// active before it.
+    pub fn end_layer(&mut self) {
+        let layer = self.layers.pop().expect("end_layer called without a matching begin_layer");
+        let surface = match layer.surface {
+            Some(surface) => surface,
+            None => {
+                self.opacity = layer.outer_opacity;
+                self.set_blend_mode(layer.outer_blend);
+                return;
+            },
+        };
+        self.end_target();
+        self.opacity = layer.outer_opacity;
+
+        let (w, h) = self.display_size;
+        let texture = surface.texture();
+        let vcol = VertCol::new(1.0, 1.0, 1.0, layer.opacity);
+        self.set_blend_mode(layer.blend);
+        self.with_screen_transform(|ctx| {
+            ctx.set_texture(texture.handle(), SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge));
+            ctx.set_shading(Shading::for_texture(texture));
+            ctx.push_verts(
+                &[
+                    Vert::with_puc(VertPos::new(0.0, 0.0), VertUV::new(0.0, 0.0), vcol),
+                    Vert::with_puc(VertPos::new(w, 0.0), VertUV::new(1.0, 0.0), vcol),
+                    Vert::with_puc(VertPos::new(0.0, h), VertUV::new(0.0, 1.0), vcol),
+                    Vert::with_puc(VertPos::new(w, h), VertUV::new(1.0, 1.0), vcol),
+                ],
+                &[0, 1, 2, 2, 3, 1],
+            );
+        });
+        // the surface goes back into the pool, so it has to be drawn before it's reused.
+        self.flush(FlushCause::TargetChanged);
+        self.set_blend_mode(layer.outer_blend);
+        self.layer_pool.push(surface);
+    }
+
+    fn set_shading(&mut self, shading: Shading) {
+        if self.current_shading == shading { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::ShaderChanged);
+        }
+        self.log_state_change(match shading {
+            Shading::Default => StateChange::Shader("default"),
+            Shading::Premultiplied => StateChange::Shader("premultiplied"),
+            Shading::Marker(m) => StateChange::Marker(m.shape),
+            Shading::Grid(_) => StateChange::Shader("grid"),
+            Shading::Palette(_) => StateChange::Shader("palette"),
+            Shading::Instanced => StateChange::Shader("instanced"),
+            Shading::Effect(index, _) => StateChange::Effect(index),
+        });
+        self.current_shading = shading;
+    }
+
+    /// Sets the filter used to draw textures that don't have their own filter set with
+    /// `Texture::set_filter`.
+    pub fn set_default_texture_filter(&mut self, filter: TextureFilter) {
+        self.default_filter = filter;
+    }
+
+    pub fn default_texture_filter(&self) -> TextureFilter {
+        self.default_filter
+    }
+
+    /// Sets the color that images drawn with `draw_image` and its variants, `fill_rect_tiled`
+    /// and `draw_nine_patch` are multiplied with. This is white by default. The tint is stored
+    /// in the vertices so changing it never causes a flush.
+    pub fn set_image_tint(&mut self, tint: Color) {
+        self.image_tint = tint;
+    }
+
+    pub fn image_tint(&self) -> Color {
+        self.image_tint
+    }
+
+    /// Multiplies the alpha of everything drawn until the matching `pop_opacity` by `opacity`,
+    /// on top of any opacity that was already pushed. Fades whole widgets or scenes without
+    /// touching their colors. Like the image tint, this is applied to the vertices so it never
+    /// causes a flush.
+    pub fn push_opacity(&mut self, opacity: f32) {
+        self.opacity_stack.push(self.opacity);
+        self.opacity *= opacity.clamp(0.0, 1.0);
+    }
+
+    /// Goes back to the opacity that was current before the matching `push_opacity`.
+    pub fn pop_opacity(&mut self) {
+        self.opacity = self.opacity_stack.pop().expect("pop_opacity called without a matching push_opacity");
+    }
+
+    /// The product of all pushed opacities.
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Draws with the image tint set to `tint` and restores the previous tint afterwards.
+    pub fn with_image_tint<F: FnOnce(&mut Context)>(&mut self, tint: Color, draw: F) {
+        let saved = self.image_tint;
+        self.image_tint = tint;
+        draw(self);
+        self.image_tint = saved;
+    }
+
+    fn sampler_for(&self, texture: &Texture, wrap: TextureWrap) -> SamplerParams {
+        SamplerParams::new(texture.filter().unwrap_or(self.default_filter), wrap)
+            .with_mipmaps(texture.has_mipmaps())
+    }
+
+    fn set_texture(&mut self, texture: gl::types::GLuint, sampler: SamplerParams) {
+        if self.current_texture == texture && self.current_sampler == sampler { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::TextureChanged);
+        }
+        self.log_state_change(StateChange::Texture(texture, sampler));
+        self.current_texture = texture;
+        self.current_sampler = sampler;
+    }
+
+    pub fn set_display_size(&mut self, width: f32, height: f32) {
+        self.display_size = (width, height);
+        self.update_projection();
+        self.update_scissor();
+    }
+
+    pub fn display_size(&self) -> (f32, f32) {
+        self.display_size
+    }
+
+    /// Changes how canvas coordinates are projected onto the display. Either way a point at
+    /// `(x, y)` on an untilted canvas ends up at pixel `(x, y)` of the display.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.update_projection();
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Flushes pending geometry and reads back the RGBA8 pixels of the current render target
+    /// (the window or the surface passed to `begin_target`) inside of `rect`, top row first.
+    /// `rect` is in display coordinates; if the framebuffer has more pixels than the display
+    /// size (HiDPI) the region is scaled so that every framebuffer pixel is returned. The region
+    /// is clamped to the target.
+    pub fn read_pixels(&mut self, rect: Rect) -> Image {
+        self.flush(FlushCause::Readback);
+
+        let (x, y, width, height) = self.readback_region(rect);
+        let mut pixels = read_pixels_rgba(x, y, width, height);
+        if !self.flip_y {
+            flip_rows_rgba(&mut pixels, width);
+        }
+        Image { width, height, pixels }
+    }
+
+    /// Starts reading the pixels of `rect` like `read_pixels`, but into a pixel buffer object
+    /// on the GPU, so this returns without waiting for the frame to finish rendering. Keep the
+    /// readback around for a frame or two and take the image once `is_ready` says so, e.g. for
+    /// screenshots or recording video without stalling.
+    pub fn read_pixels_async(&mut self, rect: Rect) -> PendingReadback {
+        self.flush(FlushCause::Readback);
+
+        let (x, y, width, height) = self.readback_region(rect);
+        let buffer = Buffer::new(BufferType::PixelPackBuffer);
+        read_pixels_rgba_into(&buffer, x, y, width, height);
+        let fence = if self.gl_info.capabilities.sync_objects { Some(Fence::insert()) } else { None };
+        PendingReadback::new(buffer, fence, width, height, !self.flip_y)
+    }
+
+    /// The framebuffer region, bottom row first like GL expects, that a readback of `rect` in
+    /// display coordinates covers.
+    fn readback_region(&self, rect: Rect) -> (i32, i32, u32, u32) {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let (vp_w, vp_h) = (viewport[2].max(0) as f32, viewport[3].max(0) as f32);
+        let (display_w, display_h) = self.display_size;
+        let (sx, sy) = (vp_w / display_w.max(1.0), vp_h / display_h.max(1.0));
+
+        let left = (rect.x * sx).round().clamp(0.0, vp_w);
+        let right = (rect.right() * sx).round().clamp(0.0, vp_w);
+        let top = (rect.y * sy).round().clamp(0.0, vp_h);
+        let bottom = (rect.bottom() * sy).round().clamp(0.0, vp_h);
+        let (width, height) = ((right - left) as u32, (bottom - top) as u32);
+
+        if self.flip_y {
+            // surfaces already store the top row first.
+            (viewport[0] + left as i32, viewport[1] + top as i32, width, height)
+        } else {
+            (viewport[0] + left as i32, viewport[1] + (vp_h - bottom) as i32, width, height)
+        }
+    }
+
+    /// Clips everything drawn from now on to a rounded rectangle in display coordinates (ignoring
+    /// the current transform). The clip is evaluated per fragment in the shaders, so it's
+    /// anti-aliased and changing it never touches any GL state other than uniforms.
+    pub fn clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        self.set_rounded_clip(Some((rect, radius.max(0.0))));
+    }
+
+    /// Removes the clip set by `clip_rounded_rect`.
+    pub fn clear_rounded_clip(&mut self) {
+        self.set_rounded_clip(None);
+    }
+
+    fn set_rounded_clip(&mut self, clip: Option<(Rect, f32)>) {
+        if self.rounded_clip == clip { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::ClipChanged);
+        }
+        self.log_state_change(StateChange::RoundedClip(clip));
+        self.rounded_clip = clip;
+    }
+
+    /// Clips everything drawn from now on to `rect` in display coordinates (ignoring the current
+    /// transform) until the matching `pop_clip_rect`. Nested clip rects are intersected with
+    /// the ones they're pushed inside of. Unlike `clip_rounded_rect` this uses the scissor test,
+    /// so the edges are hard but clipped fragments aren't shaded at all.
+    pub fn push_clip_rect(&mut self, rect: Rect) {
+        let clip = match self.clip_stack.last() {
+            Some(top) => top.intersection(&rect),
+            None => rect,
+        };
+        self.clip_stack.push(clip);
+        self.update_scissor();
+    }
+
+    /// Goes back to the clip rect that was active before the matching `push_clip_rect`.
+    pub fn pop_clip_rect(&mut self) {
+        self.clip_stack.pop().expect("pop_clip_rect called without a matching push_clip_rect");
+        self.update_scissor();
+    }
+
+    fn update_scissor(&mut self) {
+        let scissor = self.clip_stack.last().map(|&clip| self.scissor_for(clip));
+        if self.scissor == scissor { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::ClipChanged);
+        }
+        self.log_state_change(StateChange::Scissor(scissor));
+        self.scissor = scissor;
+    }
+
+    /// Converts a rect in display coordinates to framebuffer pixels in the current viewport,
+    /// which may be larger than the display size on HiDPI displays.
+    fn scissor_for(&self, clip: Rect) -> [gl::types::GLint; 4] {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let (display_w, display_h) = self.display_size;
+        let (sx, sy) = (viewport[2] as f32 / display_w.max(1.0), viewport[3] as f32 / display_h.max(1.0));
+
+        let left = (clip.x * sx).round() as i32;
+        let right = (clip.right() * sx).round() as i32;
+        let top = (clip.y * sy).round() as i32;
+        let bottom = (clip.bottom() * sy).round() as i32;
+        // surfaces are drawn upside down, so their first row is the top of the canvas.
+        let y = if self.flip_y { top } else { viewport[3] - bottom };
+        [viewport[0] + left, viewport[1] + y, (right - left).max(0), (bottom - top).max(0)]
+    }
+
+    /// Starts drawing a clip mask. Until `end_clip_mask`, primitives aren't drawn to the
+    /// target but mark the area that everything drawn afterwards is clipped to. Only the
+    /// geometry of the primitives counts, their colors and transparency are ignored. Masks are
+    /// intersected with the masks that are already active.
+    ///
+    /// The target needs a stencil buffer. The window has one, and so does every `Surface`.
+    pub fn begin_clip_mask(&mut self) {
+        let level = match self.stencil {
+            StencilMode::Disabled | StencilMode::Clear => 0,
+            StencilMode::Test(level) => level,
+            StencilMode::Increment(_) | StencilMode::Decrement(_) => panic!("begin_clip_mask called while already drawing a clip mask"),
+        };
+        assert!(level < 255, "too many nested clip masks");
+        self.flush(FlushCause::ClipChanged);
+
+        if level == 0 {
+            // nothing is clipped yet so whatever is left in the stencil buffer is stale. While
+            // recording the clear has to happen in order with the recorded draws.
+            match self.recording {
+                Some(ref mut recorded) => recorded.push(Batch::clear_stencil()),
+                None => clear_stencil(),
+            }
+        }
+        self.clip_masks.push(Vec::new());
+        self.set_stencil(StencilMode::Increment(level));
+    }
+
+    /// Finishes the clip mask started by `begin_clip_mask`. Everything drawn until the matching
+    /// `pop_clip` is clipped to it.
+    pub fn end_clip_mask(&mut self) {
+        let level = match self.stencil {
+            StencilMode::Increment(level) => level,
+            _ => panic!("end_clip_mask called without a matching begin_clip_mask"),
+        };
+        self.flush(FlushCause::ClipChanged);
+        self.set_stencil(StencilMode::Test(level + 1));
+    }
+
+    /// Removes the clip mask that was added last, which erases it by drawing it again.
+    pub fn pop_clip(&mut self) {
+        let level = match self.stencil {
+            StencilMode::Test(level) => level,
+            _ => panic!("pop_clip called without a matching begin_clip_mask and end_clip_mask"),
+        };
+        let mask = self.clip_masks.pop().expect("pop_clip called without a matching begin_clip_mask");
+        self.flush(FlushCause::ClipChanged);
+
+        self.log_state_change(StateChange::EraseClipMask(level));
+        for mut batch in mask {
+            batch.state.stencil = StencilMode::Decrement(level);
+            if let Some(ref mut recorded) = self.recording {
+                recorded.push(batch);
+            } else {
+                self.submit(FlushCause::ClipChanged, &batch.state, &self.projection_matrix, &batch.vertices, &batch.elements);
+            }
+        }
+
+        self.set_stencil(if level > 1 { StencilMode::Test(level - 1) } else { StencilMode::Disabled });
+    }
+
+    fn set_stencil(&mut self, stencil: StencilMode) {
+        if self.stencil == stencil { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::ClipChanged);
+        }
+        self.log_state_change(match stencil {
+            StencilMode::Disabled => StateChange::Stencil("disabled", 0),
+            StencilMode::Clear => StateChange::Stencil("clear", 0),
+            StencilMode::Test(level) => StateChange::Stencil("test", level),
+            StencilMode::Increment(level) => StateChange::Stencil("increment", level),
+            StencilMode::Decrement(level) => StateChange::Stencil("decrement", level),
+        });
+        self.stencil = stencil;
+    }
+
+    /// Redirects drawing into `surface` until the matching call to `end_target`. The display size
+    /// is set to the size of the surface in the meantime and clip rects pushed before are
+    /// suspended. Targets can be nested.
+    pub fn begin_target(&mut self, surface: &Surface) {
+        self.flush(FlushCause::TargetChanged);
+
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        self.target_stack.push(TargetState {
+            framebuffer: Framebuffer::current_binding(),
+            viewport,
+            display_size: self.display_size,
+            flip_y: self.flip_y,
+            clip_stack: std::mem::take(&mut self.clip_stack),
+            clip_masks: std::mem::take(&mut self.clip_masks),
+            stencil: self.stencil,
+        });
+        self.set_stencil(StencilMode::Disabled);
+
+        surface.framebuffer().bind();
+        unsafe {
+            gl::Viewport(0, 0, surface.width() as _, surface.height() as _);
+        }
+        self.log_state_change(StateChange::TargetSurface(surface.framebuffer().handle()));
+
+        self.flip_y = true;
+        self.display_size = (surface.width() as f32, surface.height() as f32);
+        self.update_projection();
+        self.update_scissor();
+    }
+
+    /// Flushes everything that was drawn into the current target and goes back to drawing into
+    /// the target that was active before `begin_target`.
+    pub fn end_target(&mut self) {
+        let state = self.target_stack.pop().expect("end_target called without a matching begin_target");
+        self.flush(FlushCause::TargetChanged);
+
+        Framebuffer::bind_handle(state.framebuffer);
+        unsafe {
+            gl::Viewport(state.viewport[0], state.viewport[1], state.viewport[2], state.viewport[3]);
+        }
+        self.log_state_change(StateChange::TargetFramebuffer(state.framebuffer));
+
+        self.flip_y = state.flip_y;
+        self.display_size = state.display_size;
+        self.update_projection();
+        self.clip_stack = state.clip_stack;
+        self.update_scissor();
+        self.clip_masks = state.clip_masks;
+        self.set_stencil(state.stencil);
+    }
+
+    fn update_projection(&mut self) {
+        let (width, height) = self.display_size;
+        let mut projection_matrix = self.projection.matrix(width, height);
+        if self.flip_y {
+            projection_matrix = transform::merge(projection_matrix, transform::scale(1.0, -1.0));
+        }
+        if projection_matrix == self.projection_matrix { return }
+        if self.has_vertices() {
+            self.flush(FlushCause::TransformChanged);
+        }
+        self.projection_matrix = projection_matrix;
+    }
+
+    /// Fills the whole display with a vertical gradient. The current transform is ignored.
+    pub fn background_gradient(&mut self, top: Color, bottom: Color) {
+        let (w, h) = self.display_size;
+        let tcol = VertCol::new(top.r, top.g, top.b, top.a);
+        let bcol = VertCol::new(bottom.r, bottom.g, bottom.b, bottom.a);
+
+        self.with_screen_transform(|ctx| {
+            ctx.push_verts(
+                &[
+                    Vert::with_pc(VertPos::new(0.0, 0.0), tcol),
+                    Vert::with_pc(VertPos::new(w, 0.0), tcol),
+                    Vert::with_pc(VertPos::new(0.0, h), bcol),
+                    Vert::with_pc(VertPos::new(w, h), bcol),
+                ],
+                &[0, 1, 2, 2, 3, 1],
+            );
+        });
+    }
+
+    /// Fills the display with `scaling.bar_color` and draws `texture` over it at the largest
+    /// whole scale that fits, centered. Textures without their own filter are drawn with
+    /// nearest filtering so every source pixel stays sharp. The current transform and image
+    /// tint are ignored.
+    pub fn present_pixel_perfect(&mut self, texture: &Texture, scaling: &IntegerScaling) {
+        let (w, h) = self.display_size;
+        let dest = scaling.dest_rect(texture.get_width(), texture.get_height(), w, h);
+        let src = Rect::new(0.0, 0.0, texture.get_width() as f32, texture.get_height() as f32);
+
+        self.background_gradient(scaling.bar_color, scaling.bar_color);
+        let saved_filter = self.default_filter;
+        self.default_filter = TextureFilter::Nearest;
+        self.with_screen_transform(|ctx| {
+            ctx.with_image_tint(Color::WHITE, |ctx| ctx.draw_image_region(texture, src, dest));
+        });
+        self.default_filter = saved_filter;
+    }
+
+    /// Fills the whole display with a radial gradient that goes from `center` in the middle of the
+    /// display to `edge` in its corners. The current transform is ignored.
+    pub fn background_vignette(&mut self, center: Color, edge: Color) {
+        const SEGMENTS: u32 = 48;
 
-pub struct Context {
-    vertex_array: VertexArray,
-    vertex_buffer: Buffer,
-    elems_buffer: Buffer,
+        let (w, h) = self.display_size;
+        let (cx, cy) = (w / 2.0, h / 2.0);
+        let radius = (cx * cx + cy * cy).sqrt();
+        let ccol = VertCol::new(center.r, center.g, center.b, center.a);
+        let ecol = VertCol::new(edge.r, edge.g, edge.b, edge.a);
 
-    uniform_projmtx: gl::types::GLint,
-    uniform_transform: gl::types::GLint,
+        let mid = Vert::with_pc(VertPos::new(cx, cy), ccol);
+        let ring = |i: u32| {
+            let theta = (i as f32) * std::f32::consts::PI * 2.0 / (SEGMENTS as f32);
+            Vert::with_pc(VertPos::new(cx + radius * theta.cos(), cy + radius * theta.sin()), ecol)
+        };
 
-    program: Program,
-    shaders: (/* vertex shader */ Shader, /* fragment shader */ Shader),
+        self.with_screen_transform(|ctx| {
+            for i in 0..SEGMENTS {
+                ctx.push_verts(&[mid, ring(i), ring(i + 1)], &[0, 1, 2]);
+            }
+        });
+    }
 
-    vertices: Vec<Vert>,
-    elements: Vec<u32>,
+    /// Runs `draw` with the view transform reset so that primitives are positioned in display
+    /// coordinates, then restores the transform.
+    fn with_screen_transform<F: FnOnce(&mut Context)>(&mut self, draw: F) {
+        let saved = self.transform;
+        self.set_transform(ViewTransform::zero());
+        self.set_texture(self.white_texture.handle(), SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge));
+        self.set_shading(Shading::Default);
+        draw(self);
+        self.set_transform(saved);
+    }
 
-    /// Final transform applied to each vertex to convert from the screen's coordinates to
-    /// device coordinates.
-    ortho_matrix: Mat4f,
-    transform: ViewTransform,
-}
+    fn set_transform(&mut self, transform: ViewTransform) {
+        if self.has_vertices() {
+            self.flush(FlushCause::TransformChanged);
+        }
+        self.log_state_change(StateChange::Transform(transform));
+        self.transform = transform;
+    }
 
-impl Context {
-    pub fn new() -> Context {
-        let vertex_shader = Shader::compile(ShaderType::Vertex, VERTEX_SHADER).expect("failed to compile vertex shader");
-        let fragment_shader = Shader::compile(ShaderType::Fragment, FRAGMENT_SHADER).expect("failed to compile fragment shader");
-        let program = Program::link(&[&vertex_shader, &fragment_shader]).expect("failed to link GL program");
+    /// Replaces the built-in shaders with the files in `dir` that exist and reloads them
+    /// whenever `reload_shaders` finds that they changed, for iterating on shaders without
+    /// restarting. The files are `default.vert` and `default.frag`, which draw everything
+    /// without special shading, `marker.frag`, `grid.frag`, `palette.frag` and
+    /// `instanced.vert`; `export_shaders` writes the built-in ones as a starting point.
+    ///
+    /// Shaders should keep declaring the same inputs in the same order, since meshes that were
+    /// already created keep the attribute locations of the program they were created with.
+    /// Every shader gets `srgb_to_linear()` and its `LinearColors` uniform, and fragment shaders
+    /// also get `clip_coverage()` along with the clip uniforms and `FragScreenPos`, added after
+    /// their version line, so the files mustn't declare those themselves.
+    pub fn watch_shaders<P: Into<PathBuf>>(&mut self, dir: P) -> Result<(), ReloadError> {
+        let dir = dir.into();
+        self.shader_watcher.clear();
+        for (name, _) in SHADER_FILES.iter() {
+            self.shader_watcher.watch(dir.join(name));
+        }
+        self.shader_dir = Some(dir);
+        self.load_shaders()
+    }
 
-        let attrib_pos = program.attrib_location("Position\0");
-        let attrib_col = program.attrib_location("Color\0");
-        let uniform_projmtx = program.uniform_location("ProjMtx\0");
-        let uniform_transform = program.uniform_location("Transform\0");
+    /// Rebuilds the built-in pipelines if any of the files watched since `watch_shaders`
+    /// changed, and returns whether it did. Meant to be called once per frame. If a shader
+    /// doesn't compile the error is returned and the programs that were in use stay in use.
+    pub fn reload_shaders(&mut self) -> Result<bool, ReloadError> {
+        if self.shader_dir.is_none() || !self.shader_watcher.changed() { return Ok(false) }
+        self.load_shaders().map(|_| true)
+    }
 
-        let vertex_array = VertexArray::new();
-        let vertex_buffer = Buffer::new(BufferType::ArrayBuffer);
+    /// Writes the built-in shaders into `dir` with the file names `watch_shaders` looks for.
+    pub fn export_shaders<P: AsRef<Path>>(dir: P) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        for (name, source) in SHADER_FILES.iter() {
+            std::fs::write(dir.as_ref().join(name), source.trim_end_matches('\0'))?;
+        }
+        Ok(())
+    }
 
-        vertex_array.bind();
-        vertex_buffer.bind();
+    fn load_shaders(&mut self) -> Result<(), ReloadError> {
+        let dir = match self.shader_dir {
+            Some(ref dir) => dir.clone(),
+            None => return Ok(()),
+        };
+        let mut sources: Vec<Cow<str>> = Vec::with_capacity(SHADER_FILES.len());
+        for (name, builtin) in SHADER_FILES.iter() {
+            sources.push(read_optional(&dir.join(name))?.map(Cow::from).unwrap_or(Cow::from(*builtin)));
+        }
+        let sources = [&*sources[0], &*sources[1], &*sources[2], &*sources[3], &*sources[4], &*sources[5]];
+        let (pipeline, marker, grid, palette, instanced) = create_pipelines(&self.streams, &self.gl_info.capabilities, &sources)?;
 
-        unsafe {
-            let szfloat = std::mem::size_of::<f32>() as i32; gl::EnableVertexAttribArray(attrib_pos as _);
-            gl::VertexAttribPointer(attrib_pos as _, 2, gl::FLOAT, gl::FALSE, 6 * szfloat, std::mem::transmute(0usize));
-            gl::EnableVertexAttribArray(attrib_col as _);
-            gl::VertexAttribPointer(attrib_col as _, 4, gl::FLOAT, gl::FALSE, 6 * szfloat, std::mem::transmute(2 * szfloat as usize));
+        self.flush(FlushCause::ShaderChanged);
+        self.pipeline = pipeline;
+        self.marker_pipeline = marker;
+        self.grid_pipeline = grid;
+        self.palette_pipeline = palette;
+        self.instanced_pipeline = instanced;
+        self.log_state_change(StateChange::ShadersReloaded);
+        Ok(())
+    }
+
+    /// Draws everything that `draw` draws with default shading (rects, shapes, images, text
+    /// and so on) through the fragment shader of `effect`. Markers, grids and other special
+    /// shading are left alone. The effect is compiled the first time it's used, and an error
+    /// is returned without drawing anything if that fails.
+    ///
+    /// The effect's uniforms are set when this is called and what was drawn is flushed when it
+    /// returns. Batches that are recorded instead, e.g. by `draw_sorted` and `record`, are drawn
+    /// with whatever the uniforms were last set to.
+    pub fn with_effect<E, F>(&mut self, effect: &E, draw: F) -> Result<(), ShaderError>
+        where E: Effect + ?Sized, F: FnOnce(&mut Context)
+    {
+        let index = self.effect_index(effect.source())?;
+        if self.has_vertices() {
+            self.flush(FlushCause::ShaderChanged);
+        }
+        effect.set_uniforms(&self.effects[index as usize].1.program);
+        self.log_state_change(StateChange::Effect(index));
+
+        let previous = self.current_effect.replace(index);
+        draw(self);
+        if self.has_vertices() {
+            self.flush(FlushCause::ShaderChanged);
+        }
+        self.current_effect = previous;
+        Ok(())
+    }
+
+    /// The index of the pipeline of the effect with `source`, which is compiled if it's new.
+    fn effect_index(&mut self, source: &str) -> Result<u32, ShaderError> {
+        if let Some(index) = self.effects.iter().position(|(s, _)| s == source) {
+            return Ok(index as u32);
         }
+        let stream_buffers: Vec<(&Buffer, &Buffer)> = self.streams.iter().map(|s| (&s.vertices, &s.elements)).collect();
+        let pipeline = Pipeline::new(VERTEX_SHADER, &fragment_source(source), &stream_buffers, &self.gl_info.capabilities)?;
+        pipeline.set_label(&format!("canvas.effect{}", self.effects.len()));
+        pipeline.program.set_uniform("Opacity", 1.0);
+        self.effects.push((source.to_string(), pipeline));
+        Ok((self.effects.len() - 1) as u32)
+    }
 
-        check_gl_errors(|e| println!("GL Error: {}", e));
+    /// The GL implementation the context was created on.
+    pub fn gl_info(&self) -> &GlInfo {
+        &self.gl_info
+    }
 
-        Context {
-            vertex_array:   vertex_array,
-            vertex_buffer:  vertex_buffer,
-            elems_buffer:   Buffer::new(BufferType::ElementArrayBuffer),
-            uniform_projmtx: uniform_projmtx,
-            uniform_transform: uniform_transform,
+    /// Starts or stops logging state changes and draw calls, for finding out why a frame needs
+    /// more draw calls than expected. Off by default, since it costs time on every state change.
+    /// Stopping drops what was logged.
+    pub fn set_frame_log_enabled(&mut self, enabled: bool) {
+        let log = self.frame_log.get_mut();
+        if enabled != log.is_some() {
+            *log = if enabled { Some(FrameLog::new()) } else { None };
+        }
+    }
 
-            program:        program,
-            shaders:        (vertex_shader, fragment_shader),
+    /// Everything that was logged since the log was last dumped. None if logging is off.
+    pub fn frame_log(&mut self) -> Option<&FrameLog> {
+        self.frame_log.get_mut().as_ref()
+    }
 
-            vertices:       Vec::with_capacity(MAX_VERTS),
-            elements:       Vec::with_capacity(MAX_ELEMS),
+    /// Returns a human readable listing of the state changes and draw calls (and what caused
+    /// each of them) since the last call to this and then clears the log. Call this once per
+    /// frame after the final flush; the log only keeps `frame_log::MAX_ENTRIES` entries. Empty
+    /// if logging is off.
+    pub fn dump_frame_log(&mut self) -> String {
+        match self.frame_log.get_mut() {
+            Some(log) => {
+                let dump = log.to_string();
+                log.clear();
+                dump
+            },
+            None => String::new(),
+        }
+    }
 
-            ortho_matrix:   cgmath::ortho(-1.0, 1.0, -1.0, 1.0, 0.0, 1.0),
-            transform:      ViewTransform::zero(),
+    fn log_state_change(&mut self, change: StateChange) {
+        if let Some(log) = self.frame_log.get_mut() {
+            log.record_state_change(change);
         }
     }
 
-    pub fn push_verts(&mut self, verts: &[Vert], elems: &[u32]) {
-        if self.vertices.len() + verts.len() > MAX_VERTS || self.elements.len() + elems.len() > MAX_ELEMS{
-            self.flush_verts();
+    fn log_draw_call(&self, cause: FlushCause, vertices: usize, elements: usize) {
+        if let Some(log) = self.frame_log.borrow_mut().as_mut() {
+            log.record_draw_call(cause, vertices, elements);
         }
+    }
 
-        debug_assert!(elems.len() % 3 == 0, "number of elements must be a multiple of 3");
-        let elem_delta = self.vertices.len() as u32;
+    /// Set origin for affine transformations.
+    pub fn set_origin(&mut self, ox: f32, oy: f32) {
+        self.set_transform(self.transform.with_origin(vec2f(ox, oy)));
+    }
 
-        for v in verts.iter() {
-            // self.vertices.push(v.transform(&self.display_transform));
-            self.vertices.push(v.clone());
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.set_transform(self.transform.with_rotation(rotation));
+    }
+
+    pub fn set_rotation_deg(&mut self, rotation_deg: f32) {
+        self.set_transform(self.transform.with_rotation(deg2rad_h(rotation_deg)));
+    }
+
+    /// Scales everything drawn around the origin. Scaling happens before rotation.
+    pub fn set_scale(&mut self, scale_x: f32, scale_y: f32) {
+        self.set_transform(self.transform.with_scale(vec2f(scale_x, scale_y)));
+    }
+
+    /// Shears everything drawn around the origin, before it is scaled and rotated. See
+    /// `transform::skew`.
+    pub fn set_skew(&mut self, skew_x: f32, skew_y: f32) {
+        self.set_transform(self.transform.with_skew(vec2f(skew_x, skew_y)));
+    }
+
+    /// Mirrors everything drawn horizontally around the vertical line at `x = around` in canvas
+    /// coordinates. Flipping again undoes it. Use it between `push_transform` and
+    /// `pop_transform` to mirror part of the scene, e.g. for reflections or right-to-left
+    /// layouts.
+    pub fn flip_x(&mut self, around: f32) {
+        let local = transform::merge(transform::flip_x(around), self.transform.local);
+        self.set_transform(self.transform.with_local(local));
+    }
+
+    /// Mirrors everything drawn vertically around the horizontal line at `y = around` in
+    /// canvas coordinates. Flipping again undoes it.
+    pub fn flip_y(&mut self, around: f32) {
+        let local = transform::merge(transform::flip_y(around), self.transform.local);
+        self.set_transform(self.transform.with_local(local));
+    }
+
+    /// Moves everything drawn by `(dx, dy)` after it has been scaled and rotated, which pans the
+    /// view.
+    pub fn set_translation(&mut self, dx: f32, dy: f32) {
+        self.set_transform(self.transform.with_translation(vec2f(dx, dy)));
+    }
+
+    /// Adds `(dx, dy)` to the current translation.
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        let t = self.transform.translation;
+        self.set_translation(t.x + dx, t.y + dy);
+    }
+
+    /// Sets the rotation and scale around the origin together, which only flushes once.
+    pub fn set_rotation_and_scale(&mut self, rotation: f32, scale_x: f32, scale_y: f32) {
+        self.set_transform(self.transform.with_rotation(rotation).with_scale(vec2f(scale_x, scale_y)));
+    }
+
+    /// Replaces the current transform with `matrix`, which maps canvas coordinates to display
+    /// coordinates. Anything set with `set_origin`, `set_rotation`, `set_scale` etc. is reset,
+    /// though setting those afterwards applies them on top of `matrix`. Transforms pushed with
+    /// `push_transform` still apply after it.
+    pub fn set_transform_matrix(&mut self, matrix: Mat4f) {
+        let base = self.transform.base;
+        self.set_transform(ViewTransform::zero().with_base(base).with_local(matrix));
+    }
+
+    /// The current transform. Its matrix includes any transforms pushed with `push_transform`.
+    pub fn view_transform(&self) -> ViewTransform {
+        self.transform
+    }
+
+    /// Replaces the current transform, e.g. with one interpolated with `ViewTransform::lerp`.
+    /// Transforms pushed with `push_transform` still apply after it.
+    pub fn set_view_transform(&mut self, transform: ViewTransform) {
+        let base = self.transform.base;
+        self.set_transform(transform.with_base(base));
+    }
+
+    /// The matrix that currently maps canvas coordinates to display coordinates, including
+    /// every pushed transform.
+    pub fn transform_matrix(&self) -> Mat4f {
+        self.transform.matrix()
+    }
+
+    /// Where a point in canvas coordinates ends up on the display, in display coordinates.
+    /// Takes the current transform and projection into account.
+    pub fn canvas_to_screen(&self, point: Vec2f) -> Vec2f {
+        let (w, h) = self.display_size;
+        let device = self.canvas_to_device() * vec4f(point.x, point.y, 0.0, 1.0);
+        let (nx, ny) = (device.x / device.w, device.y / device.w);
+        vec2f((nx + 1.0) * 0.5 * w, (1.0 - ny) * 0.5 * h)
+    }
+
+    /// The point in canvas coordinates that is drawn at a point in display coordinates, e.g.
+    /// the canvas point under the mouse cursor. Returns `None` if nothing is drawn there
+    /// because the current transform collapses the canvas (like a zero scale).
+    pub fn screen_to_canvas(&self, point: Vec2f) -> Option<Vec2f> {
+        let (w, h) = self.display_size;
+        let (nx, ny) = (point.x / w * 2.0 - 1.0, 1.0 - point.y / h * 2.0);
+
+        // canvas points all have z = 0, so the mapping to the device's xy plane is a 2D
+        // homography made of the x, y and w rows and columns of the full matrix. Inverting it
+        // works for perspective projections too.
+        let m = self.canvas_to_device();
+        let homography = Mat3f::new(
+            m.x.x, m.x.y, m.x.w,
+            m.y.x, m.y.y, m.y.w,
+            m.w.x, m.w.y, m.w.w,
+        );
+        let canvas = homography.invert()? * vec3f(nx, ny, 1.0);
+        if canvas.z.abs() <= f32::EPSILON { return None }
+        Some(vec2f(canvas.x / canvas.z, canvas.y / canvas.z))
+    }
+
+    /// Maps canvas coordinates to device coordinates as if drawing to the display, ignoring the
+    /// flip used for surfaces.
+    fn canvas_to_device(&self) -> Mat4f {
+        let (w, h) = self.display_size;
+        transform::merge(self.transform.matrix(), self.projection.matrix(w, h))
+    }
+
+    /// Saves the current transform and starts a new one on top of it. Origins, rotations and
+    /// so on that are set until the matching `pop_transform` are relative to the saved
+    /// transform, so nested content can be transformed along with its parent. Pushing doesn't
+    /// change what is drawn and never causes a flush.
+    pub fn push_transform(&mut self) {
+        self.transform_stack.push(self.transform);
+        self.transform = ViewTransform::zero().with_base(self.transform.matrix());
+    }
+
+    /// Goes back to the transform saved by the matching `push_transform`.
+    pub fn pop_transform(&mut self) {
+        let transform = self.transform_stack.pop().expect("pop_transform called without a matching push_transform");
+        self.set_transform(transform);
+    }
+
+    /// Runs `draw` between `push_transform` and `pop_transform`.
+    pub fn with_transform<F: FnOnce(&mut Context)>(&mut self, draw: F) {
+        self.push_transform();
+        draw(self);
+        self.pop_transform();
+    }
+
+    /// Saves the transform, clips, blend mode, opacity and image tint so the matching `restore`
+    /// can go back to them, like `save` on an HTML canvas. Transforms, clip rects, clip masks
+    /// and opacities that are pushed in between don't have to be popped, `restore` drops them.
+    /// Saves can be nested.
+    pub fn save(&mut self) {
+        self.saved_states.push(SavedState {
+            transform:      self.transform,
+            transform_depth: self.transform_stack.len(),
+            rounded_clip:   self.rounded_clip,
+            clip_depth:     self.clip_stack.len(),
+            mask_depth:     self.clip_masks.len(),
+            blend:          self.current_blend,
+            opacity:        self.opacity,
+            opacity_depth:  self.opacity_stack.len(),
+            image_tint:     self.image_tint,
+        });
+    }
+
+    /// Goes back to the state saved by the matching `save`.
+    pub fn restore(&mut self) {
+        let saved = self.saved_states.pop().expect("restore called without a matching save");
+        assert!(!self.stencil.writes_stencil(), "restore called while drawing a clip mask");
+
+        while self.clip_masks.len() > saved.mask_depth {
+            self.pop_clip();
         }
+        self.clip_stack.truncate(saved.clip_depth);
+        self.update_scissor();
+        self.set_rounded_clip(saved.rounded_clip);
 
-        for e in elems.iter() {
-            debug_assert!(*e < verts.len() as u32, "element is larger than the number of vertices provided");
-            self.elements.push((*e) + elem_delta);
+        self.transform_stack.truncate(saved.transform_depth);
+        if self.transform != saved.transform {
+            self.set_transform(saved.transform);
         }
+
+        self.set_blend_mode(saved.blend);
+        self.opacity_stack.truncate(saved.opacity_depth);
+        self.opacity = saved.opacity;
+        self.image_tint = saved.image_tint;
     }
 
-    pub fn flush_verts(&mut self) {
-        if self.vertices.len() == 0 { return }
+    /// Runs `draw` between `save` and `restore`.
+    pub fn with_saved_state<F: FnOnce(&mut Context)>(&mut self, draw: F) {
+        self.save();
+        draw(self);
+        self.restore();
+    }
+}
 
-        self.program.bind();
+impl Default for Context {
+    fn default() -> Context {
+        Context::new()
+    }
+}
 
-        unsafe {
-            gl::UniformMatrix4fv(self.uniform_projmtx, 1, gl::FALSE, self.ortho_matrix.as_ptr());
-            let transform_mtx = self.transform.matrix();
-            gl::UniformMatrix4fv(self.uniform_transform, 1, gl::FALSE, transform_mtx.as_ptr());
+impl Drop for Context {
+    fn drop(&mut self) {
+        // reports a context that outlived its window. Its GL objects are leaked rather than
+        // deleted in that case, which is harmless since the driver freed them already. With
+        // several windows, the context's window should be made current before dropping it, or
+        // its vertex arrays are leaked.
+        if let Some(ref headless) = self.headless {
+            headless.make_current();
         }
+        self.is_gl_context_alive();
+    }
+}
 
-        self.vertex_array.bind();
-        self.vertex_buffer.bind();
-        self.elems_buffer.bind();
-        self.vertex_buffer.set_data(&self.vertices, BufferUsage::StreamDraw);
-        self.elems_buffer.set_data(&self.elements, BufferUsage::StreamDraw);
+/// How the canvas is projected onto the display.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// A flat orthographic projection. This is the default with `near = -1` and `far = 1`.
+    Ortho {
+        near: f32,
+        far:  f32,
+    },
 
-        unsafe {
-            gl::DrawElements(gl::TRIANGLES, self.elements.len() as _, gl::UNSIGNED_INT, std::mem::transmute(0usize));
+    /// Views the canvas through a perspective camera after tilting it about its center by
+    /// `tilt_x` (around the horizontal axis) and `tilt_y` (around the vertical axis) radians.
+    /// `fov_y` is the vertical field of view in radians. Useful for card flips and other 2.5D
+    /// transitions.
+    Perspective {
+        fov_y:  f32,
+        tilt_x: f32,
+        tilt_y: f32,
+        near:   f32,
+        far:    f32,
+    },
+}
+
+impl Projection {
+    pub fn perspective(fov_y: f32, tilt_x: f32, tilt_y: f32) -> Projection {
+        Projection::Perspective { fov_y, tilt_x, tilt_y, near: 1.0, far: 10000.0 }
+    }
+
+    /// The matrix that maps canvas coordinates to device coordinates for a display of the given
+    /// size.
+    pub fn matrix(&self, width: f32, height: f32) -> Mat4f {
+        match *self {
+            Projection::Ortho { near, far } => {
+                cgmath::ortho(0.0, width, height, 0.0, near, far)
+            },
+
+            Projection::Perspective { fov_y, tilt_x, tilt_y, near, far } => {
+                let (cx, cy) = (width / 2.0, height / 2.0);
+                let distance = cy / (fov_y / 2.0).tan();
+
+                // move the canvas' center to the origin with y pointing up, tilt it, and then
+                // push it away from the camera just far enough for it to fill the display.
+                let to_center = transform::merge(transform::translate(-cx, -cy), transform::scale(1.0, -1.0));
+                let tilt = Mat4f::from_angle_x(radf(tilt_x)) * Mat4f::from_angle_y(radf(tilt_y));
+                let away = Mat4f::from_translation(vec3f(0.0, 0.0, -distance));
+                let perspective = cgmath::perspective(radf(fov_y), width / height.max(1.0), near, far);
+
+                transform::merge_all(&[to_center, tilt, away, perspective])
+            },
         }
+    }
+}
 
-        self.vertices.clear();
-        self.elements.clear();
+impl Default for Projection {
+    fn default() -> Projection {
+        Projection::Ortho { near: -1.0, far: 1.0 }
     }
+}
 
-    #[inline]
-    pub fn has_vertices(&self) -> bool {
-        self.vertices.len() > 0
+/// How `Context::draw_sorted` orders what's drawn inside of it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BatchOrder {
+    /// By z index. Primitives with the same z index are drawn in the order they were drawn in.
+    ZIndex,
+    /// By z index, then by texture and then by blend mode, so that switching back and forth
+    /// between textures doesn't cost a draw call each time. Primitives with the same z index
+    /// can be drawn in any order, so they shouldn't overlap.
+    ZIndexAndState,
+}
+
+/// The corners of a rectangle whose top left corner is at `(x, y)`, in the order that
+/// `UNIT_QUAD_ELEMS` expects.
+pub(crate) fn rect_verts(color: Color, x: f32, y: f32, width: f32, height: f32) -> [Vert; 4] {
+    let vcol = VertCol::new(color.r, color.g, color.b, color.a);
+    [
+        Vert::with_pc(VertPos::new(x, y), vcol),
+        Vert::with_pc(VertPos::new(x + width, y), vcol),
+        Vert::with_pc(VertPos::new(x, y + height), vcol),
+        Vert::with_pc(VertPos::new(x + width, y + height), vcol),
+    ]
+}
+
+/// The corners of a quad that maps `src` of `texture` onto `dest`, flipped and rotated by
+/// `sprite` and tinted with `tint`, in the order that `UNIT_QUAD_ELEMS` expects.
+pub(crate) fn image_quad_verts(texture: &Texture, src: Rect, dest: Rect, sprite: &SpriteTransform, tint: Color) -> [Vert; 4] {
+    let (tw, th) = (texture.get_width() as f32, texture.get_height() as f32);
+    let (mut u0, mut v0) = (src.x / tw, src.y / th);
+    let (mut u1, mut v1) = (src.right() / tw, src.bottom() / th);
+    if sprite.flip_x { std::mem::swap(&mut u0, &mut u1) }
+    if sprite.flip_y { std::mem::swap(&mut v0, &mut v1) }
+
+    let pivot = vec2f(dest.x + sprite.pivot.x * dest.width, dest.y + sprite.pivot.y * dest.height);
+    let (sin, cos) = sprite.rotation.sin_cos();
+    let pos = |x: f32, y: f32| {
+        let (dx, dy) = (x - pivot.x, y - pivot.y);
+        VertPos::new(pivot.x + dx * cos - dy * sin, pivot.y + dx * sin + dy * cos)
+    };
+
+    let vcol = VertCol::new(tint.r, tint.g, tint.b, tint.a);
+    [
+        Vert::with_puc(pos(dest.x, dest.y), VertUV::new(u0, v0), vcol),
+        Vert::with_puc(pos(dest.right(), dest.y), VertUV::new(u1, v0), vcol),
+        Vert::with_puc(pos(dest.x, dest.bottom()), VertUV::new(u0, v1), vcol),
+        Vert::with_puc(pos(dest.right(), dest.bottom()), VertUV::new(u1, v1), vcol),
+    ]
+}
+
+/// Everything that's needed to draw a batch of vertices.
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) struct BatchState {
+    /// None for the white texture, which batches built without a context don't know.
+    pub texture:        Option<TextureId>,
+    pub sampler:        SamplerParams,
+    pub shading:        Shading,
+    pub blend:          BlendMode,
+    pub linear_colors:  bool,
+    pub transform:      Mat4f,
+    pub rounded_clip:   Option<(Rect, f32)>,
+    pub scissor:        Option<[gl::types::GLint; 4]>,
+    pub stencil:        StencilMode,
+    /// Only used to sort the batches recorded by `Context::draw_sorted`.
+    pub z_index:        i32,
+}
+
+impl BatchState {
+    /// Untextured, unclipped and untransformed.
+    pub fn new() -> BatchState {
+        BatchState {
+            texture: None,
+            sampler: SamplerParams::new(TextureFilter::Nearest, TextureWrap::ClampToEdge),
+            shading: Shading::Default,
+            blend: BlendMode::Normal,
+            linear_colors: false,
+            transform: transform::identity(),
+            rounded_clip: None,
+            scissor: None,
+            stencil: StencilMode::Disabled,
+            z_index: 0,
+        }
     }
 
-    pub fn set_clear_color(&self, color: Color) {
-        unsafe {
-            gl::ClearColor(color.r, color.g, color.b, color.a);
+    /// The state that a batch recorded with this state is drawn with when it's replayed while
+    /// `outer` is current.
+    fn nested_in(&self, outer: &BatchState) -> BatchState {
+        let scissor = match (self.scissor, outer.scissor) {
+            (Some([x0, y0, w0, h0]), Some([x1, y1, w1, h1])) => {
+                let (x, y) = (x0.max(x1), y0.max(y1));
+                Some([x, y, ((x0 + w0).min(x1 + w1) - x).max(0), ((y0 + h0).min(y1 + h1) - y).max(0)])
+            },
+            (scissor, None) | (None, scissor) => scissor,
+        };
+
+        BatchState {
+            transform: transform::merge(self.transform, outer.transform),
+            rounded_clip: self.rounded_clip.or(outer.rounded_clip),
+            scissor,
+            stencil: self.stencil.nested_in(outer.stencil),
+            z_index: self.z_index.saturating_add(outer.z_index),
+            // colors are converted for the target they're drawn into.
+            linear_colors: outer.linear_colors,
+            ..*self
         }
     }
+}
 
-    pub fn clear(&self) {
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+/// A batch that was recorded instead of drawn.
+#[derive(Clone)]
+pub(crate) struct Batch {
+    pub state:      BatchState,
+    pub vertices:   Vec<Vert>,
+    pub elements:   Vec<u32>,
+}
+
+/// Stable sorts batches in the given order and joins neighbours that can be drawn together.
+/// Clip masks have to be drawn in between the same batches as before, so batches are only
+/// sorted between the batches that write to the stencil buffer.
+fn sort_batches(batches: Vec<Batch>, order: BatchOrder, max_verts: usize, max_elems: usize) -> Vec<Batch> {
+    let mut sorted: Vec<Batch> = Vec::with_capacity(batches.len());
+    let mut segment: Vec<Batch> = Vec::new();
+    for batch in batches.into_iter().chain(std::iter::once(Batch::barrier())) {
+        if !batch.state.stencil.writes_stencil() {
+            segment.push(batch);
+            continue;
+        }
+        match order {
+            BatchOrder::ZIndex => segment.sort_by_key(|b| b.state.z_index),
+            BatchOrder::ZIndexAndState => segment.sort_by_key(|b| (b.state.z_index, b.state.texture, b.state.blend as u8)),
+        }
+        for batch in segment.drain(..) {
+            match sorted.last_mut() {
+                Some(last) if last.can_join(&batch, max_verts, max_elems) => {
+                    let delta = last.vertices.len() as u32;
+                    last.vertices.extend_from_slice(&batch.vertices);
+                    last.elements.extend(batch.elements.iter().map(|e| e + delta));
+                },
+                _ => sorted.push(batch),
+            }
+        }
+        if !batch.vertices.is_empty() || batch.state.stencil == StencilMode::Clear {
+            sorted.push(batch);
         }
     }
+    sorted
+}
 
-    pub fn rect<P: Into<f32>, S: Into<f32>>(&mut self, color: Color, x: P, y: P, width: S, height: S) {
-        let (x, y, w, h) = (x.into(), y.into(), width.into(), height.into());
-
-        let vcol = VertCol::new(color.r, color.g, color.b, color.a);
-        let tl = Vert::with_pc(VertPos::new(x, y), vcol);
-        let tr = Vert::with_pc(VertPos::new(x + w, y), vcol);
-        let bl = Vert::with_pc(VertPos::new(x, y - h), vcol);
-        let br = Vert::with_pc(VertPos::new(x + w, y - h), vcol);
-
-        // let tl_t = tl.transform(&self.display_transform);
-        // let tr_t = tr.transform(&self.display_transform);
-        // let bl_t = bl.transform(&self.display_transform);
-        // let br_t = br.transform(&self.display_transform);
-        // unsafe {
-        //     println!("   TOP  LEFT: {}, {} -> {}, {}", tl.pos.x, tl.pos.y, tl_t.pos.x, tl_t.pos.y);
-        //     println!("   TOP RIGHT: {}, {} -> {}, {}", tr.pos.x, tr.pos.y, tr_t.pos.x, tr_t.pos.y);
-        //     println!("BOTTOM  LEFT: {}, {} -> {}, {}", bl.pos.x, bl.pos.y, bl_t.pos.x, bl_t.pos.y);
-        //     println!("BOTTOM RIGHT: {}, {} -> {}, {}", br.pos.x, br.pos.y, br_t.pos.x, br_t.pos.y);
-        // }
-        // println!();
+/// Clears the whole stencil buffer, which removes every clip mask.
+fn clear_stencil() {
+    unsafe {
+        gl::Disable(gl::SCISSOR_TEST);
+        gl::StencilMask(0xFF);
+        gl::ClearStencil(0);
+        gl::Clear(gl::STENCIL_BUFFER_BIT);
+    }
+}
 
-        self.push_verts(
-            &[tl, tr, bl, br],
-            &[0, 1, 2, 2, 3, 1],
-        );
+impl Batch {
+    /// A batch without vertices that clears the stencil buffer when it's drawn, which is
+    /// recorded before the first clip mask.
+    fn clear_stencil() -> Batch {
+        Batch {
+            state: BatchState {
+                stencil: StencilMode::Clear,
+                ..BatchState::new()
+            },
+            vertices: Vec::new(),
+            elements: Vec::new(),
+        }
     }
 
-    pub fn set_display_size(&mut self, width: f32, height: f32) {
-        self.ortho_matrix = cgmath::ortho(0.0, width, height, 0.0, -1.0, 1.0);
+    /// An empty batch that ends the last segment in `sort_batches`.
+    fn barrier() -> Batch {
+        Batch {
+            state: BatchState {
+                stencil: StencilMode::Increment(0),
+                ..BatchState::new()
+            },
+            vertices: Vec::new(),
+            elements: Vec::new(),
+        }
     }
 
-    fn set_transform(&mut self, transform: ViewTransform) {
-        if self.has_vertices() {
-            self.flush_verts();
+    fn can_join(&self, other: &Batch, max_verts: usize, max_elems: usize) -> bool {
+        let (a, b) = (&self.state, &other.state);
+        a.texture == b.texture && a.sampler == b.sampler && a.shading == b.shading && a.blend == b.blend
+            && a.linear_colors == b.linear_colors && a.transform == b.transform && a.rounded_clip == b.rounded_clip
+            && a.scissor == b.scissor && a.stencil == b.stencil
+            && self.vertices.len() + other.vertices.len() <= max_verts
+            && self.elements.len() + other.elements.len() <= max_elems
+    }
+}
+
+/// Builds the default, marker, grid, palette and instanced pipelines from shader sources in the
+/// order of `SHADER_FILES`.
+fn create_pipelines(streams: &[StreamBuffers], caps: &GlCapabilities, sources: &[&str; 6]) -> Result<(Pipeline, Pipeline, Pipeline, Pipeline, Pipeline), ShaderError> {
+    let [vertex, fragment, marker_fragment, grid_fragment, palette_fragment, instanced_vertex] = *sources;
+    let stream_buffers: Vec<(&Buffer, &Buffer)> = streams.iter().map(|s| (&s.vertices, &s.elements)).collect();
+    let pipeline = Pipeline::new(vertex, fragment, &stream_buffers, caps)?;
+    let marker_pipeline = Pipeline::new(vertex, marker_fragment, &stream_buffers, caps)?;
+    let grid_pipeline = Pipeline::new(vertex, grid_fragment, &stream_buffers, caps)?;
+    let palette_pipeline = Pipeline::new(vertex, palette_fragment, &stream_buffers, caps)?;
+    let instanced_pipeline = Pipeline::new(instanced_vertex, fragment, &stream_buffers, caps)?;
+    pipeline.set_label("canvas.default");
+    marker_pipeline.set_label("canvas.marker");
+    grid_pipeline.set_label("canvas.grid");
+    palette_pipeline.set_label("canvas.palette");
+    instanced_pipeline.set_label("canvas.instanced");
+    // GLSL ES doesn't allow uniform initializers, so meshes' opacity starts out at 0.
+    pipeline.program.set_uniform("Opacity", 1.0);
+    instanced_pipeline.program.set_uniform("Opacity", 1.0);
+    if caps.instancing {
+        for (index, stream) in streams.iter().enumerate() {
+            bind_instance_attributes(&instanced_pipeline, index, &stream.instances);
         }
-        self.transform = transform;
     }
+    Ok((pipeline, marker_pipeline, grid_pipeline, palette_pipeline, instanced_pipeline))
+}
 
-    /// Set origin for affine transformations.
-    pub fn set_origin(&mut self, ox: f32, oy: f32) {
-        self.set_transform(self.transform.with_origin(vec2f(ox, oy)));
+/// A set of buffers that batches are streamed through. A fence is inserted after each draw
+/// call that reads from them, so the next upload can tell whether the GPU is done with them.
+struct StreamBuffers {
+    vertices:   Buffer,
+    elements:   Buffer,
+    /// Holds the per-instance data of `rect_instanced`.
+    instances:  Buffer,
+    fence:      Cell<Option<Fence>>,
+    fences:     bool,
+}
+
+impl StreamBuffers {
+    /// Without `fences` the buffers are always orphaned before they're written to.
+    fn new(index: usize, fences: bool) -> StreamBuffers {
+        let stream = StreamBuffers {
+            vertices:   Buffer::new(BufferType::ArrayBuffer),
+            elements:   Buffer::new(BufferType::ElementArrayBuffer),
+            instances:  Buffer::new(BufferType::ArrayBuffer),
+            fence:      Cell::new(None),
+            fences,
+        };
+        stream.vertices.set_label(&format!("canvas.stream{}.vertices", index));
+        stream.elements.set_label(&format!("canvas.stream{}.elements", index));
+        stream.instances.set_label(&format!("canvas.stream{}.instances", index));
+        stream
     }
 
-    pub fn set_rotation(&mut self, rotation: f32) {
-        self.set_transform(self.transform.with_rotation(rotation));
+    /// Whether the GPU has finished the last draw call that read from the buffers.
+    fn is_idle(&self) -> bool {
+        if !self.fences { return false }
+        match self.fence.take() {
+            Some(fence) if !fence.is_signaled() => {
+                self.fence.set(Some(fence));
+                false
+            },
+            _ => true,
+        }
     }
 
-    pub fn set_rotation_deg(&mut self, rotation_deg: f32) {
-        self.set_transform(self.transform.with_rotation(deg2rad_h(rotation_deg)));
+    /// Writes `data` into `buffer`, which must be bound. If the GPU may still be reading from
+    /// the buffer it's orphaned instead of waiting for it.
+    fn upload<T: BufferDataType>(&self, buffer: &Buffer, data: &[T], idle: bool) {
+        if idle {
+            buffer.update_data(data, BufferUsage::StreamDraw);
+        } else {
+            buffer.stream_data(data);
+        }
+    }
+
+    /// Marks the buffers as used by the draw call that was just issued.
+    fn fence(&self) {
+        if !self.fences { return }
+        self.fence.set(Some(Fence::insert()));
+    }
+}
+
+/// What `end_target` has to restore.
+struct TargetState {
+    framebuffer:    gl::types::GLuint,
+    viewport:       [gl::types::GLint; 4],
+    display_size:   (f32, f32),
+    flip_y:         bool,
+    /// Clip rects and masks don't carry over into a target; they're restored once it ends.
+    clip_stack:     Vec<Rect>,
+    clip_masks:     Vec<Vec<Batch>>,
+    stencil:        StencilMode,
+}
+
+/// What `Context::save` saves. The depths are the lengths of the stacks, which `restore`
+/// truncates them back to.
+struct SavedState {
+    transform:      ViewTransform,
+    transform_depth: usize,
+    rounded_clip:   Option<(Rect, f32)>,
+    clip_depth:     usize,
+    mask_depth:     usize,
+    blend:          BlendMode,
+    opacity:        f32,
+    opacity_depth:  usize,
+    image_tint:     Color,
+}
+
+/// A layer that is being drawn into.
+struct Layer {
+    /// None if the surface couldn't be created and the layer is drawn onto the outer target.
+    surface:        Option<Surface>,
+    opacity:        f32,
+    blend:          BlendMode,
+    /// What `end_layer` has to restore.
+    outer_opacity:  f32,
+    outer_blend:    BlendMode,
+}
+
+/// How a batch uses the stencil buffer, which holds the number of clip masks that cover each
+/// pixel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum StencilMode {
+    Disabled,
+    /// Clears the stencil buffer before the first clip mask instead of drawing. Only used by
+    /// recorded batches without vertices, see `Batch::clear_stencil`.
+    Clear,
+    /// Only draws where the given number of clip masks overlap.
+    Test(u8),
+    /// Adds a clip mask inside of the given number of clip masks instead of drawing.
+    Increment(u8),
+    /// Removes the innermost clip mask from pixels covered by the given number of clip masks
+    /// instead of drawing.
+    Decrement(u8),
+}
+
+impl StencilMode {
+    fn writes_stencil(self) -> bool {
+        match self {
+            StencilMode::Clear | StencilMode::Increment(_) | StencilMode::Decrement(_) => true,
+            StencilMode::Disabled | StencilMode::Test(_) => false,
+        }
     }
+
+    /// The number of clip masks that have to cover a pixel.
+    fn level(self) -> u8 {
+        match self {
+            StencilMode::Disabled | StencilMode::Clear => 0,
+            StencilMode::Test(level) | StencilMode::Increment(level) | StencilMode::Decrement(level) => level,
+        }
+    }
+
+    /// The mode of a recorded batch when it's replayed inside of the clip masks of `outer`.
+    fn nested_in(self, outer: StencilMode) -> StencilMode {
+        let base = outer.level();
+        match (self, outer) {
+            (StencilMode::Disabled, _) => outer,
+            (StencilMode::Clear, StencilMode::Disabled) => StencilMode::Clear,
+            // the outer clip masks are in the stencil buffer, so it must not be cleared. Batches
+            // without vertices aren't drawn at all.
+            (StencilMode::Clear, _) => outer,
+            (StencilMode::Test(level), StencilMode::Increment(_)) => StencilMode::Increment(base.saturating_add(level)),
+            (StencilMode::Test(level), _) => StencilMode::Test(base.saturating_add(level)),
+            (StencilMode::Increment(level), _) => StencilMode::Increment(base.saturating_add(level)),
+            (StencilMode::Decrement(level), _) => StencilMode::Decrement(base.saturating_add(level)),
+        }
+    }
+}
+
+/// The built-in pipelines that `Context` can draw a batch with.
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum Shading {
+    Default,
+    /// The default pipeline for textures whose colors are already premultiplied by alpha.
+    Premultiplied,
+    Marker(MarkerUniforms),
+    Grid(GridStyle),
+    /// Looks up indexed texels in the palette texture with the given handle.
+    Palette(gl::types::GLuint),
+    /// Draws `RectInstance`s. Only used by `Context::rect_instanced`, which never leaves
+    /// vertices pending with it.
+    Instanced,
+    /// Replaces default shading inside of `Context::with_effect`. Holds the index of the
+    /// effect's pipeline and whether the texture is premultiplied.
+    Effect(u32, bool),
 }
 
-struct ViewTransform {
-    origin:     Vec2f,
-    rotation:   f32,
+impl Shading {
+    /// The shading that draws a texture with the default pipeline.
+    pub fn for_texture(texture: &Texture) -> Shading {
+        if texture.is_premultiplied() { Shading::Premultiplied } else { Shading::Default }
+    }
+}
+
+/// The transform that `Context` applies to everything it draws. Skew, scale and rotation happen
+/// around `origin`, in that order, followed by the translation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ViewTransform {
+    pub origin:     Vec2f,
+    /// Rotation in radians.
+    pub rotation:   f32,
+    pub scale:      Vec2f,
+    /// Shear factors, applied before scaling.
+    pub skew:       Vec2f,
+    /// Applied after scaling and rotating.
+    pub translation: Vec2f,
+    /// An arbitrary matrix applied before everything else.
+    pub local:      Mat4f,
+    /// The transforms pushed with `push_transform`, applied after this one.
+    base:           Mat4f,
 }
 
 impl ViewTransform {
+    /// The full matrix, including any transforms this was pushed on top of.
     pub fn matrix(&self) -> Mat4f {
         let t0 = transform::translate(-self.origin.x, -self.origin.y);
-        let t1 = transform::rotation(self.rotation);
-        let t2 = transform::translate(self.origin.x, self.origin.y);
-        transform::merge_all(&[t0, t1, t2])
+        let t1 = transform::skew(self.skew.x, self.skew.y);
+        let t2 = transform::scale(self.scale.x, self.scale.y);
+        let t3 = transform::rotation(self.rotation);
+        let t4 = transform::translate(self.origin.x + self.translation.x, self.origin.y + self.translation.y);
+        transform::merge_all(&[self.local, t0, t1, t2, t3, t4, self.base])
     }
 
+    /// A transform that leaves everything where it is.
     pub fn zero() -> ViewTransform {
         ViewTransform {
             origin:     vec2f(0.0, 0.0),
             rotation:   0.0,
+            scale:      vec2f(1.0, 1.0),
+            skew:       vec2f(0.0, 0.0),
+            translation: vec2f(0.0, 0.0),
+            local:      transform::identity(),
+            base:       transform::identity(),
+        }
+    }
+
+    pub fn with_scale(&self, scale: Vec2f) -> ViewTransform {
+        ViewTransform {
+            scale,
+            ..*self
+        }
+    }
+
+    pub fn with_skew(&self, skew: Vec2f) -> ViewTransform {
+        ViewTransform {
+            skew,
+            ..*self
+        }
+    }
+
+    pub fn with_translation(&self, translation: Vec2f) -> ViewTransform {
+        ViewTransform {
+            translation,
+            ..*self
+        }
+    }
+
+    pub fn with_local(&self, local: Mat4f) -> ViewTransform {
+        ViewTransform {
+            local,
+            ..*self
+        }
+    }
+
+    fn with_base(&self, base: Mat4f) -> ViewTransform {
+        ViewTransform {
+            base,
+            ..*self
         }
     }
 
     pub fn with_rotation(&self, rotation: f32) -> ViewTransform {
         ViewTransform {
-            rotation: rotation,
+            rotation,
             ..*self
         }
     }
 
     pub fn with_origin(&self, origin: Vec2f) -> ViewTransform {
         ViewTransform {
-            origin: origin,
+            origin,
             ..*self
         }
     }
+
+    /// Interpolates between two transforms, where `t = 0` gives `a` and `t = 1` gives `b`.
+    /// The rotation takes the shortest way around; everything else is interpolated linearly.
+    pub fn lerp(a: &ViewTransform, b: &ViewTransform, t: f32) -> ViewTransform {
+        use std::f32::consts::PI;
+
+        let lerp2 = |a: Vec2f, b: Vec2f| a + (b - a) * t;
+        let lerp_mat = |a: Mat4f, b: Mat4f| a + (b - a) * t;
+        let mut turn = (b.rotation - a.rotation) % (2.0 * PI);
+        if turn > PI {
+            turn -= 2.0 * PI;
+        } else if turn < -PI {
+            turn += 2.0 * PI;
+        }
+
+        ViewTransform {
+            origin:     lerp2(a.origin, b.origin),
+            rotation:   a.rotation + turn * t,
+            scale:      lerp2(a.scale, b.scale),
+            skew:       lerp2(a.skew, b.skew),
+            translation: lerp2(a.translation, b.translation),
+            local:      lerp_mat(a.local, b.local),
+            base:       lerp_mat(a.base, b.base),
+        }
+    }
+}
+
+impl Default for ViewTransform {
+    fn default() -> ViewTransform {
+        ViewTransform::zero()
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -257,9 +2647,25 @@ impl VertCol {
     }
 }
 
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct VertUV {
+    pub u: f32,
+    pub v: f32,
+}
+
+impl VertUV {
+    #[inline]
+    pub fn new(u: f32, v: f32) -> VertUV {
+        VertUV { u, v }
+    }
+}
+
+#[derive(Clone, Copy)]
 #[repr(C, packed)]
 pub struct Vert {
     pub pos: VertPos,
+    pub uv:  VertUV,
     pub col: VertCol,
 }
 
@@ -268,52 +2674,76 @@ impl Vert {
     pub fn new(x: f32, y: f32, r: f32, g: f32, b: f32, a: f32) -> Vert {
         Vert {
             pos: VertPos::new(x, y),
+            uv:  VertUV::new(0.0, 0.0),
             col: VertCol::new(r, g, b, a),
         }
     }
 
     #[inline]
     pub fn with_pc(pos: VertPos, col: VertCol) -> Vert {
-        Vert {
-            pos: pos,
-            col: col,
-        }
+        Vert { pos, uv: VertUV::new(0.0, 0.0), col }
     }
-}
 
-impl Clone for Vert {
-    fn clone(&self) -> Self {
-        Vert::new(
-            self.pos.x, self.pos.y,
-            self.col.r, self.col.g, self.col.b, self.col.a
-        )
+    #[inline]
+    pub fn with_puc(pos: VertPos, uv: VertUV, col: VertCol) -> Vert {
+        Vert { pos, uv, col }
     }
 }
 
 impl super::opengl::BufferDataType for Vert {}
 
+impl VertexFormat for Vert {
+    fn layout() -> VertexLayout {
+        let szfloat = std::mem::size_of::<f32>();
+        VertexLayout::new(std::mem::size_of::<Vert>())
+            .float("Position", 2, 0)
+            .float("UV", 2, 2 * szfloat)
+            .float("Color", 4, 4 * szfloat)
+    }
+}
+
 pub const VERTEX_SHADER: &str   = "\
 #version 130
 
 uniform mat4 Transform;
 uniform mat4 ProjMtx;
 in  vec2 Position;
+in  vec2 UV;
 in  vec4 Color;
+out vec2 FragUV;
 out vec4 FragColor;
+out vec2 FragScreenPos;
 
 void main() {
-    FragColor = Color;
-    vec4 t = ProjMtx * Transform * vec4(Position.xy, 1.0, 1.0);
-    gl_Position = t;
+    FragUV = UV;
+    FragColor = vec4(srgb_to_linear(Color.rgb), Color.a);
+    vec4 screen_pos = Transform * vec4(Position.xy, 0.0, 1.0);
+    FragScreenPos = screen_pos.xy;
+    gl_Position = ProjMtx * screen_pos;
 }\0";
 
+/// Like every fragment shader used by `Context`, this outputs colors with premultiplied alpha.
 pub const FRAGMENT_SHADER: &str = "\
 #version 130
 
+uniform sampler2D Texture;
+in  vec2 FragUV;
 in  vec4 FragColor;
 out vec4 OutColor;
 
+uniform bool TexturePremultiplied;
+// only used by meshes, everything else has the opacity in its vertex colors.
+uniform float Opacity;
+
 void main() {
-    OutColor = FragColor;
+    vec4 texel = texture(Texture, FragUV.st);
+    if (TexturePremultiplied) {
+        OutColor = texel * vec4(FragColor.rgb * FragColor.a, FragColor.a) * clip_coverage();
+    } else {
+        OutColor = FragColor * texel;
+        OutColor.a *= clip_coverage();
+        OutColor.rgb *= OutColor.a;
+    }
+    OutColor *= Opacity;
 }\0";
 
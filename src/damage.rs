@@ -0,0 +1,42 @@
+use super::math::Rect;
+
+/// Merges dirty rectangles into at most `max_rects` larger ones that cover all of them.
+///
+/// Pairs are merged greedily, cheapest first, where the cost of a merge is the area the union
+/// covers beyond the two rectangles. Rectangles keep being merged while there are more than
+/// `max_rects` of them, and after that only while a merge doesn't add any overdraw (overlapping
+/// or touching rectangles). Empty rectangles are dropped.
+///
+/// This is quadratic in the number of rectangles per merge, which is fine for the handful of
+/// widgets that change in a frame.
+pub fn merge_damage(rects: &[Rect], max_rects: usize) -> Vec<Rect> {
+    let max_rects = max_rects.max(1);
+    let mut merged: Vec<Rect> = rects.iter().cloned().filter(|r| !r.is_empty()).collect();
+
+    while merged.len() > 1 {
+        let mut best = (0, 0, f32::INFINITY);
+        for i in 0..merged.len() {
+            for j in (i + 1)..merged.len() {
+                let cost = merge_cost(&merged[i], &merged[j]);
+                if cost < best.2 {
+                    best = (i, j, cost);
+                }
+            }
+        }
+
+        let (i, j, cost) = best;
+        if merged.len() <= max_rects && cost > 0.0 {
+            break;
+        }
+        let other = merged.swap_remove(j);
+        merged[i] = merged[i].union(&other);
+    }
+
+    merged
+}
+
+/// Area covered by the union of two rectangles that neither of them covers. Negative when they
+/// overlap.
+fn merge_cost(a: &Rect, b: &Rect) -> f32 {
+    a.union(b).area() - a.area() - b.area()
+}
@@ -0,0 +1,187 @@
+use super::math::*;
+use super::blend::BlendMode;
+use super::context::{ Batch, BatchState, Shading, Vert, rect_verts, image_quad_verts, DEFAULT_MAX_VERTS, DEFAULT_MAX_ELEMS };
+use super::instancing::UNIT_QUAD_ELEMS;
+use super::sprite::SpriteTransform;
+use super::opengl::{ Texture, TextureFilter, SamplerParams };
+
+/// Everything drawn inside of `Context::record` or with a `DrawListBuilder`, which can be drawn
+/// with `Context::draw_list`. This is plain CPU data, so lists can be built and sent between
+/// threads.
+#[derive(Clone, Default)]
+pub struct DrawList {
+    pub(crate) batches: Vec<Batch>,
+}
+
+impl DrawList {
+    pub(crate) fn from_batches(batches: Vec<Batch>) -> DrawList {
+        DrawList { batches }
+    }
+
+    /// The number of draw calls it takes to draw the list.
+    pub fn batches(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.batches.iter().map(|b| b.vertices.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Appends the batches of `other`, e.g. to join lists built on several threads in order.
+    pub fn append(&mut self, other: DrawList) {
+        self.batches.extend(other.batches);
+    }
+}
+
+/// Builds a `DrawList` without a `Context` and without making any GL calls, so the vertices of
+/// expensive content can be generated on worker threads. The main thread then draws the
+/// finished lists with `Context::draw_list`, which applies the context's state on top.
+///
+/// Textures passed to the builder must still be alive when the list is drawn. Textures without
+/// their own filter are drawn with linear filtering.
+pub struct DrawListBuilder {
+    batches:    Vec<Batch>,
+    state:      BatchState,
+    vertices:   Vec<Vert>,
+    elements:   Vec<u32>,
+    max_verts:  usize,
+    max_elems:  usize,
+    opacity:    f32,
+    image_tint: Color,
+}
+
+impl DrawListBuilder {
+    pub fn new() -> DrawListBuilder {
+        DrawListBuilder::with_capacity(DEFAULT_MAX_VERTS, DEFAULT_MAX_ELEMS)
+    }
+
+    /// Like `Context::with_capacity`.
+    pub fn with_capacity(max_verts: usize, max_elems: usize) -> DrawListBuilder {
+        DrawListBuilder {
+            batches:    Vec::new(),
+            state:      BatchState::new(),
+            vertices:   Vec::new(),
+            elements:   Vec::new(),
+            max_verts:  max_verts.max(1),
+            max_elems:  max_elems.max(3),
+            opacity:    1.0,
+            image_tint: Color::WHITE,
+        }
+    }
+
+    /// Sets the matrix that maps what's drawn from now on to the coordinates of the context
+    /// the list is drawn with.
+    pub fn set_transform(&mut self, matrix: Mat4f) {
+        self.update(|state| state.transform = matrix);
+    }
+
+    pub fn transform(&self) -> Mat4f {
+        self.state.transform
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode) {
+        self.update(|state| state.blend = blend);
+    }
+
+    /// Like `Context::set_z_index`, relative to the z index the list is drawn with.
+    pub fn set_z_index(&mut self, z_index: i32) {
+        self.update(|state| state.z_index = z_index);
+    }
+
+    /// Multiplied into the alpha of every vertex that is pushed from now on.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Like `Context::set_image_tint`.
+    pub fn set_image_tint(&mut self, tint: Color) {
+        self.image_tint = tint;
+    }
+
+    /// Adds vertices drawn with the current texture, like `Context::push_verts`.
+    pub fn push_verts(&mut self, verts: &[Vert], elems: &[u32]) {
+        if self.vertices.len() + verts.len() > self.max_verts || self.elements.len() + elems.len() > self.max_elems {
+            self.finish_batch();
+        }
+
+        debug_assert!(elems.len() % 3 == 0, "number of elements must be a multiple of 3");
+        let elem_delta = self.vertices.len() as u32;
+        let opacity = self.opacity;
+        self.vertices.extend(verts.iter().map(|v| {
+            let mut v = *v;
+            v.col.a *= opacity;
+            v
+        }));
+        self.elements.extend(elems.iter().map(|e| {
+            debug_assert!(*e < verts.len() as u32, "element is larger than the number of vertices provided");
+            e + elem_delta
+        }));
+    }
+
+    /// Like `Context::rect`.
+    pub fn rect(&mut self, color: Color, x: f32, y: f32, width: f32, height: f32) {
+        self.set_untextured();
+        self.push_verts(&rect_verts(color, x, y, width, height), &UNIT_QUAD_ELEMS);
+    }
+
+    /// Like `Context::draw_image_region`.
+    pub fn draw_image_region(&mut self, texture: &Texture, src: Rect, dest: Rect) {
+        let sampler = SamplerParams::new(texture.filter().unwrap_or(TextureFilter::Linear), texture.wrap())
+            .with_mipmaps(texture.has_mipmaps());
+        let shading = Shading::for_texture(texture);
+        self.update(|state| {
+            state.texture = Some(texture.handle());
+            state.sampler = sampler;
+            state.shading = shading;
+        });
+        self.push_verts(&image_quad_verts(texture, src, dest, &SpriteTransform::new(), self.image_tint), &UNIT_QUAD_ELEMS);
+    }
+
+    /// Like `Context::draw_image`.
+    pub fn draw_image(&mut self, texture: &Texture, x: f32, y: f32) {
+        let (w, h) = (texture.get_width() as f32, texture.get_height() as f32);
+        self.draw_image_region(texture, Rect::new(0.0, 0.0, w, h), Rect::new(x, y, w, h));
+    }
+
+    pub fn finish(mut self) -> DrawList {
+        self.finish_batch();
+        DrawList::from_batches(self.batches)
+    }
+
+    fn set_untextured(&mut self) {
+        let untextured = BatchState::new();
+        self.update(|state| {
+            state.texture = untextured.texture;
+            state.sampler = untextured.sampler;
+            state.shading = untextured.shading;
+        });
+    }
+
+    /// Changes the state, which starts a new batch if it's different.
+    fn update<F: FnOnce(&mut BatchState)>(&mut self, change: F) {
+        let mut state = self.state;
+        change(&mut state);
+        if state == self.state { return }
+        self.finish_batch();
+        self.state = state;
+    }
+
+    fn finish_batch(&mut self) {
+        if self.vertices.is_empty() { return }
+        self.batches.push(Batch {
+            state:      self.state,
+            vertices:   std::mem::take(&mut self.vertices),
+            elements:   std::mem::take(&mut self.elements),
+        });
+    }
+}
+
+impl Default for DrawListBuilder {
+    fn default() -> DrawListBuilder {
+        DrawListBuilder::new()
+    }
+}
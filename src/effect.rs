@@ -0,0 +1,67 @@
+use super::opengl::Program;
+
+/// A fragment shader that `Context::with_effect` draws primitives through, e.g. for dissolves
+/// or glows. The source only has to define
+///
+/// ```glsl
+/// vec4 effect(vec4 color, vec2 uv, vec2 screen_pos)
+/// ```
+///
+/// which returns the color of a fragment with straight (not premultiplied) alpha. `color` is
+/// the interpolated vertex color, `uv` the texture coordinate and `screen_pos` the position in
+/// display coordinates. It's compiled after `EFFECT_PRELUDE`, so the current texture can be
+/// sampled with `texel(uv)` or through the `Texture` sampler. The context's opacity is already
+/// in the alpha of `color`, and clipping and premultiplication are applied afterwards. Further
+/// uniforms can be declared and set in `set_uniforms`.
+pub trait Effect {
+    fn source(&self) -> &str;
+
+    /// Sets the effect's own uniforms. The program is bound.
+    fn set_uniforms(&self, _program: &Program) {}
+}
+
+/// An effect that is just a fragment shader source without uniforms of its own.
+impl Effect for str {
+    fn source(&self) -> &str {
+        self
+    }
+}
+
+/// Declares the inputs that every effect can use. Uses GLSL 1.30 like the built-in shaders and
+/// is adapted to the context's GLSL version the same way. The clip uniforms and
+/// `FragScreenPos` are added along with `clip_coverage()` when the pipeline is built.
+pub const EFFECT_PRELUDE: &str = "\
+#version 130
+
+uniform sampler2D Texture;
+uniform bool TexturePremultiplied;
+in  vec2 FragUV;
+in  vec4 FragColor;
+out vec4 OutColor;
+
+// the texel of the current texture at uv, with straight alpha.
+vec4 texel(vec2 uv) {
+    vec4 t = texture(Texture, uv);
+    if (TexturePremultiplied && t.a > 0.0) t.rgb /= t.a;
+    return t;
+}
+
+vec4 effect(vec4 color, vec2 uv, vec2 screen_pos);
+";
+
+/// Calls the effect and premultiplies its result.
+pub(crate) const EFFECT_MAIN: &str = "
+// only used by meshes, everything else has the opacity in its vertex colors.
+uniform float Opacity;
+
+void main() {
+    vec4 color = effect(FragColor, FragUV, FragScreenPos);
+    color.a *= clip_coverage();
+    OutColor = vec4(color.rgb * color.a, color.a) * Opacity;
+}
+";
+
+/// The full fragment shader for an effect.
+pub(crate) fn fragment_source(effect_source: &str) -> String {
+    format!("{}\n{}\n{}", EFFECT_PRELUDE, effect_source.trim_end_matches('\0'), EFFECT_MAIN)
+}
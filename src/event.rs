@@ -0,0 +1,72 @@
+use glutin::{
+    ElementState,
+    KeyboardInput,
+    MouseButton,
+    MouseScrollDelta,
+    TouchPhase,
+    WindowEvent,
+};
+use std::path::PathBuf;
+
+/// Something that happened to a window, in the order it happened. `Window` keeps track of the
+/// state most applications need (see `Window::mouse` and `Window::keyboard_input`), but every
+/// event is also available as one of these.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// The user asked for the window to close, e.g. with its close button. `Window::running`
+    /// returns false from then on.
+    CloseRequested,
+    /// The client area was resized, in physical pixels.
+    Resized { width: f32, height: f32 },
+    /// The number of physical pixels per logical pixel changed, usually because the window
+    /// moved to a monitor with a different scale. A `Resized` event with the new physical size
+    /// follows.
+    DpiFactorChanged(f32),
+    Focused(bool),
+    Key(KeyboardInput),
+    /// A character was typed, after keyboard layouts and dead keys were applied, or committed
+    /// by an input method. Keys like backspace and enter also produce control characters.
+    Text(char),
+    /// The cursor moved to a position in logical pixels.
+    MouseMoved { x: f32, y: f32 },
+    MouseButton { button: MouseButton, state: ElementState },
+    Scroll(MouseScrollDelta),
+    CursorEntered,
+    CursorLeft,
+    /// A finger touched, moved or was lifted, at a position in logical pixels. `id` stays the
+    /// same for one finger from `TouchPhase::Started` until it ends.
+    Touch { id: u64, phase: TouchPhase, x: f32, y: f32 },
+    /// A file was dropped on the window.
+    DroppedFile(PathBuf),
+    /// A file is being dragged over the window.
+    HoveredFile(PathBuf),
+    /// The file that was being dragged over the window left it or the drag was cancelled.
+    HoveredFileCancelled,
+    /// Any other window event, as glutin reported it.
+    Other(WindowEvent),
+}
+
+impl Event {
+    /// `physical_size` is the client area size for resize events, which glutin reports in
+    /// logical pixels.
+    pub(crate) fn from_window_event(event: &WindowEvent, physical_size: (f32, f32)) -> Event {
+        match *event {
+            WindowEvent::CloseRequested => Event::CloseRequested,
+            WindowEvent::Resized(_) => Event::Resized { width: physical_size.0, height: physical_size.1 },
+            WindowEvent::HiDpiFactorChanged(dpi_factor) => Event::DpiFactorChanged(dpi_factor as f32),
+            WindowEvent::Focused(focused) => Event::Focused(focused),
+            WindowEvent::KeyboardInput { input, .. } => Event::Key(input),
+            WindowEvent::ReceivedCharacter(c) => Event::Text(c),
+            WindowEvent::CursorMoved { position, .. } => Event::MouseMoved { x: position.x as f32, y: position.y as f32 },
+            WindowEvent::MouseInput { button, state, .. } => Event::MouseButton { button, state },
+            WindowEvent::MouseWheel { delta, .. } => Event::Scroll(delta),
+            WindowEvent::CursorEntered { .. } => Event::CursorEntered,
+            WindowEvent::CursorLeft { .. } => Event::CursorLeft,
+            WindowEvent::Touch(touch) => Event::Touch { id: touch.id, phase: touch.phase, x: touch.location.x as f32, y: touch.location.y as f32 },
+            WindowEvent::DroppedFile(ref path) => Event::DroppedFile(path.clone()),
+            WindowEvent::HoveredFile(ref path) => Event::HoveredFile(path.clone()),
+            WindowEvent::HoveredFileCancelled => Event::HoveredFileCancelled,
+            ref other => Event::Other(other.clone()),
+        }
+    }
+}
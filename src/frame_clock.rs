@@ -0,0 +1,194 @@
+use std::time::{ Duration, Instant };
+
+/// How much of the previous average the smoothed frame rate keeps each frame. Higher values
+/// steady the number at the cost of reacting more slowly.
+const FPS_SMOOTHING: f32 = 0.9;
+
+/// Measures the time between frames, so movement can be scaled by it instead of depending on
+/// the refresh rate. `Window` ticks one on every `flip`, see `Window::frame_clock`.
+#[derive(Copy, Clone, Debug)]
+pub struct FrameClock {
+    start:      Instant,
+    last_tick:  Instant,
+    delta:      Duration,
+    frames:     u64,
+    /// Exponential moving average, 0.0 until the first frame was measured.
+    fps:        f32,
+}
+
+impl FrameClock {
+    pub fn new() -> FrameClock {
+        let now = Instant::now();
+        FrameClock {
+            start:      now,
+            last_tick:  now,
+            delta:      Duration::from_secs(0),
+            frames:     0,
+            fps:        0.0,
+        }
+    }
+
+    /// Ends a frame. The time since the last tick (or since the clock was created) becomes the
+    /// duration of the frame that follows.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        self.delta = now - self.last_tick;
+        self.last_tick = now;
+        self.frames += 1;
+
+        let seconds = self.delta_seconds();
+        if seconds > 0.0 {
+            let fps = 1.0 / seconds;
+            self.fps = if self.fps == 0.0 { fps } else { self.fps * FPS_SMOOTHING + fps * (1.0 - FPS_SMOOTHING) };
+        }
+    }
+
+    /// How long the last frame took, in seconds. 0.0 before the first tick.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Frames per second, averaged over roughly the last ten frames so it can be displayed
+    /// without flickering.
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Seconds since the clock was created, as of the last tick.
+    pub fn elapsed_seconds(&self) -> f32 {
+        (self.last_tick - self.start).as_secs_f32()
+    }
+
+    /// The number of ticks so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> FrameClock {
+        FrameClock::new()
+    }
+}
+
+/// Runs game updates at a fixed rate no matter how fast frames are shown, by collecting frame
+/// time and spending it in whole steps. Rendering then blends between the last two updates by
+/// the returned alpha, so motion stays smooth when the rates don't line up.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step:           Duration,
+    accumulator:    Duration,
+    /// More steps than this in one frame are dropped, so a slow frame can't make the next one
+    /// even slower by running ever more updates.
+    max_steps:      u32,
+}
+
+impl FixedTimestep {
+    /// Updates `rate` times per second, e.g. 60.0. Panics if `rate` isn't a positive, finite
+    /// number or is so small that a step doesn't fit into a `Duration`.
+    pub fn new(rate: f64) -> FixedTimestep {
+        let step = Duration::try_from_secs_f64(1.0 / rate).ok().filter(|_| rate.is_finite() && rate > 0.0);
+        FixedTimestep {
+            step:           step.unwrap_or_else(|| panic!("FixedTimestep needs a positive, finite rate, got {}", rate)),
+            accumulator:    Duration::from_secs(0),
+            max_steps:      8,
+        }
+    }
+
+    /// Limits how many updates one frame can run. Time beyond that is dropped, which slows the
+    /// game down instead of freezing it when updates can't keep up.
+    pub fn with_max_steps(self, max_steps: u32) -> FixedTimestep {
+        FixedTimestep {
+            max_steps: max_steps.max(1),
+            ..self
+        }
+    }
+
+    /// The duration of one update, in seconds.
+    pub fn step_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Adds the time of a frame (usually `FrameClock::delta`) and calls `update` with the step
+    /// in seconds once for every whole step that is due. Returns how far the time left over is
+    /// into the next step, from 0.0 to 1.0, to interpolate between the previous and the current
+    /// state when rendering.
+    pub fn advance<F: FnMut(f32)>(&mut self, frame_time: Duration, mut update: F) -> f32 {
+        self.accumulator += frame_time;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_steps {
+                self.accumulator = Duration::from_secs(0);
+                break;
+            }
+            update(self.step.as_secs_f32());
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        self.alpha()
+    }
+
+    /// How far the time left over is into the next step, from 0.0 to 1.0.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()) as f32
+    }
+
+    /// Drops the time left over, e.g. after loading or unpausing so no updates are made up for.
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::from_secs(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_runs_whole_steps_and_keeps_the_rest() {
+        let mut timestep = FixedTimestep::new(10.0);
+        let mut steps = 0;
+        let alpha = timestep.advance(Duration::from_millis(250), |dt| {
+            assert!((dt - 0.1).abs() < 1e-6);
+            steps += 1;
+        });
+        assert_eq!(steps, 2);
+        assert!((alpha - 0.5).abs() < 1e-3);
+
+        let alpha = timestep.advance(Duration::from_millis(50), |_| steps += 1);
+        assert_eq!(steps, 3);
+        assert!(alpha.abs() < 1e-3);
+    }
+
+    #[test]
+    fn advance_drops_time_beyond_max_steps() {
+        let mut timestep = FixedTimestep::new(10.0).with_max_steps(3);
+        let mut steps = 0;
+        let alpha = timestep.advance(Duration::from_secs(10), |_| steps += 1);
+        assert_eq!(steps, 3);
+        assert_eq!(alpha, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive, finite rate")]
+    fn rejects_a_zero_rate() {
+        FixedTimestep::new(0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive, finite rate")]
+    fn rejects_a_nan_rate() {
+        FixedTimestep::new(f64::NAN);
+    }
+
+    #[test]
+    fn reset_drops_the_time_left_over() {
+        let mut timestep = FixedTimestep::new(10.0);
+        timestep.advance(Duration::from_millis(50), |_| panic!("no step is due yet"));
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use super::math::Rect;
+use super::blend::BlendMode;
+use super::marker::MarkerShape;
+use super::opengl::SamplerParams;
+use super::context::ViewTransform;
+
+/// The most entries a log holds. Entries beyond this are only counted, so a log that is never
+/// dumped doesn't grow without bounds.
+pub const MAX_ENTRIES: usize = 16 * 1024;
+
+/// The reason a batch of vertices was submitted to the GPU.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FlushCause {
+    /// `Context::flush_verts` was called.
+    Explicit,
+    /// The batch did not have room for the next primitive.
+    BatchFull,
+    /// The view transform changed while vertices were pending.
+    TransformChanged,
+    /// A primitive using a different texture was drawn.
+    TextureChanged,
+    /// A primitive drawn by a different shader (or with different shader parameters) was drawn.
+    ShaderChanged,
+    /// Drawing was redirected to a different render target.
+    TargetChanged,
+    /// The clip region changed.
+    ClipChanged,
+    /// Pixels were read back from the render target.
+    Readback,
+    /// Drawing for a set of views started or ended, or a batch was replayed into a view.
+    ViewsChanged,
+    /// A GPU query began or ended.
+    Query,
+    /// The blend mode changed.
+    BlendChanged,
+    /// The z index changed, or z-sorted drawing started or ended.
+    ZIndexChanged,
+    /// Instances were drawn with `Context::rect_instanced`.
+    Instanced,
+    /// A mesh was drawn with `Context::draw_mesh`.
+    Mesh,
+    /// Recording a draw list started or ended, or a recorded batch was drawn.
+    DrawList,
+    /// Vertices of a custom pipeline were drawn with `Context::draw_custom`.
+    Custom,
+}
+
+impl FlushCause {
+    pub const ALL: &'static [FlushCause] = &[
+        FlushCause::Explicit,
+        FlushCause::BatchFull,
+        FlushCause::TransformChanged,
+        FlushCause::TextureChanged,
+        FlushCause::ShaderChanged,
+        FlushCause::TargetChanged,
+        FlushCause::ClipChanged,
+        FlushCause::Readback,
+        FlushCause::ViewsChanged,
+        FlushCause::Query,
+        FlushCause::BlendChanged,
+        FlushCause::ZIndexChanged,
+        FlushCause::Instanced,
+        FlushCause::Mesh,
+        FlushCause::DrawList,
+        FlushCause::Custom,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            FlushCause::Explicit => "explicit flush",
+            FlushCause::BatchFull => "batch full",
+            FlushCause::TransformChanged => "transform changed",
+            FlushCause::TextureChanged => "texture changed",
+            FlushCause::ShaderChanged => "shader changed",
+            FlushCause::TargetChanged => "render target changed",
+            FlushCause::ClipChanged => "clip changed",
+            FlushCause::Readback => "pixel readback",
+            FlushCause::ViewsChanged => "views",
+            FlushCause::Query => "query",
+            FlushCause::BlendChanged => "blend mode changed",
+            FlushCause::ZIndexChanged => "z index changed",
+            FlushCause::Instanced => "instanced draw",
+            FlushCause::Mesh => "mesh",
+            FlushCause::DrawList => "draw list",
+            FlushCause::Custom => "custom pipeline",
+        }
+    }
+}
+
+impl fmt::Display for FlushCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+/// A piece of render state that a `Context` changed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StateChange {
+    Srgb(bool),
+    Blend(BlendMode),
+    /// One of the built-in shaders, by name.
+    Shader(&'static str),
+    Marker(MarkerShape),
+    /// The effect with the given index replaced the default shader.
+    Effect(u32),
+    /// The palette texture with the given handle was set.
+    Palette(u32),
+    /// The texture with the given handle and how it's sampled.
+    Texture(u32, SamplerParams),
+    /// The rect and corner radius of the rounded clip.
+    RoundedClip(Option<(Rect, f32)>),
+    /// The scissor box in framebuffer pixels.
+    Scissor(Option<[i32; 4]>),
+    /// How batches use the stencil buffer (`"disabled"`, `"test"`, `"increment"` or
+    /// `"decrement"`) and the number of clip masks involved.
+    Stencil(&'static str, u8),
+    /// The clip mask at the given level is drawn again to erase it.
+    EraseClipMask(u8),
+    /// Drawing went into the surface with the given framebuffer handle.
+    TargetSurface(u32),
+    /// Drawing went back to the framebuffer with the given handle.
+    TargetFramebuffer(u32),
+    Transform(ViewTransform),
+    ShadersReloaded,
+    /// The viewport of a view in display coordinates, which the views' batches are drawn into.
+    View(Rect),
+    /// `draw_views` was called while recording, so its batches were drawn without views.
+    ViewsNested,
+    ZIndex(i32),
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateChange::Srgb(enabled) => write!(f, "srgb: {}", enabled),
+            StateChange::Blend(blend) => write!(f, "blend: {:?}", blend),
+            StateChange::Shader(name) => write!(f, "shader: {}", name),
+            StateChange::Marker(shape) => write!(f, "shader: marker {:?}", shape),
+            StateChange::Effect(index) => write!(f, "shader: effect {}", index),
+            StateChange::Palette(texture) => write!(f, "palette: {}", texture),
+            StateChange::Texture(texture, sampler) => {
+                write!(f, "texture: {} ({:?}, {:?}, mipmaps: {})", texture, sampler.filter, sampler.wrap, sampler.mipmaps)
+            },
+            StateChange::RoundedClip(Some((r, radius))) => {
+                write!(f, "rounded clip: ({}, {}) {}x{} radius {}", r.x, r.y, r.width, r.height, radius)
+            },
+            StateChange::RoundedClip(None) => write!(f, "rounded clip: none"),
+            StateChange::Scissor(Some([x, y, w, h])) => write!(f, "scissor: ({}, {}) {}x{}", x, y, w, h),
+            StateChange::Scissor(None) => write!(f, "scissor: none"),
+            StateChange::Stencil(mode, level) => write!(f, "stencil: {} {}", mode, level),
+            StateChange::EraseClipMask(level) => write!(f, "stencil: erasing clip mask {}", level),
+            StateChange::TargetSurface(framebuffer) => write!(f, "target: surface {}", framebuffer),
+            StateChange::TargetFramebuffer(framebuffer) => write!(f, "target: framebuffer {}", framebuffer),
+            StateChange::Transform(t) => {
+                write!(f, "transform: origin ({}, {}), rotation {} rad, scale ({}, {}), skew ({}, {}), translation ({}, {})",
+                    t.origin.x, t.origin.y, t.rotation, t.scale.x, t.scale.y, t.skew.x, t.skew.y, t.translation.x, t.translation.y)
+            },
+            StateChange::ShadersReloaded => write!(f, "shaders reloaded"),
+            StateChange::View(r) => write!(f, "view: ({}, {}) {}x{}", r.x, r.y, r.width, r.height),
+            StateChange::ViewsNested => write!(f, "views: recorded inside of another recording, drawn without views"),
+            StateChange::ZIndex(z_index) => write!(f, "z index: {}", z_index),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FrameLogEntry {
+    StateChange(StateChange),
+    /// A batch was drawn with a single draw call.
+    DrawCall {
+        cause:      FlushCause,
+        vertices:   usize,
+        elements:   usize,
+    },
+}
+
+/// A record of the state changes and draw calls issued by a `Context` since the log was last
+/// taken. Logging is off by default, see `Context::set_frame_log_enabled`.
+#[derive(Clone, Debug, Default)]
+pub struct FrameLog {
+    entries: Vec<FrameLogEntry>,
+    /// Entries that didn't fit after `MAX_ENTRIES`.
+    dropped: usize,
+}
+
+impl FrameLog {
+    pub fn new() -> FrameLog {
+        FrameLog { entries: Vec::new(), dropped: 0 }
+    }
+
+    pub fn entries(&self) -> &[FrameLogEntry] {
+        &self.entries
+    }
+
+    /// The number of entries that weren't kept because the log was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    fn push(&mut self, entry: FrameLogEntry) {
+        if self.entries.len() < MAX_ENTRIES {
+            self.entries.push(entry);
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    pub fn record_state_change(&mut self, change: StateChange) {
+        self.push(FrameLogEntry::StateChange(change));
+    }
+
+    pub fn record_draw_call(&mut self, cause: FlushCause, vertices: usize, elements: usize) {
+        self.push(FrameLogEntry::DrawCall { cause, vertices, elements });
+    }
+
+    pub fn draw_calls(&self) -> usize {
+        self.entries.iter().filter(|e| matches!(e, FrameLogEntry::DrawCall { .. })).count()
+    }
+
+    /// The number of draw calls that were caused by `cause`.
+    pub fn draw_calls_caused_by(&self, cause: FlushCause) -> usize {
+        self.entries.iter().filter(|e| match e {
+            FrameLogEntry::DrawCall { cause: c, .. } => *c == cause,
+            _ => false,
+        }).count()
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.entries.iter().map(|e| match e {
+            FrameLogEntry::DrawCall { vertices, .. } => *vertices,
+            _ => 0,
+        }).sum()
+    }
+}
+
+impl fmt::Display for FrameLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut draw_idx = 0;
+        for entry in self.entries.iter() {
+            match entry {
+                FrameLogEntry::StateChange(change) => {
+                    writeln!(f, "  state: {}", change)?;
+                },
+                FrameLogEntry::DrawCall { cause, vertices, elements } => {
+                    writeln!(f, "  draw #{}: {} vertices, {} triangles ({})", draw_idx, vertices, elements / 3, cause)?;
+                    draw_idx += 1;
+                },
+            }
+        }
+
+        let state_changes = self.entries.len() - self.draw_calls();
+        write!(f, "{} draw calls, {} vertices, {} state changes", self.draw_calls(), self.vertices(), state_changes)?;
+        if self.dropped > 0 {
+            write!(f, " ({} more entries dropped)", self.dropped)?;
+        }
+
+        for cause in FlushCause::ALL.iter() {
+            let count = self.draw_calls_caused_by(*cause);
+            if count > 0 {
+                write!(f, "\n  flushes due to {}: {}", cause, count)?;
+            }
+        }
+        Ok(())
+    }
+}
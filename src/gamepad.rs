@@ -0,0 +1,254 @@
+use gilrs::{ Gilrs, EventType };
+
+/// A button in the layout of an Xbox controller, which gamepads are mapped to. Face buttons are
+/// named by their position, so `South` is A on an Xbox controller and the cross on a
+/// PlayStation one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    /// The button with the vendor's logo.
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    /// A button that the layout doesn't name, by the platform's code for it.
+    Other(u32),
+}
+
+impl GamepadButton {
+    /// None for the triggers, which are exposed as axes.
+    fn from_gilrs(button: gilrs::Button, code: gilrs::ev::Code) -> Option<GamepadButton> {
+        use gilrs::Button;
+        Some(match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::West => GamepadButton::West,
+            Button::North => GamepadButton::North,
+            Button::LeftTrigger => GamepadButton::LeftBumper,
+            Button::RightTrigger => GamepadButton::RightBumper,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::Mode => GamepadButton::Mode,
+            Button::LeftThumb => GamepadButton::LeftStick,
+            Button::RightThumb => GamepadButton::RightStick,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            Button::LeftTrigger2 | Button::RightTrigger2 => return None,
+            Button::C | Button::Z | Button::Unknown => GamepadButton::Other(code.into_u32()),
+        })
+    }
+}
+
+/// An analog input of a gamepad. Sticks go from -1.0 to 1.0 with positive y pointing up and
+/// triggers go from 0.0 (released) to 1.0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+    /// An axis that the layout doesn't name, by the platform's code for it, from -1.0 to 1.0.
+    Other(u32),
+}
+
+impl GamepadAxis {
+    /// None for the d-pad, which is exposed as buttons.
+    fn from_gilrs(axis: gilrs::Axis, code: gilrs::ev::Code) -> Option<GamepadAxis> {
+        use gilrs::Axis;
+        Some(match axis {
+            Axis::LeftStickX => GamepadAxis::LeftStickX,
+            Axis::LeftStickY => GamepadAxis::LeftStickY,
+            Axis::RightStickX => GamepadAxis::RightStickX,
+            Axis::RightStickY => GamepadAxis::RightStickY,
+            Axis::LeftZ => GamepadAxis::LeftTrigger,
+            Axis::RightZ => GamepadAxis::RightTrigger,
+            Axis::DPadX | Axis::DPadY => return None,
+            Axis::Unknown => GamepadAxis::Other(code.into_u32()),
+        })
+    }
+}
+
+/// The state of one connected gamepad, as of the last call to `Window::handle_events` or
+/// `Window::wait_events`. "This frame" means since the call before that, like for `Mouse`.
+#[derive(Clone, Debug)]
+pub struct Gamepad {
+    /// Stays the same while the gamepad is connected. A gamepad that is reconnected keeps its
+    /// id if the platform can tell that it's the same one.
+    id:         usize,
+    name:       String,
+    down:       Vec<GamepadButton>,
+    pressed:    Vec<GamepadButton>,
+    released:   Vec<GamepadButton>,
+    axes:       Vec<(GamepadAxis, f32)>,
+}
+
+impl Gamepad {
+    fn new(id: usize, name: String) -> Gamepad {
+        Gamepad {
+            id,
+            name,
+            down:       Vec::new(),
+            pressed:    Vec::new(),
+            released:   Vec::new(),
+            axes:       Vec::new(),
+        }
+    }
+
+    fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    fn set_button(&mut self, button: GamepadButton, down: bool) {
+        let was_down = self.down.contains(&button);
+        if down && !was_down {
+            self.down.push(button);
+            self.pressed.push(button);
+        } else if !down && was_down {
+            self.down.retain(|&b| b != button);
+            self.released.push(button);
+        }
+    }
+
+    fn set_axis(&mut self, axis: GamepadAxis, value: f32) {
+        match self.axes.iter_mut().find(|(a, _)| *a == axis) {
+            Some(entry) => entry.1 = value,
+            None => self.axes.push((axis, value)),
+        }
+    }
+
+    fn handle_event(&mut self, event: EventType) {
+        match event {
+            EventType::ButtonPressed(button, code) => {
+                if let Some(button) = GamepadButton::from_gilrs(button, code) {
+                    self.set_button(button, true);
+                }
+            },
+            EventType::ButtonReleased(button, code) => {
+                if let Some(button) = GamepadButton::from_gilrs(button, code) {
+                    self.set_button(button, false);
+                }
+            },
+            // analog triggers are reported as buttons with a value.
+            EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => self.set_axis(GamepadAxis::LeftTrigger, value),
+            EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => self.set_axis(GamepadAxis::RightTrigger, value),
+            EventType::AxisChanged(axis, value, code) => {
+                if let Some(axis) = GamepadAxis::from_gilrs(axis, code) {
+                    self.set_axis(axis, value);
+                }
+            },
+            _ => { /* NOP */ }
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The name the platform reports for the gamepad, e.g. to tell players which one is which.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the button is held down.
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        self.down.contains(&button)
+    }
+
+    /// Whether the button was pressed this frame.
+    pub fn was_pressed(&self, button: GamepadButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Whether the button was released this frame.
+    pub fn was_released(&self, button: GamepadButton) -> bool {
+        self.released.contains(&button)
+    }
+
+    /// The position of an axis, or 0.0 if the gamepad doesn't have it. Small movements of the
+    /// sticks around their center are already filtered out as a dead zone.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.iter().find(|(a, _)| *a == axis).map(|&(_, value)| value).unwrap_or(0.0)
+    }
+}
+
+/// The connected gamepads. `Window` keeps these up to date along with the mouse and keyboard,
+/// see `Window::gamepads`. Only available with the `gamepad` feature.
+///
+/// Gamepads are read through gilrs, which maps them to the layout of an Xbox controller with
+/// SDL's controller database and notices when they're connected without polling for them.
+/// Platforms gilrs doesn't support never have any gamepads. Gamepad input doesn't wake up
+/// `Window::wait_events`.
+pub struct Gamepads {
+    /// None if the platform's gamepad API couldn't be opened.
+    gilrs:  Option<Gilrs>,
+    pads:   Vec<Gamepad>,
+}
+
+impl Gamepads {
+    pub(crate) fn new() -> Gamepads {
+        let gilrs = Gilrs::new().ok();
+        let pads = gilrs.iter()
+            .flat_map(|gilrs| gilrs.gamepads())
+            .map(|(id, pad)| Gamepad::new(id.into(), pad.name().to_string()))
+            .collect();
+        Gamepads { gilrs, pads }
+    }
+
+    /// Forgets what happened during the previous frame and reads what happened since.
+    pub(crate) fn update(&mut self) {
+        for pad in self.pads.iter_mut() {
+            pad.begin_frame();
+        }
+
+        let gilrs = match self.gilrs {
+            Some(ref mut gilrs) => gilrs,
+            None => return,
+        };
+        while let Some(gilrs::Event { id: gamepad_id, event, .. }) = gilrs.next_event() {
+            let id: usize = gamepad_id.into();
+            match event {
+                EventType::Connected => {
+                    if self.pads.iter().all(|pad| pad.id != id) {
+                        let name = gilrs.gamepad(gamepad_id).name().to_string();
+                        self.pads.push(Gamepad::new(id, name));
+                    }
+                },
+                EventType::Disconnected => self.pads.retain(|pad| pad.id != id),
+                event => {
+                    if let Some(pad) = self.pads.iter_mut().find(|pad| pad.id == id) {
+                        pad.handle_event(event);
+                    }
+                },
+            }
+        }
+    }
+
+    /// The connected gamepads, in the order they were connected.
+    pub fn iter(&self) -> impl Iterator<Item = &Gamepad> {
+        self.pads.iter()
+    }
+
+    /// The gamepad that was connected first, for single player games.
+    pub fn first(&self) -> Option<&Gamepad> {
+        self.iter().next()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Gamepad> {
+        self.iter().find(|pad| pad.id == id)
+    }
+}
@@ -0,0 +1,248 @@
+//! Reports from the GL driver through `KHR_debug` (core since GL 4.3), which describes errors,
+//! performance warnings and misuse as they happen instead of through `glGetError`.
+
+use gl::types::{ GLenum, GLuint, GLsizei, GLchar };
+use std::sync::Mutex;
+use std::ffi::c_void;
+use super::opengl::{ GLErrorType, GlCapabilities, check_gl_errors };
+
+/// How important a debug message is, from least to most.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    fn from_gl(severity: GLenum) -> DebugSeverity {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        }
+    }
+}
+
+/// What produced a debug message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DebugSource {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+impl DebugSource {
+    fn from_gl(source: GLenum) -> DebugSource {
+        match source {
+            gl::DEBUG_SOURCE_API => DebugSource::Api,
+            gl::DEBUG_SOURCE_WINDOW_SYSTEM => DebugSource::WindowSystem,
+            gl::DEBUG_SOURCE_SHADER_COMPILER => DebugSource::ShaderCompiler,
+            gl::DEBUG_SOURCE_THIRD_PARTY => DebugSource::ThirdParty,
+            gl::DEBUG_SOURCE_APPLICATION => DebugSource::Application,
+            _ => DebugSource::Other,
+        }
+    }
+}
+
+/// What a debug message is about.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DebugKind {
+    Error,
+    DeprecatedBehavior,
+    UndefinedBehavior,
+    Portability,
+    Performance,
+    Marker,
+    PushGroup,
+    PopGroup,
+    Other,
+}
+
+impl DebugKind {
+    fn from_gl(kind: GLenum) -> DebugKind {
+        match kind {
+            gl::DEBUG_TYPE_ERROR => DebugKind::Error,
+            gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => DebugKind::DeprecatedBehavior,
+            gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => DebugKind::UndefinedBehavior,
+            gl::DEBUG_TYPE_PORTABILITY => DebugKind::Portability,
+            gl::DEBUG_TYPE_PERFORMANCE => DebugKind::Performance,
+            gl::DEBUG_TYPE_MARKER => DebugKind::Marker,
+            gl::DEBUG_TYPE_PUSH_GROUP => DebugKind::PushGroup,
+            gl::DEBUG_TYPE_POP_GROUP => DebugKind::PopGroup,
+            _ => DebugKind::Other,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DebugMessage<'a> {
+    pub source:     DebugSource,
+    pub kind:       DebugKind,
+    pub severity:   DebugSeverity,
+    /// Identifies the message within its source and kind, e.g. for muting a noisy warning.
+    pub id:         u32,
+    pub text:       &'a str,
+}
+
+impl std::fmt::Display for DebugMessage<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "GL {:?} ({:?}, {:?} severity): {}", self.kind, self.source, self.severity, self.text)
+    }
+}
+
+/// Receives debug messages. It may be called from a driver thread unless the output is
+/// synchronous.
+pub type DebugCallback = dyn Fn(&DebugMessage) + Send + Sync;
+
+struct DebugHandler {
+    min_severity:   DebugSeverity,
+    callback:       Box<DebugCallback>,
+}
+
+impl DebugHandler {
+    fn report(&self, message: &DebugMessage) {
+        if message.severity >= self.min_severity {
+            (self.callback)(message);
+        }
+    }
+}
+
+/// The handler that the driver was given as its user parameter, which owns it. Null while no
+/// callback is set. The lock is held while the handler is replaced so that `report` never sees
+/// one that was freed.
+static HANDLER: Mutex<HandlerPtr> = Mutex::new(HandlerPtr(std::ptr::null_mut()));
+
+struct HandlerPtr(*mut DebugHandler);
+
+// the handler is only ever read through a shared reference and `DebugCallback` is Send + Sync.
+unsafe impl Send for HandlerPtr {}
+
+/// Makes `handler` the one that messages go to and frees the previous one, after the driver was
+/// told to stop using it. The driver is only told about the new one if debug output is
+/// supported.
+fn replace_handler(handler: Option<DebugHandler>) {
+    let new = handler.map(|handler| Box::into_raw(Box::new(handler))).unwrap_or(std::ptr::null_mut());
+    let mut current = HANDLER.lock().unwrap_or_else(|err| err.into_inner());
+    if is_supported() {
+        // `on_debug_message` ignores messages without a handler, the bindings don't allow
+        // passing no callback at all.
+        unsafe {
+            gl::DebugMessageCallback(on_debug_message, new as *const c_void);
+        }
+    }
+    let old = std::mem::replace(&mut current.0, new);
+    if !old.is_null() {
+        drop(unsafe { Box::from_raw(old) });
+    }
+}
+
+/// Whether the current context can report debug messages. Contexts that were not created with
+/// the debug flag might still only report a few of them or none at all.
+pub fn is_supported() -> bool {
+    GlCapabilities::current().debug_output
+}
+
+/// Sends the driver's debug messages of at least `min_severity` to `callback`. With
+/// `synchronous` they're reported from inside of the GL call that caused them, so a breakpoint
+/// in the callback shows where they came from. Returns false if debug output isn't supported,
+/// in which case `report_gl_errors` still goes through `callback`.
+pub fn set_debug_callback<F>(min_severity: DebugSeverity, synchronous: bool, callback: F) -> bool
+    where F: Fn(&DebugMessage) + Send + Sync + 'static
+{
+    replace_handler(Some(DebugHandler { min_severity, callback: Box::new(callback) }));
+
+    if !is_supported() { return false }
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        if synchronous {
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        } else {
+            gl::Disable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+    }
+    true
+}
+
+/// Prints debug messages of at least `min_severity` to stderr.
+pub fn print_debug_messages(min_severity: DebugSeverity, synchronous: bool) -> bool {
+    set_debug_callback(min_severity, synchronous, |message| eprintln!("{}", message))
+}
+
+/// Stops the driver from reporting debug messages and frees the callback, after which
+/// `report_gl_errors` prints to stderr again.
+pub fn disable_debug_output() {
+    if is_supported() {
+        unsafe {
+            gl::Disable(gl::DEBUG_OUTPUT);
+        }
+    }
+    replace_handler(None);
+}
+
+/// Reports the errors that `glGetError` has collected, through the debug callback if one was
+/// set and to stderr otherwise. Errors are already reported as they happen while debug output
+/// is on, so this only drains them then. Returns true if an error occurred.
+pub fn report_gl_errors() -> bool {
+    check_gl_errors(|error| {
+        let text = format!("glGetError: {}", error);
+        report(&DebugMessage {
+            source: DebugSource::Api,
+            kind: DebugKind::Error,
+            severity: DebugSeverity::High,
+            id: error_id(error),
+            text: &text,
+        });
+    })
+}
+
+/// Reports misuse of the library that the driver can't detect, like `report_gl_errors` does.
+pub(crate) fn report_error(text: &str) {
+    report(&DebugMessage {
+        source: DebugSource::Application,
+        kind: DebugKind::Error,
+        severity: DebugSeverity::High,
+        id: 0,
+        text,
+    });
+}
+
+/// Sends a message to the debug callback if one was set and to stderr otherwise.
+fn report(message: &DebugMessage) {
+    let current = HANDLER.lock().unwrap_or_else(|err| err.into_inner());
+    match unsafe { current.0.as_ref() } {
+        Some(handler) => handler.report(message),
+        None => eprintln!("{}", message),
+    }
+}
+
+fn error_id(error: GLErrorType) -> u32 {
+    match error {
+        GLErrorType::NoError => gl::NO_ERROR,
+        GLErrorType::InvalidEnum => gl::INVALID_ENUM,
+        GLErrorType::InvalidValue => gl::INVALID_VALUE,
+        GLErrorType::InvalidOperation => gl::INVALID_OPERATION,
+        GLErrorType::InvalidFrameBufferOperation => gl::INVALID_FRAMEBUFFER_OPERATION,
+        GLErrorType::OutOfMemory => gl::OUT_OF_MEMORY,
+        GLErrorType::Unknown => 0,
+    }
+}
+
+extern "system" fn on_debug_message(source: GLenum, kind: GLenum, id: GLuint, severity: GLenum, length: GLsizei, text: *const GLchar, user_param: *mut c_void) {
+    if user_param.is_null() || text.is_null() { return }
+    let handler = unsafe { &*(user_param as *const DebugHandler) };
+    let bytes = unsafe { std::slice::from_raw_parts(text as *const u8, length.max(0) as usize) };
+    let text = String::from_utf8_lossy(bytes);
+    handler.report(&DebugMessage {
+        source: DebugSource::from_gl(source),
+        kind: DebugKind::from_gl(kind),
+        severity: DebugSeverity::from_gl(severity),
+        id,
+        text: text.trim_end_matches('\0').trim_end(),
+    });
+}
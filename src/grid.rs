@@ -0,0 +1,81 @@
+use super::math::Color;
+use super::opengl::Program;
+
+/// How `Context::draw_grid` draws its grid.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridStyle {
+    /// Distance between the finest grid lines in canvas units.
+    pub spacing:            f32,
+    /// Number of minor cells between major lines. Also the factor by which the spacing grows
+    /// when zooming out.
+    pub subdivisions:       f32,
+    pub minor_color:        Color,
+    pub major_color:        Color,
+    /// Line width in pixels.
+    pub line_width:         f32,
+    /// Minor lines that would be closer together than this many pixels are faded out and
+    /// replaced by the next coarser level.
+    pub min_pixel_spacing:  f32,
+}
+
+impl GridStyle {
+    pub fn new(spacing: f32, minor_color: Color, major_color: Color) -> GridStyle {
+        GridStyle {
+            spacing,
+            subdivisions: 10.0,
+            minor_color,
+            major_color,
+            line_width: 1.0,
+            min_pixel_spacing: 8.0,
+        }
+    }
+
+    /// Sets the uniforms of the grid program, which must be bound.
+    pub(crate) fn apply(&self, program: &Program) {
+        let (minor, major) = (self.minor_color, self.major_color);
+        program.set_uniform("Spacing", self.spacing);
+        program.set_uniform("Subdivisions", self.subdivisions.max(2.0));
+        program.set_uniform("MinorColor", minor);
+        program.set_uniform("MajorColor", major);
+        program.set_uniform("LineWidth", self.line_width);
+        program.set_uniform("MinPixelSpacing", self.min_pixel_spacing);
+    }
+}
+
+/// Draws grid lines at the canvas coordinates passed in as UVs.
+pub const GRID_FRAGMENT_SHADER: &str = "\
+#version 130
+
+uniform float Spacing;
+uniform float Subdivisions;
+uniform vec4  MinorColor;
+uniform vec4  MajorColor;
+uniform float LineWidth;
+uniform float MinPixelSpacing;
+in  vec2 FragUV;
+in  vec4 FragColor;
+out vec4 OutColor;
+
+float grid_line(vec2 p, float spacing) {
+    vec2 coord = p / spacing;
+    vec2 dist = abs(fract(coord - 0.5) - 0.5) / fwidth(coord);
+    return 1.0 - clamp(min(dist.x, dist.y) - (LineWidth - 1.0) * 0.5, 0.0, 1.0);
+}
+
+void main() {
+    float units_per_pixel = max(length(fwidth(FragUV)), 1e-20);
+    float lod = max(log(MinPixelSpacing * units_per_pixel / Spacing) / log(Subdivisions), 0.0);
+    float minor_spacing = Spacing * pow(Subdivisions, floor(lod));
+    float major_spacing = minor_spacing * Subdivisions;
+
+    float minor_fade = 1.0 - fract(lod);
+    vec4 minor_color = vec4(srgb_to_linear(MinorColor.rgb), MinorColor.a);
+    vec4 major_color = vec4(srgb_to_linear(MajorColor.rgb), MajorColor.a);
+    vec4 minor = minor_color * vec4(1.0, 1.0, 1.0, grid_line(FragUV, minor_spacing) * minor_fade);
+    vec4 major = mix(minor_color, major_color, minor_fade) * vec4(1.0, 1.0, 1.0, grid_line(FragUV, major_spacing));
+
+    vec4 color = major.a > 0.0 ? major : minor;
+    OutColor = color * FragColor;
+    OutColor.a *= clip_coverage();
+    OutColor.rgb *= OutColor.a;
+}\0";
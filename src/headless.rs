@@ -0,0 +1,66 @@
+use glutin::{
+    ContextTrait,
+    EventsLoop,
+};
+use super::opengl::ContextId;
+use super::surface::Surface;
+
+/// A GL context without a visible window and the surface that a headless `Context` draws
+/// into, since the context may not have a default framebuffer at all.
+pub(crate) struct HeadlessGl {
+    /// Taken on drop to delete it while the context still exists.
+    surface: Option<Surface>,
+    context_id: ContextId,
+    context: glutin::Context,
+    /// Some platforms create headless contexts through a hidden window, which belongs to it.
+    _events_loop: EventsLoop,
+}
+
+impl HeadlessGl {
+    /// Creates the context, makes it current and loads the GL functions from it.
+    pub fn new(width: u32, height: u32) -> Result<HeadlessGl, String> {
+        // winit panics instead of returning an error if there's no display server to talk to.
+        let events_loop = std::panic::catch_unwind(EventsLoop::new)
+            .map_err(|_| String::from("no display server is available for a headless context"))?;
+        let context = glutin::Context::new_headless(
+            &events_loop,
+            glutin::ContextBuilder::new(),
+            glutin::dpi::PhysicalSize::new(width as f64, height as f64),
+        ).map_err(|err| format!("failed to create a headless context: {}", err))?;
+
+        unsafe {
+            context.make_current().map_err(|err| format!("failed to make the headless context current: {:?}", err))?;
+            gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+        }
+        let context_id = ContextId::register();
+        let surface = Surface::new(width, height)?;
+
+        Ok(HeadlessGl {
+            surface: Some(surface),
+            context_id,
+            context,
+            _events_loop: events_loop,
+        })
+    }
+
+    pub fn surface(&self) -> &Surface {
+        self.surface.as_ref().expect("surface is only taken on drop")
+    }
+
+    /// Makes the context current again, e.g. after a window's context was made current.
+    pub fn make_current(&self) {
+        if self.context_id.is_current() { return }
+        unsafe {
+            self.context.make_current().expect("Failed to make the headless context current.");
+        }
+        self.context_id.make_current();
+    }
+}
+
+impl Drop for HeadlessGl {
+    fn drop(&mut self) {
+        self.make_current();
+        self.surface.take();
+        self.context_id.unregister();
+    }
+}
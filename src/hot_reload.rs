@@ -0,0 +1,92 @@
+//! Reloading shaders from files on disk while the program runs, for iterating on them without
+//! restarting. Files are polled for changes to their modification time, so this is meant for
+//! development builds rather than shipping.
+
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+use super::opengl::ShaderError;
+
+/// Polls a set of files for changes.
+#[derive(Clone, Debug, Default)]
+pub struct FileWatcher {
+    files: Vec<WatchedFile>,
+}
+
+#[derive(Clone, Debug)]
+struct WatchedFile {
+    path:       PathBuf,
+    /// None if the file didn't exist when it was last checked.
+    modified:   Option<SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher { files: Vec::new() }
+    }
+
+    /// Starts watching `path`, which doesn't have to exist yet. Its current state is what
+    /// `changed` compares against.
+    pub fn watch<P: Into<PathBuf>>(&mut self, path: P) {
+        let path = path.into();
+        let modified = modified_time(&path);
+        self.files.push(WatchedFile { path, modified });
+    }
+
+    /// Stops watching every file.
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    /// Whether any of the files was modified, created or deleted since the last call (or since
+    /// it was watched).
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for file in self.files.iter_mut() {
+            let modified = modified_time(&file.path);
+            if modified != file.modified {
+                file.modified = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Why shaders couldn't be reloaded. The programs that were in use before stay in use.
+#[derive(Debug)]
+pub enum ReloadError {
+    Io(PathBuf, io::Error),
+    Shader(ShaderError),
+}
+
+impl From<ShaderError> for ReloadError {
+    fn from(err: ShaderError) -> ReloadError {
+        ReloadError::Shader(err)
+    }
+}
+
+impl std::fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReloadError::Io(path, err) => write!(f, "failed to read {}: {}", path.display(), err),
+            ReloadError::Shader(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+/// Reads a shader file. Returns None if it doesn't exist, so the built-in shader can be used
+/// in its place.
+pub(crate) fn read_optional(path: &Path) -> Result<Option<String>, ReloadError> {
+    match std::fs::read_to_string(path) {
+        Ok(source) => Ok(Some(source)),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(ReloadError::Io(path.to_path_buf(), err)),
+    }
+}
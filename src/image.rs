@@ -0,0 +1,154 @@
+use super::opengl::{
+    Buffer,
+    Fence,
+    flip_rows_rgba,
+    Texture,
+    InternalPixelFormat,
+    PixelDataFormat,
+    PixelDataType,
+};
+
+/// Decoded RGBA8 pixels, stored top row first.
+pub struct Image {
+    pub width:  u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Decodes a PNG image into RGBA8 pixels. Grayscale, indexed and 16-bit images are
+    /// converted.
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Image, ImageError> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf)?;
+
+        let (color_type, _) = reader.output_color_type();
+        let pixel_count = (info.width * info.height) as usize;
+        let pixels = match color_type {
+            png::ColorType::RGBA => buf,
+            png::ColorType::RGB => {
+                let mut pixels = Vec::with_capacity(pixel_count * 4);
+                for rgb in buf.chunks_exact(3) {
+                    pixels.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 0xFF]);
+                }
+                pixels
+            },
+            png::ColorType::GrayscaleAlpha => {
+                let mut pixels = Vec::with_capacity(pixel_count * 4);
+                for ga in buf.chunks_exact(2) {
+                    pixels.extend_from_slice(&[ga[0], ga[0], ga[0], ga[1]]);
+                }
+                pixels
+            },
+            png::ColorType::Grayscale => {
+                let mut pixels = Vec::with_capacity(pixel_count * 4);
+                for g in buf.iter() {
+                    pixels.extend_from_slice(&[*g, *g, *g, 0xFF]);
+                }
+                pixels
+            },
+            png::ColorType::Indexed => return Err(ImageError::Unsupported("indexed color was not expanded")),
+        };
+
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+impl Texture {
+    /// Creates a texture from the bytes of a PNG file.
+    pub fn from_image_bytes(bytes: &[u8]) -> Result<Texture, ImageError> {
+        Image::from_png_bytes(bytes).map(|image| Texture::from_image(&image))
+    }
+
+    pub fn from_image(image: &Image) -> Texture {
+        Texture::new(
+            image.width, image.height,
+            InternalPixelFormat::RGBA,
+            PixelDataFormat::RGBA,
+            PixelDataType::UnsignedByte,
+            Some(&image.pixels[..]),
+        )
+    }
+
+    /// Like `from_image` but the pixels are stored as sRGB, so they're converted to linear when
+    /// sampled. Use this for color images when sRGB-correct rendering is on.
+    pub fn from_image_srgb(image: &Image) -> Texture {
+        Texture::new(
+            image.width, image.height,
+            InternalPixelFormat::SRGBA,
+            PixelDataFormat::RGBA,
+            PixelDataType::UnsignedByte,
+            Some(&image.pixels[..]),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Decoding(png::DecodingError),
+    Unsupported(&'static str),
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> ImageError {
+        ImageError::Decoding(err)
+    }
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageError::Decoding(err) => write!(f, "failed to decode image: {}", err),
+            ImageError::Unsupported(what) => write!(f, "unsupported image: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Pixels that `Context::read_pixels_async` is reading back. The readback is dropped without
+/// waiting if the image is never taken.
+pub struct PendingReadback {
+    buffer:     Buffer,
+    /// None if the context has no sync objects, in which case taking the image may wait.
+    fence:      Option<Fence>,
+    width:      u32,
+    height:     u32,
+    /// Whether the rows arrive bottom row first.
+    flip:       bool,
+}
+
+impl PendingReadback {
+    pub(crate) fn new(buffer: Buffer, fence: Option<Fence>, width: u32, height: u32, flip: bool) -> PendingReadback {
+        PendingReadback { buffer, fence, width, height, flip }
+    }
+
+    /// Whether the GPU has finished writing the pixels, so that taking them won't wait.
+    pub fn is_ready(&self) -> bool {
+        self.fence.as_ref().is_none_or(|fence| fence.is_signaled())
+    }
+
+    /// Takes the image if it's ready, or gives the readback back otherwise.
+    pub fn try_take(self) -> Result<Image, PendingReadback> {
+        if self.is_ready() { Ok(self.wait()) } else { Err(self) }
+    }
+
+    /// Takes the image, waiting for the GPU to finish writing it if necessary.
+    pub fn wait(self) -> Image {
+        let (width, height) = (self.width, self.height);
+        self.buffer.bind();
+        let mut pixels = self.buffer.read_bytes(width as usize * height as usize * 4);
+        self.buffer.unbind();
+        if self.flip {
+            flip_rows_rgba(&mut pixels, width);
+        }
+        Image { width, height, pixels }
+    }
+}
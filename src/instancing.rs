@@ -0,0 +1,126 @@
+use super::math::Color;
+use super::context::{ Vert, VertPos, VertUV, VertCol };
+use super::opengl::{ Buffer, BufferDataType };
+use super::pipeline::Pipeline;
+
+/// One rectangle drawn by `Context::rect_instanced`. `(x, y)` is the top left corner of the
+/// rectangle before it is rotated around its center.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RectInstance {
+    pub x:          f32,
+    pub y:          f32,
+    pub width:      f32,
+    pub height:     f32,
+    /// Rotation in radians around the center of the rectangle.
+    pub rotation:   f32,
+    pub color:      Color,
+}
+
+impl RectInstance {
+    pub fn new(x: f32, y: f32, width: f32, height: f32, color: Color) -> RectInstance {
+        RectInstance { x, y, width, height, rotation: 0.0, color }
+    }
+
+    pub fn with_rotation(self, rotation: f32) -> RectInstance {
+        RectInstance {
+            rotation,
+            ..self
+        }
+    }
+
+    /// The corners of the rectangle in the same order as the unit quad, for drawing it without
+    /// instancing.
+    pub(crate) fn vertices(&self) -> [Vert; 4] {
+        let (cx, cy) = (self.x + self.width / 2.0, self.y + self.height / 2.0);
+        let (sin, cos) = self.rotation.sin_cos();
+        let vcol = VertCol::new(self.color.r, self.color.g, self.color.b, self.color.a);
+        let corner = |u: f32, v: f32| {
+            let (dx, dy) = ((u - 0.5) * self.width, (v - 0.5) * self.height);
+            Vert::with_puc(VertPos::new(cx + dx * cos - dy * sin, cy + dx * sin + dy * cos), VertUV::new(u, v), vcol)
+        };
+        [corner(0.0, 0.0), corner(1.0, 0.0), corner(0.0, 1.0), corner(1.0, 1.0)]
+    }
+}
+
+/// The layout of a `RectInstance` in the instance buffer.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub(crate) struct InstanceVert {
+    rect:       [f32; 4],
+    color:      [f32; 4],
+    rotation:   f32,
+}
+
+impl InstanceVert {
+    /// `opacity` is multiplied into the alpha like it is for pushed vertices.
+    pub fn new(instance: &RectInstance, opacity: f32) -> InstanceVert {
+        let c = instance.color;
+        InstanceVert {
+            rect: [instance.x, instance.y, instance.width, instance.height],
+            color: [c.r, c.g, c.b, c.a * opacity],
+            rotation: instance.rotation,
+        }
+    }
+}
+
+impl BufferDataType for InstanceVert {}
+
+/// The unit quad that every instance is drawn from.
+pub(crate) const UNIT_QUAD: [Vert; 4] = [
+    Vert { pos: VertPos { x: 0.0, y: 0.0 }, uv: VertUV { u: 0.0, v: 0.0 }, col: VertCol { r: 1.0, g: 1.0, b: 1.0, a: 1.0 } },
+    Vert { pos: VertPos { x: 1.0, y: 0.0 }, uv: VertUV { u: 1.0, v: 0.0 }, col: VertCol { r: 1.0, g: 1.0, b: 1.0, a: 1.0 } },
+    Vert { pos: VertPos { x: 0.0, y: 1.0 }, uv: VertUV { u: 0.0, v: 1.0 }, col: VertCol { r: 1.0, g: 1.0, b: 1.0, a: 1.0 } },
+    Vert { pos: VertPos { x: 1.0, y: 1.0 }, uv: VertUV { u: 1.0, v: 1.0 }, col: VertCol { r: 1.0, g: 1.0, b: 1.0, a: 1.0 } },
+];
+pub(crate) const UNIT_QUAD_ELEMS: [u32; 6] = [0, 1, 2, 2, 3, 1];
+
+/// Adds the per-instance attributes read from `instance_buffer` to the instanced pipeline's
+/// vertex array for the stream buffer with index `stream`.
+pub(crate) fn bind_instance_attributes(pipeline: &Pipeline, stream: usize, instance_buffer: &Buffer) {
+    let program = &pipeline.program;
+    let attribs = [
+        (program.attrib_location("InstanceRect\0"), 4, 0),
+        (program.attrib_location("InstanceColor\0"), 4, 4),
+        (program.attrib_location("InstanceRotation\0"), 1, 8),
+    ];
+
+    pipeline.vertex_arrays[stream].bind();
+    let _buffer = instance_buffer.bind_scoped();
+    unsafe {
+        let szfloat = std::mem::size_of::<f32>();
+        let stride = std::mem::size_of::<InstanceVert>() as i32;
+        for &(attrib, size, offset) in attribs.iter() {
+            if attrib < 0 { continue }
+            gl::EnableVertexAttribArray(attrib as _);
+            gl::VertexAttribPointer(attrib as _, size, gl::FLOAT, gl::FALSE, stride, (offset * szfloat) as *const _);
+            gl::VertexAttribDivisor(attrib as _, 1);
+        }
+    }
+}
+
+/// Positions the unit quad for each instance. Used with the default fragment shader.
+pub const INSTANCED_VERTEX_SHADER: &str = "\
+#version 130
+
+uniform mat4 Transform;
+uniform mat4 ProjMtx;
+in  vec2 Position;
+in  vec4 InstanceRect;
+in  vec4 InstanceColor;
+in  float InstanceRotation;
+out vec2 FragUV;
+out vec4 FragColor;
+out vec2 FragScreenPos;
+
+void main() {
+    vec2 local = (Position - 0.5) * InstanceRect.zw;
+    float s = sin(InstanceRotation);
+    float c = cos(InstanceRotation);
+    vec2 pos = InstanceRect.xy + InstanceRect.zw * 0.5 + vec2(local.x * c - local.y * s, local.x * s + local.y * c);
+
+    FragUV = Position;
+    FragColor = vec4(srgb_to_linear(InstanceColor.rgb), InstanceColor.a);
+    vec4 screen_pos = Transform * vec4(pos, 0.0, 1.0);
+    FragScreenPos = screen_pos.xy;
+    gl_Position = ProjMtx * screen_pos;
+}\0";
@@ -1,7 +1,46 @@
 pub mod opengl;
 pub mod math;
 pub mod context;
+pub mod frame_log;
 pub mod window;
+pub mod image;
+pub mod marker;
+pub mod grid;
+pub mod surface;
+pub mod streaming;
+pub mod view;
+pub mod sprite;
+pub mod palette;
+pub mod presenter;
+pub mod camera;
+pub mod blend;
+pub mod damage;
+pub mod shortcuts;
+pub mod timer;
+pub mod instancing;
+pub mod mesh;
+pub mod draw_list;
+pub mod vertex_layout;
+pub mod gl_debug;
+pub mod hot_reload;
+pub mod effect;
+pub mod program_cache;
+pub mod mouse;
+pub mod event;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod touch;
+pub mod frame_clock;
+
+mod pipeline;
+mod clipboard;
+mod headless;
+mod swap_control;
 
 pub use self::context::Context;
-pub use self::window::Window;
+pub use self::window::{ Window, WindowBuilder, Frame, GlVersion, FullscreenMode, ScalePolicy };
+pub use self::event::Event;
+pub use self::shortcuts::Shortcuts;
+pub use self::opengl::{ Texture, TextureFormat, TextureFilter, TextureWrap, CompressedFormat, StateGuard, ShaderError, TextureUnit };
+pub use self::surface::Surface;
+pub use self::streaming::StreamingTexture;
@@ -0,0 +1,128 @@
+use super::math::Color;
+use super::opengl::Program;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MarkerShape {
+    Circle,
+    Square,
+    Cross,
+    Triangle,
+}
+
+impl MarkerShape {
+    /// The value of the `Shape` uniform in the marker fragment shader.
+    pub(crate) fn as_uniform(self) -> i32 {
+        match self {
+            MarkerShape::Circle => 0,
+            MarkerShape::Square => 1,
+            MarkerShape::Cross => 2,
+            MarkerShape::Triangle => 3,
+        }
+    }
+}
+
+/// How a plot marker is drawn. `size` is the width of the marker and `outline_width` is drawn
+/// inside of it, both in canvas units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MarkerStyle {
+    pub shape:          MarkerShape,
+    pub size:           f32,
+    pub fill:           Color,
+    pub outline:        Color,
+    pub outline_width:  f32,
+}
+
+impl MarkerStyle {
+    pub fn new(shape: MarkerShape, size: f32, fill: Color) -> MarkerStyle {
+        MarkerStyle {
+            shape,
+            size,
+            fill,
+            outline: fill,
+            outline_width: 0.0,
+        }
+    }
+
+    pub fn with_outline(self, outline: Color, outline_width: f32) -> MarkerStyle {
+        MarkerStyle {
+            outline,
+            outline_width,
+            ..self
+        }
+    }
+}
+
+/// Marker parameters that are passed to the shader as uniforms. Consecutive markers only share a
+/// draw call if these are equal.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MarkerUniforms {
+    pub shape:          MarkerShape,
+    pub outline:        Color,
+    /// Outline width relative to the marker's radius.
+    pub outline_width:  f32,
+}
+
+impl MarkerUniforms {
+    pub fn from_style(style: &MarkerStyle) -> MarkerUniforms {
+        let radius = style.size / 2.0;
+        MarkerUniforms {
+            shape: style.shape,
+            outline: style.outline,
+            outline_width: if radius > 0.0 { style.outline_width / radius } else { 0.0 },
+        }
+    }
+
+    /// Sets the uniforms of the marker program, which must be bound.
+    pub fn apply(&self, program: &Program) {
+        program.set_uniform("Shape", self.shape.as_uniform());
+        program.set_uniform("OutlineColor", self.outline);
+        program.set_uniform("OutlineWidth", self.outline_width);
+    }
+}
+
+/// Draws markers as signed distance fields on quads whose UVs span [-1, 1] across the marker.
+pub const MARKER_FRAGMENT_SHADER: &str = "\
+#version 130
+
+uniform int   Shape;
+uniform vec4  OutlineColor;
+uniform float OutlineWidth;
+in  vec2 FragUV;
+in  vec4 FragColor;
+out vec4 OutColor;
+
+float sd_box(vec2 p, vec2 b) {
+    vec2 d = abs(p) - b;
+    return length(max(d, 0.0)) + min(max(d.x, d.y), 0.0);
+}
+
+// an equilateral triangle whose corners lie on the unit circle, so it fits inside of the quad
+// like the other shapes.
+float sd_triangle(vec2 p) {
+    const float k = sqrt(3.0);
+    const float scale = 2.0 / k;
+    p *= scale;
+    p.y = -p.y; // screen space y points down.
+    p.x = abs(p.x) - 1.0;
+    p.y = p.y + 1.0 / k;
+    if (p.x + k * p.y > 0.0) p = vec2(p.x - k * p.y, -k * p.x - p.y) / 2.0;
+    p.x -= clamp(p.x, -2.0, 0.0);
+    return -length(p) * sign(p.y) / scale;
+}
+
+float marker_distance(vec2 p) {
+    if (Shape == 0) return length(p) - 1.0;
+    if (Shape == 1) return sd_box(p, vec2(1.0));
+    if (Shape == 2) return min(sd_box(p, vec2(1.0, 0.3)), sd_box(p, vec2(0.3, 1.0)));
+    return sd_triangle(p);
+}
+
+void main() {
+    float d = marker_distance(FragUV);
+    float aa = fwidth(d);
+    float coverage = 1.0 - smoothstep(-aa, aa, d);
+    float outline = OutlineWidth > 0.0 ? smoothstep(-aa, aa, d + OutlineWidth) : 0.0;
+    vec4 color = mix(FragColor, vec4(srgb_to_linear(OutlineColor.rgb), OutlineColor.a), outline);
+    OutColor = vec4(color.rgb, color.a * coverage * clip_coverage());
+    OutColor.rgb *= OutColor.a;
+}\0";
@@ -9,9 +9,8 @@ use cgmath::{
     Matrix4,
     Decomposed,
 };
-use cgmath::prelude::*;
 
-pub use cgmath::Matrix;
+pub use cgmath::{ Matrix, SquareMatrix };
 
 pub type Vec2f = Vector2<f32>;
 pub type Vec3f = Vector3<f32>;
@@ -57,6 +56,7 @@ pub fn radf(theta: f32) -> Radf {
     Rad(theta)
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -77,7 +77,7 @@ impl Color {
             r: (r as f32) / 255.0,
             g: (g as f32) / 255.0,
             b: (b as f32) / 255.0,
-            a: a,
+            a,
         }
     }
 
@@ -85,6 +85,91 @@ impl Color {
     pub fn rgb(r: u8, g: u8, b: u8) -> Color {
         Color::rgba(r, g, b, 1.0)
     }
+
+    /// Converts the color from sRGB to linear RGB. Alpha is left alone.
+    pub fn to_linear(self) -> Color {
+        let linear = |c: f32| if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+        Color {
+            r: linear(self.r),
+            g: linear(self.g),
+            b: linear(self.b),
+            a: self.a,
+        }
+    }
+}
+
+/// An axis aligned rectangle in screen coordinates where `(x, y)` is the top left corner.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    #[inline]
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    #[inline]
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    #[inline]
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    #[inline]
+    pub fn area(&self) -> f32 {
+        self.width.max(0.0) * self.height.max(0.0)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+
+    /// The area covered by both rectangles. Empty if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        Rect::new(x, y, (self.right().min(other.right()) - x).max(0.0), (self.bottom().min(other.bottom()) - y).max(0.0))
+    }
+
+    /// The smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Rect::new(x, y, self.right().max(other.right()) - x, self.bottom().max(other.bottom()) - y)
+    }
+}
+
+/// Distances inset from each edge of a rectangle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Margins {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Margins {
+    #[inline]
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Margins {
+        Margins { left, top, right, bottom }
+    }
+
+    #[inline]
+    pub fn uniform(margin: f32) -> Margins {
+        Margins::new(margin, margin, margin, margin)
+    }
+}
+
+#[inline]
+pub fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+    Rect::new(x, y, width, height)
 }
 
 #[inline]
@@ -180,6 +265,30 @@ pub mod transform {
         )
     }
 
+    /// Shears x by `skew_x` times y and y by `skew_y` times x. The factors are the tangents of
+    /// the angles that vertical and horizontal lines are slanted by.
+    #[inline]
+    pub fn skew(skew_x: f32, skew_y: f32) -> Mat4f {
+        Matrix4::new(
+            1.0, skew_y, 0.0, 0.0,
+            skew_x, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        )
+    }
+
+    /// Mirrors horizontally around the vertical line at `x = around`.
+    #[inline]
+    pub fn flip_x(around: f32) -> Mat4f {
+        merge_all(&[translate(-around, 0.0), scale(-1.0, 1.0), translate(around, 0.0)])
+    }
+
+    /// Mirrors vertically around the horizontal line at `y = around`.
+    #[inline]
+    pub fn flip_y(around: f32) -> Mat4f {
+        merge_all(&[translate(0.0, -around), scale(1.0, -1.0), translate(0.0, around)])
+    }
+
     #[inline]
     pub fn apply2(t: Mat4f, v: Vec2f) -> Vec2f {
         let e = apply3(t, vec3f(v.x, v.y, 1.0));
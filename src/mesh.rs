@@ -0,0 +1,56 @@
+use super::context::Vert;
+use super::opengl::{ Buffer, BufferType, BufferUsage, VertexArray };
+use super::pipeline::Pipeline;
+
+/// Geometry that is uploaded to the GPU once and can then be drawn any number of times with
+/// `Context::draw_mesh`, without going through the context's vertex batches. Created with
+/// `Context::create_mesh`.
+///
+/// A copy of the vertices is kept so the mesh can still be drawn inside of `draw_views`,
+/// `draw_sorted` and clip masks, whose batches are replayed from their vertices.
+pub struct Mesh {
+    vertex_array:   VertexArray,
+    _vertex_buffer: Buffer,
+    _elems_buffer:  Buffer,
+    vertices:       Vec<Vert>,
+    elements:       Vec<u32>,
+}
+
+impl Mesh {
+    pub(crate) fn new(pipeline: &Pipeline, vertices: &[Vert], elements: &[u32]) -> Mesh {
+        debug_assert!(elements.len() % 3 == 0, "number of elements must be a multiple of 3");
+        debug_assert!(elements.iter().all(|&e| (e as usize) < vertices.len()), "element is larger than the number of vertices provided");
+
+        let vertex_buffer = Buffer::new(BufferType::ArrayBuffer);
+        let vertex_array = pipeline.vertex_array_for(&vertex_buffer);
+        vertex_buffer.set_data(vertices, BufferUsage::StaticDraw);
+
+        let elems_buffer = Buffer::new(BufferType::ElementArrayBuffer);
+        vertex_array.set_element_buffer(&elems_buffer);
+        elems_buffer.set_data(elements, BufferUsage::StaticDraw);
+
+        vertex_array.set_label("canvas.mesh");
+        vertex_buffer.set_label("canvas.mesh.vertices");
+        elems_buffer.set_label("canvas.mesh.elements");
+
+        Mesh {
+            vertex_array,
+            _vertex_buffer: vertex_buffer,
+            _elems_buffer: elems_buffer,
+            vertices: vertices.to_vec(),
+            elements: elements.to_vec(),
+        }
+    }
+
+    pub(crate) fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+
+    pub fn vertices(&self) -> &[Vert] {
+        &self.vertices
+    }
+
+    pub fn elements(&self) -> &[u32] {
+        &self.elements
+    }
+}
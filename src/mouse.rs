@@ -0,0 +1,175 @@
+use glutin::{
+    ElementState,
+    MouseButton,
+    MouseScrollDelta,
+    WindowEvent,
+};
+
+/// The state of the mouse over a window, as of the last call to `Window::handle_events` or
+/// `Window::wait_events`. "This frame" means since the call before that.
+#[derive(Clone, Debug)]
+pub struct Mouse {
+    /// In logical pixels, relative to the top left corner of the window's client area.
+    position:   (f32, f32),
+    delta:      (f32, f32),
+    /// Motion reported by the device this frame, which doesn't stop at the window's edges.
+    raw_delta:  (f32, f32),
+    /// Physical pixels per logical pixel when the position was last updated.
+    dpi_factor: f32,
+    /// False until the cursor has moved over the window for the first time.
+    has_moved:  bool,
+    inside:     bool,
+    /// Raw motion only counts while the window has focus, since it's reported for any window.
+    focused:    bool,
+    down:       Vec<MouseButton>,
+    pressed:    Vec<MouseButton>,
+    released:   Vec<MouseButton>,
+    /// Scrolling this frame from wheels that scroll in steps, in lines.
+    scroll_lines:   (f32, f32),
+    /// Scrolling this frame from trackpads and other smooth scrolling, in logical pixels.
+    scroll_pixels:  (f32, f32),
+}
+
+impl Mouse {
+    pub(crate) fn new(dpi_factor: f32) -> Mouse {
+        Mouse {
+            position:   (0.0, 0.0),
+            delta:      (0.0, 0.0),
+            raw_delta:  (0.0, 0.0),
+            dpi_factor,
+            has_moved:  false,
+            inside:     false,
+            focused:    true,
+            down:       Vec::new(),
+            pressed:    Vec::new(),
+            released:   Vec::new(),
+            scroll_lines:   (0.0, 0.0),
+            scroll_pixels:  (0.0, 0.0),
+        }
+    }
+
+    /// Forgets what happened during the previous frame.
+    pub(crate) fn begin_frame(&mut self) {
+        self.delta = (0.0, 0.0);
+        self.raw_delta = (0.0, 0.0);
+        self.pressed.clear();
+        self.released.clear();
+        self.scroll_lines = (0.0, 0.0);
+        self.scroll_pixels = (0.0, 0.0);
+    }
+
+    pub(crate) fn handle_event(&mut self, event: &WindowEvent, dpi_factor: f32) {
+        self.dpi_factor = dpi_factor;
+        match *event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = (position.x as f32, position.y as f32);
+                // the first position isn't a movement from wherever the cursor was assumed to be.
+                if self.has_moved {
+                    self.delta.0 += position.0 - self.position.0;
+                    self.delta.1 += position.1 - self.position.1;
+                }
+                self.position = position;
+                self.has_moved = true;
+            },
+            WindowEvent::CursorEntered { .. } => self.inside = true,
+            WindowEvent::CursorLeft { .. } => self.inside = false,
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => {
+                if !self.down.contains(&button) {
+                    self.down.push(button);
+                }
+                self.pressed.push(button);
+            },
+            WindowEvent::MouseInput { state: ElementState::Released, button, .. } => {
+                self.down.retain(|&b| b != button);
+                self.released.push(button);
+            },
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(x, y), .. } => {
+                self.scroll_lines.0 += x;
+                self.scroll_lines.1 += y;
+            },
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::PixelDelta(delta), .. } => {
+                self.scroll_pixels.0 += delta.x as f32;
+                self.scroll_pixels.1 += delta.y as f32;
+            },
+            // buttons that are released while the window isn't focused never report it.
+            WindowEvent::Focused(false) => {
+                self.down.clear();
+                self.focused = false;
+            },
+            WindowEvent::Focused(true) => self.focused = true,
+            _ => { /* NOP */ }
+        }
+    }
+
+    pub(crate) fn handle_motion(&mut self, dx: f64, dy: f64) {
+        if !self.focused { return }
+        self.raw_delta.0 += dx as f32;
+        self.raw_delta.1 += dy as f32;
+    }
+
+    /// The cursor position in logical pixels.
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// The cursor position in physical pixels of the framebuffer.
+    pub fn physical_position(&self) -> (f32, f32) {
+        (self.position.0 * self.dpi_factor, self.position.1 * self.dpi_factor)
+    }
+
+    /// How far the cursor moved this frame, in logical pixels.
+    pub fn delta(&self) -> (f32, f32) {
+        self.delta
+    }
+
+    /// How far the cursor moved this frame, in physical pixels.
+    pub fn physical_delta(&self) -> (f32, f32) {
+        (self.delta.0 * self.dpi_factor, self.delta.1 * self.dpi_factor)
+    }
+
+    /// How far the mouse itself moved this frame while the window had focus, in units of the
+    /// device (usually counts, before pointer acceleration). Unlike `delta` this keeps going
+    /// when the cursor is grabbed or at the edge of the screen, which suits camera controls.
+    pub fn raw_delta(&self) -> (f32, f32) {
+        self.raw_delta
+    }
+
+    /// How far mouse wheels scrolled this frame, in lines (or notches). Positive y scrolls up,
+    /// away from the user, and positive x to the right.
+    pub fn scroll_lines(&self) -> (f32, f32) {
+        self.scroll_lines
+    }
+
+    /// How far trackpads and other devices that scroll smoothly scrolled this frame, in logical
+    /// pixels, with the same directions as `scroll_lines`.
+    pub fn scroll_pixels(&self) -> (f32, f32) {
+        self.scroll_pixels
+    }
+
+    /// All scrolling this frame in logical pixels, with lines counted as `line_height` pixels
+    /// each. Handy for zooming, where both kinds of devices should work.
+    pub fn scroll(&self, line_height: f32) -> (f32, f32) {
+        (self.scroll_pixels.0 + self.scroll_lines.0 * line_height, self.scroll_pixels.1 + self.scroll_lines.1 * line_height)
+    }
+
+    /// Whether the cursor is over the window's client area.
+    pub fn is_inside(&self) -> bool {
+        self.inside
+    }
+
+    /// Whether the button is held down.
+    pub fn is_down(&self, button: MouseButton) -> bool {
+        self.down.contains(&button)
+    }
+
+    /// Whether the button was pressed this frame. A click that is shorter than a frame is both
+    /// pressed and released without ever being down.
+    pub fn was_pressed(&self, button: MouseButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Whether the button was released this frame.
+    pub fn was_released(&self, button: MouseButton) -> bool {
+        self.released.contains(&button)
+    }
+}
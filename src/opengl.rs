@@ -1,5 +1,5 @@
-///! A thin opengl wrapper. You'll probably still have to use raw opengl calls most of the time but
-///  this should at least handle the lifetime of the more common objects like buffers and textures.
+//! A thin opengl wrapper. You'll probably still have to use raw opengl calls most of the time but
+//! this should at least handle the lifetime of the more common objects like buffers and textures.
 
 use gl::types::{
     GLuint,
@@ -8,7 +8,10 @@ use gl::types::{
 };
 use std::{ mem, ptr };
 use std::borrow::Cow;
+use std::cell::{ Cell, RefCell };
+use std::collections::HashMap;
 use std::ffi::{ CStr, CString };
+use super::math::{ Vec2f, Vec3f, Vec4f, Mat3f, Mat4f, Color };
 
 #[derive(Copy, Clone)]
 pub enum BufferUsage {
@@ -43,10 +46,14 @@ impl BufferUsage {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum BufferType {
     ArrayBuffer,
     ElementArrayBuffer,
+    /// Pixels are uploaded to textures from a buffer bound here instead of from CPU memory.
+    PixelUnpackBuffer,
+    /// Pixels are read back into a buffer bound here instead of into CPU memory.
+    PixelPackBuffer,
 }
 
 impl BufferType {
@@ -54,11 +61,14 @@ impl BufferType {
         match self {
             BufferType::ArrayBuffer => gl::ARRAY_BUFFER,
             BufferType::ElementArrayBuffer => gl::ELEMENT_ARRAY_BUFFER,
+            BufferType::PixelUnpackBuffer => gl::PIXEL_UNPACK_BUFFER,
+            BufferType::PixelPackBuffer => gl::PIXEL_PACK_BUFFER,
         }
     }
 }
 
-pub struct Buffer(GLuint, BufferType);
+/// The size of the buffer's storage in bytes is tracked so that uploads can reuse it.
+pub struct Buffer(GLuint, BufferType, Cell<usize>, Option<ContextId>);
 
 impl Buffer {
     pub fn new(buffer_type: BufferType) -> Buffer {
@@ -66,34 +76,123 @@ impl Buffer {
         unsafe {
             gl::GenBuffers(1, &mut buffer_id);
         }
-        Buffer(buffer_id, buffer_type)
+        Buffer(buffer_id, buffer_type, Cell::new(0), ContextId::current())
     }
 
     pub fn bind(&self) {
+        bindings::bind_buffer(self.1, self.0);
+    }
+
+    /// Names the buffer for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        // names from glGen* only become objects once they're bound. A target that isn't part of
+        // the vertex array state is used, so element buffers don't end up in the bound one.
+        unsafe {
+            if gl::IsBuffer(self.0) == gl::FALSE {
+                gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.0);
+                gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+            }
+        }
+        set_object_label(gl::BUFFER, self.0, label);
+    }
+
+    /// Binds the buffer until the returned guard is dropped, which binds the buffer that was
+    /// bound before again.
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        let previous = bindings::buffer(self.1);
+        self.bind();
+        BindGuard::new(Binding::Buffer(self.1, previous))
+    }
+
+    /// Binds 0 in place of this buffer. Pixel buffers have to be unbound after use, since
+    /// every upload or readback goes through a bound one instead of CPU memory.
+    pub fn unbind(&self) {
+        bindings::bind_buffer(self.1, 0);
+    }
+
+    /// Allocates `size` bytes of new storage with undefined contents, which orphans the old
+    /// storage. The buffer must be bound.
+    pub fn allocate(&self, size: usize, usage: BufferUsage) {
+        unsafe {
+            gl::BufferData(self.1.as_gl(), size as isize, ptr::null(), usage.as_gl());
+        }
+        self.2.set(size);
+    }
+
+    /// Copies the first `len` bytes of the buffer into CPU memory by mapping it. The buffer
+    /// must be bound. This waits for the GPU if it's still writing into the buffer.
+    pub fn read_bytes(&self, len: usize) -> Vec<u8> {
+        let len = len.min(self.capacity());
+        let target = self.1.as_gl();
+        let mut bytes = vec![0u8; len];
+        if len == 0 { return bytes }
         unsafe {
-            gl::BindBuffer(self.1.as_gl(), self.0);
+            let mapped = gl::MapBufferRange(target, 0, len as isize, gl::MAP_READ_BIT);
+            if !mapped.is_null() {
+                ptr::copy_nonoverlapping(mapped as *const u8, bytes.as_mut_ptr(), len);
+                gl::UnmapBuffer(target);
+            }
         }
+        bytes
+    }
+
+    /// The size of the buffer's storage in bytes.
+    pub fn capacity(&self) -> usize {
+        self.2.get()
     }
 
+    /// Allocates new storage of exactly the size of `data` and fills it.
     #[inline]
     pub fn set_data<DataType: BufferDataType>(&self, data: &[DataType], usage: BufferUsage) {
         unsafe {
-            let data_type_size = mem::size_of::<DataType>();
-            let buffer_size = data.len() * data_type_size;
-            gl::BufferData(self.1.as_gl(), buffer_size as isize, mem::transmute(data.as_ptr()), usage.as_gl());
+            let buffer_size = mem::size_of_val(data);
+            gl::BufferData(self.1.as_gl(), buffer_size as isize, data.as_ptr() as *const _, usage.as_gl());
+            self.2.set(buffer_size);
+        }
+    }
+
+    /// Writes `data` to the start of the buffer. The storage is only reallocated if `data`
+    /// doesn't fit into it, so repeated uploads only allocate until the largest one is reached.
+    pub fn update_data<DataType: BufferDataType>(&self, data: &[DataType], usage: BufferUsage) {
+        let buffer_size = mem::size_of_val(data);
+        if buffer_size > self.capacity() {
+            self.set_data(data, usage);
+        } else {
+            unsafe {
+                gl::BufferSubData(self.1.as_gl(), 0, buffer_size as isize, data.as_ptr() as *const _);
+            }
+        }
+    }
+
+    /// Replaces the contents of the buffer with data that is only drawn once or a few times.
+    /// The old storage is orphaned first, which lets the driver hand out fresh memory instead
+    /// of waiting for draw calls that are still reading from it. The new storage has the size
+    /// of the largest data streamed so far, which drivers can usually recycle without
+    /// allocating.
+    pub fn stream_data<DataType: BufferDataType>(&self, data: &[DataType]) {
+        let target = self.1.as_gl();
+        let buffer_size = mem::size_of_val(data);
+        let capacity = self.capacity().max(buffer_size);
+        unsafe {
+            gl::BufferData(target, capacity as isize, ptr::null(), gl::STREAM_DRAW);
+            gl::BufferSubData(target, 0, buffer_size as isize, data.as_ptr() as *const _);
         }
+        self.2.set(capacity);
     }
 }
 
 impl Drop for Buffer {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(1, &self.0);
-        }
+        delete_shared(self.3, GlObject::Buffer(self.0));
     }
 }
 
-pub struct VertexArray(GLuint);
+/// A vertex array object. Along with the attribute pointers it records which element buffer
+/// is bound, so that should be set once with `set_element_buffer` instead of binding it before
+/// every draw call. Core profiles (and macOS in particular) don't have a default vertex array,
+/// so element buffers can't be bound at all without one.
+pub struct VertexArray(GLuint, Option<ContextId>, Cell<Option<GLuint>>);
 
 impl VertexArray {
     pub fn new() -> VertexArray {
@@ -101,25 +200,51 @@ impl VertexArray {
         unsafe {
             gl::GenVertexArrays(1, &mut vertex_array_id);
         }
-        VertexArray(vertex_array_id)
+        VertexArray(vertex_array_id, ContextId::current(), Cell::new(None))
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.0);
-        }
+        bindings::bind_vertex_array(self.0, self.2.get());
+    }
+
+    /// Binds the vertex array and records `buffer` in it as the element buffer that draw calls
+    /// read indices from. The vertex array is left bound.
+    pub fn set_element_buffer(&self, buffer: &Buffer) {
+        debug_assert!(buffer.1 == BufferType::ElementArrayBuffer, "the element buffer of a vertex array must be an element array buffer");
+        self.bind();
+        buffer.bind();
+        self.2.set(Some(buffer.0));
+    }
+
+    /// Names the vertex array for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        let _vertex_array = self.bind_scoped();
+        set_object_label(gl::VERTEX_ARRAY, self.0, label);
+    }
+
+    /// Binds the vertex array until the returned guard is dropped. The element array buffer
+    /// binding belongs to the vertex array, so it's restored along with it.
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        let previous = bindings::vertex_array();
+        self.bind();
+        BindGuard::new(Binding::VertexArray(previous))
+    }
+}
+
+impl Default for VertexArray {
+    fn default() -> VertexArray {
+        VertexArray::new()
     }
 }
 
 impl Drop for VertexArray {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &self.0);
-        }
+        delete_unshared(self.1, GlObject::VertexArray(self.0));
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -134,10 +259,51 @@ impl ShaderType {
     }
 }
 
-pub struct Shader(GLuint, ShaderType);
+/// Why shaders couldn't be built. Compile and link errors carry the complete info log of the GL
+/// driver, which says where in the source things went wrong.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShaderError {
+    Compile { shader_type: ShaderType, log: String },
+    Link { log: String },
+    /// The context's GL version, which can't run the built-in shaders.
+    UnsupportedVersion(String),
+}
+
+impl ShaderError {
+    /// The info log of the GL driver, which is empty if nothing was compiled.
+    pub fn log(&self) -> &str {
+        match self {
+            ShaderError::Compile { log, .. } | ShaderError::Link { log } => log,
+            ShaderError::UnsupportedVersion(_) => "",
+        }
+    }
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ShaderError::Compile { shader_type: ShaderType::Vertex, log } => write!(f, "failed to compile vertex shader:\n{}", log),
+            ShaderError::Compile { shader_type: ShaderType::Fragment, log } => write!(f, "failed to compile fragment shader:\n{}", log),
+            ShaderError::Link { log } => write!(f, "failed to link program:\n{}", log),
+            ShaderError::UnsupportedVersion(version) => write!(f, "GL {} is not supported, GL 3.0 or GL ES 3.0 is required", version),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Turns an info log that was read into `buf` into a string without the NUL terminator.
+fn info_log(mut buf: Vec<u8>) -> String {
+    while buf.last() == Some(&0) {
+        buf.pop();
+    }
+    String::from_utf8_lossy(&buf).trim_end().to_string()
+}
+
+pub struct Shader(GLuint, ShaderType, Option<ContextId>);
 
 impl Shader {
-    pub fn compile(shader_type: ShaderType, source: &str) -> Result<Shader, String> {
+    pub fn compile(shader_type: ShaderType, source: &str) -> Result<Shader, ShaderError> {
         let zsource = as_zero_str(source);
         unsafe {
             let handle = gl::CreateShader(shader_type.as_gl());
@@ -145,19 +311,31 @@ impl Shader {
             gl::CompileShader(handle);
 
             if Self::is_compile_success(handle) {
-                Ok(Shader(handle, shader_type))
+                Ok(Shader(handle, shader_type, ContextId::current()))
             } else {
-                Err(Self::get_error(handle))
+                let log = Self::get_error(handle);
+                gl::DeleteShader(handle);
+                Err(ShaderError::Compile { shader_type, log })
             }
         }
     }
 
+    /// Names the shader for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        set_object_label(gl::SHADER, self.0, label);
+    }
+
+    pub fn shader_type(&self) -> ShaderType {
+        self.1
+    }
+
     fn is_compile_success(handle: GLuint) -> bool {
         let mut status: GLint = 0;
         unsafe {
             gl::GetShaderiv(handle, gl::COMPILE_STATUS, &mut status);
         }
-        return (status as GLboolean) != gl::FALSE;
+        (status as GLboolean) != gl::FALSE
     }
 
     fn get_error(handle: GLuint) -> String {
@@ -166,12 +344,11 @@ impl Shader {
             gl::GetShaderiv(handle, gl::INFO_LOG_LENGTH, &mut log_length);
         }
         if log_length > 0 {
-            let mut buf: Vec<u8> = Vec::with_capacity(log_length as usize);
-            buf.resize(log_length as usize, 0);
+            let mut buf: Vec<u8> = vec![0; log_length as usize];
             unsafe {
-                gl::GetShaderInfoLog(handle, log_length, ptr::null_mut(), mem::transmute(buf.as_mut_ptr()));
-                String::from_utf8_unchecked(buf)
+                gl::GetShaderInfoLog(handle, log_length, ptr::null_mut(), buf.as_mut_ptr() as *mut _);
             }
+            info_log(buf)
         } else {
             String::new()
         }
@@ -180,35 +357,101 @@ impl Shader {
 
 impl Drop for Shader {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteShader(self.0);
-        }
+        delete_shared(self.2, GlObject::Shader(self.0));
     }
 }
 
-pub struct Program(GLuint);
+/// A linked program. The locations of uniforms that are looked up by name are cached.
+pub struct Program(GLuint, RefCell<HashMap<String, GLint>>, Option<ContextId>);
 
 impl Program {
-    pub fn link(shaders: &[&Shader]) -> Result<Program, String> {
+    pub fn link(shaders: &[&Shader]) -> Result<Program, ShaderError> {
+        Program::link_with_outputs(shaders, &[])
+    }
+
+    /// Links a program whose fragment shader writes the `out` variables named in `outputs` to
+    /// the color attachments with the same index, for drawing into a `Framebuffer` with several
+    /// color attachments. GLSL ES has no way to bind them at link time, so there the outputs
+    /// need `layout(location = N)` qualifiers instead.
+    pub fn link_with_outputs(shaders: &[&Shader], outputs: &[&str]) -> Result<Program, ShaderError> {
         unsafe {
             let handle = gl::CreateProgram();
             for shader in shaders.iter() {
                 gl::AttachShader(handle, shader.0);
             }
+            if gl::BindFragDataLocation::is_loaded() {
+                for (index, name) in outputs.iter().enumerate() {
+                    let name = CString::new(*name).unwrap_or_default();
+                    gl::BindFragDataLocation(handle, index as GLuint, name.as_ptr());
+                }
+            }
+            if gl::ProgramParameteri::is_loaded() {
+                // some drivers only keep the binary around if they're told it will be asked for.
+                gl::ProgramParameteri(handle, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as _);
+            }
             gl::LinkProgram(handle);
 
             if Self::is_link_success(handle) {
-                Ok(Program(handle))
+                Ok(Program(handle, RefCell::new(HashMap::new()), ContextId::current()))
             } else {
-                Err(Self::get_error(handle))
+                let log = Self::get_error(handle);
+                gl::DeleteProgram(handle);
+                Err(ShaderError::Link { log })
             }
         }
     }
 
-    pub fn bind(&self) {
+    /// Recreates a program from a binary that `binary` returned. Returns None if the driver
+    /// rejects it, which happens whenever the driver or the GPU changed since it was saved.
+    pub fn from_binary(binary: &ProgramBinary) -> Option<Program> {
+        if !gl::ProgramBinary::is_loaded() { return None }
+        unsafe {
+            let handle = gl::CreateProgram();
+            gl::ProgramBinary(handle, binary.format, binary.data.as_ptr() as *const _, binary.data.len() as _);
+            if Self::is_link_success(handle) {
+                Some(Program(handle, RefCell::new(HashMap::new()), ContextId::current()))
+            } else {
+                gl::DeleteProgram(handle);
+                None
+            }
+        }
+    }
+
+    /// The driver's compiled form of the linked program, or None if the driver can't provide
+    /// one (`glGetProgramBinary` needs GL 4.1 or `ARB_get_program_binary`).
+    pub fn binary(&self) -> Option<ProgramBinary> {
+        if !gl::GetProgramBinary::is_loaded() { return None }
+        let mut length: GLint = 0;
+        unsafe {
+            gl::GetProgramiv(self.0, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        if length <= 0 { return None }
+
+        let mut data = vec![0u8; length as usize];
+        let (mut written, mut format) = (0, 0);
         unsafe {
-            gl::UseProgram(self.0);
+            gl::GetProgramBinary(self.0, length, &mut written, &mut format, data.as_mut_ptr() as *mut _);
         }
+        if written <= 0 { return None }
+        data.truncate(written as usize);
+        Some(ProgramBinary { format, data })
+    }
+
+    pub fn bind(&self) {
+        bindings::use_program(self.0);
+    }
+
+    /// Names the program for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        set_object_label(gl::PROGRAM, self.0, label);
+    }
+
+    /// Uses the program until the returned guard is dropped.
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        let previous = bindings::program();
+        self.bind();
+        BindGuard::new(Binding::Program(previous))
     }
 
     pub fn attrib_location(&self, attrib: &str) -> GLint {
@@ -218,11 +461,30 @@ impl Program {
         }
     }
 
+    /// The location of a uniform, or -1 if the program doesn't use it.
     pub fn uniform_location(&self, uniform: &str) -> GLint {
-        let zuniform = as_zero_str(uniform);
-        unsafe {
-            gl::GetUniformLocation(self.0, zuniform.as_ptr())
+        let name = uniform.trim_end_matches('\0');
+        if let Some(&location) = self.1.borrow().get(name) {
+            return location;
         }
+        let zuniform = as_zero_str(uniform);
+        let location = unsafe { gl::GetUniformLocation(self.0, zuniform.as_ptr()) };
+        self.1.borrow_mut().insert(name.to_string(), location);
+        location
+    }
+
+    /// Binds the program and sets a uniform, found by name or by location, to `value`.
+    /// Uniforms the program doesn't use are ignored.
+    ///
+    /// ```ignore
+    /// program.set_uniform("Strength", 0.5);
+    /// program.set_uniform("Noise", TextureUnit(1));
+    /// ```
+    pub fn set_uniform<U: Uniform, V: UniformValue>(&self, uniform: U, value: V) {
+        let location = uniform.location(self);
+        if location < 0 { return }
+        self.bind();
+        value.set(location);
     }
 
     fn is_link_success(handle: GLuint) -> bool {
@@ -230,7 +492,7 @@ impl Program {
         unsafe {
             gl::GetProgramiv(handle, gl::LINK_STATUS, &mut status);
         }
-        return (status as GLboolean) != gl::FALSE;
+        (status as GLboolean) != gl::FALSE
     }
 
     fn get_error(handle: GLuint) -> String {
@@ -239,12 +501,11 @@ impl Program {
             gl::GetProgramiv(handle, gl::INFO_LOG_LENGTH, &mut log_length);
         }
         if log_length > 0 {
-            let mut buf: Vec<u8> = Vec::with_capacity(log_length as usize);
-            buf.resize(log_length as usize, 0);
+            let mut buf: Vec<u8> = vec![0; log_length as usize];
             unsafe {
-                gl::GetProgramInfoLog(handle, log_length, ptr::null_mut(), mem::transmute(buf.as_mut_ptr()));
-                String::from_utf8_unchecked(buf)
+                gl::GetProgramInfoLog(handle, log_length, ptr::null_mut(), buf.as_mut_ptr() as *mut _);
             }
+            info_log(buf)
         } else {
             String::new()
         }
@@ -253,18 +514,147 @@ impl Program {
 
 impl Drop for Program {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteProgram(self.0)
-        }
+        delete_shared(self.2, GlObject::Program(self.0));
     }
 }
 
-#[derive(Copy, Clone)]
+/// A linked program in the driver's own format, which only the same driver on the same GPU can
+/// load again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramBinary {
+    pub format: gl::types::GLenum,
+    pub data:   Vec<u8>,
+}
+
+impl ProgramBinary {
+    /// The format followed by the data, for saving the binary to a file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.data.len());
+        bytes.extend_from_slice(&self.format.to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    /// Reads a binary written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Option<ProgramBinary> {
+        if bytes.len() <= 4 { return None }
+        let format = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        Some(ProgramBinary { format, data: bytes[4..].to_vec() })
+    }
+}
+
+/// Identifies a uniform of a program, either by its name or by a location that was looked up
+/// before.
+pub trait Uniform {
+    fn location(&self, program: &Program) -> GLint;
+}
+
+impl Uniform for GLint {
+    fn location(&self, _program: &Program) -> GLint {
+        *self
+    }
+}
+
+impl Uniform for &str {
+    fn location(&self, program: &Program) -> GLint {
+        program.uniform_location(self)
+    }
+}
+
+/// A value that a uniform can be set to with `Program::set_uniform`. The program the uniform
+/// belongs to must be bound.
+pub trait UniformValue {
+    fn set(&self, location: GLint);
+}
+
+/// The index of a texture unit, for setting `sampler` uniforms.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureUnit(pub u32);
+
+impl<V: UniformValue + ?Sized> UniformValue for &V {
+    fn set(&self, location: GLint) {
+        (**self).set(location)
+    }
+}
+
+impl UniformValue for f32 {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform1f(location, *self) }
+    }
+}
+
+impl UniformValue for i32 {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform1i(location, *self) }
+    }
+}
+
+impl UniformValue for bool {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform1i(location, *self as _) }
+    }
+}
+
+impl UniformValue for TextureUnit {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform1i(location, self.0 as _) }
+    }
+}
+
+impl UniformValue for Vec2f {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform2f(location, self.x, self.y) }
+    }
+}
+
+impl UniformValue for Vec3f {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform3f(location, self.x, self.y, self.z) }
+    }
+}
+
+impl UniformValue for Vec4f {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform4f(location, self.x, self.y, self.z, self.w) }
+    }
+}
+
+impl UniformValue for Color {
+    fn set(&self, location: GLint) {
+        unsafe { gl::Uniform4f(location, self.r, self.g, self.b, self.a) }
+    }
+}
+
+impl UniformValue for Mat3f {
+    fn set(&self, location: GLint) {
+        use cgmath::Matrix;
+        unsafe { gl::UniformMatrix3fv(location, 1, gl::FALSE, self.as_ptr()) }
+    }
+}
+
+impl UniformValue for Mat4f {
+    fn set(&self, location: GLint) {
+        use cgmath::Matrix;
+        unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, self.as_ptr()) }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum InternalPixelFormat {
     Red,
     RG,
     RGB,
     RGBA,
+    /// RGB stored in sRGB and converted to linear when sampled.
+    SRGB,
+    /// RGBA with the color stored in sRGB and converted to linear when sampled.
+    SRGBA,
+    /// A 24-bit depth buffer, for textures that are attached to a framebuffer's depth.
+    Depth,
+    /// A 24-bit depth and 8-bit stencil buffer.
+    DepthStencil,
+    /// A single 32-bit unsigned integer channel, e.g. for object IDs.
+    R32UI,
 }
 
 impl InternalPixelFormat {
@@ -274,22 +664,32 @@ impl InternalPixelFormat {
             InternalPixelFormat::RG => gl::RG,
             InternalPixelFormat::RGB => gl::RGB,
             InternalPixelFormat::RGBA => gl::RGBA,
+            InternalPixelFormat::SRGB => gl::SRGB8,
+            InternalPixelFormat::SRGBA => gl::SRGB8_ALPHA8,
+            InternalPixelFormat::Depth => gl::DEPTH_COMPONENT24,
+            InternalPixelFormat::DepthStencil => gl::DEPTH24_STENCIL8,
+            InternalPixelFormat::R32UI => gl::R32UI,
         }
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum PixelDataFormat {
     Red,
     RG,
     RGB,
     BGR,
     RGBA,
-    BGRA
+    BGRA,
+    Depth,
+    /// Used with `PixelDataType::UnsignedInt_24_8`.
+    DepthStencil,
+    /// A red channel that is kept an integer, for integer formats like `R32UI`.
+    RedInteger,
 }
 
 impl PixelDataFormat {
-    fn as_gl(self) -> GLuint {
+    pub(crate) fn as_gl(self) -> GLuint {
         match self {
             PixelDataFormat::Red => gl::RED,
             PixelDataFormat::RG => gl::RG,
@@ -297,11 +697,23 @@ impl PixelDataFormat {
             PixelDataFormat::BGR => gl::BGR,
             PixelDataFormat::RGBA => gl::RGBA,
             PixelDataFormat::BGRA => gl::BGRA,
+            PixelDataFormat::Depth => gl::DEPTH_COMPONENT,
+            PixelDataFormat::DepthStencil => gl::DEPTH_STENCIL,
+            PixelDataFormat::RedInteger => gl::RED_INTEGER,
+        }
+    }
+
+    pub fn channels(self) -> usize {
+        match self {
+            PixelDataFormat::Red | PixelDataFormat::Depth | PixelDataFormat::DepthStencil | PixelDataFormat::RedInteger => 1,
+            PixelDataFormat::RG => 2,
+            PixelDataFormat::RGB | PixelDataFormat::BGR => 3,
+            PixelDataFormat::RGBA | PixelDataFormat::BGRA => 4,
         }
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum PixelDataType {
     UnsignedByte,
     Byte,
@@ -334,10 +746,12 @@ pub enum PixelDataType {
     UnsignedInt_10_10_10_2,
     #[allow(non_camel_case_types)]
     UnsignedInt_2_10_10_10_Rev,
+    #[allow(non_camel_case_types)]
+    UnsignedInt_24_8,
 }
 
 impl PixelDataType {
-    fn as_gl(self) -> GLuint {
+    pub(crate) fn as_gl(self) -> GLuint {
         match self {
             PixelDataType::UnsignedByte => gl::UNSIGNED_BYTE,
             PixelDataType::Byte => gl::BYTE,
@@ -358,114 +772,1894 @@ impl PixelDataType {
             PixelDataType::UnsignedInt_8_8_8_8_Rev => gl::UNSIGNED_INT_8_8_8_8_REV,
             PixelDataType::UnsignedInt_10_10_10_2 => gl::UNSIGNED_INT_10_10_10_2,
             PixelDataType::UnsignedInt_2_10_10_10_Rev => gl::UNSIGNED_INT_2_10_10_10_REV,
+            PixelDataType::UnsignedInt_24_8 => gl::UNSIGNED_INT_24_8,
+        }
+    }
+
+    /// The size of a single pixel with this data type and `format` in bytes.
+    pub fn bytes_per_pixel(self, format: PixelDataFormat) -> usize {
+        match self {
+            PixelDataType::UnsignedByte | PixelDataType::Byte => format.channels(),
+            PixelDataType::UnsignedShort | PixelDataType::Short => format.channels() * 2,
+            PixelDataType::UnsignedInt | PixelDataType::Int | PixelDataType::Float => format.channels() * 4,
+            PixelDataType::UnsignedByte_3_3_2 | PixelDataType::UnsignedByte_2_3_3_Rev => 1,
+            PixelDataType::UnsignedShort_5_6_5 | PixelDataType::UnsignedShort_5_6_5_Rev |
+            PixelDataType::UnsignedShort_4_4_4_4 | PixelDataType::UnsignedShort_4_4_4_4_Rev |
+            PixelDataType::UnsignedShort_5_5_5_1 | PixelDataType::UnsignedShort_1_5_5_5_Rev => 2,
+            PixelDataType::UnsignedInt_8_8_8_8 | PixelDataType::UnsignedInt_8_8_8_8_Rev |
+            PixelDataType::UnsignedInt_10_10_10_2 | PixelDataType::UnsignedInt_2_10_10_10_Rev |
+            PixelDataType::UnsignedInt_24_8 => 4,
         }
     }
 }
 
-pub struct Texture {
-    handle: GLuint,
-    width:  u32,
-    height: u32,
+/// The common combinations of a texture's storage format and the format of the pixel data
+/// uploaded to it, for `Texture::with_format`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    /// RGB stored in sRGB and converted to linear when sampled.
+    SRGB8,
+    /// RGBA with the color stored in sRGB and converted to linear when sampled.
+    SRGBA8,
+    /// 32-bit float depth values are uploaded.
+    Depth24,
+    /// Uploaded as 32-bit integers with the depth in the upper 24 bits.
+    Depth24Stencil8,
+    /// 32-bit unsigned integers, which a fragment shader writes as `uint` outputs. Can't be
+    /// filtered, so it's meant for color attachments that are read back, like picking IDs.
+    R32UI,
 }
 
-impl Texture {
-    pub fn new<PData: PixelData>(width: u32, height: u32, internal_format: InternalPixelFormat,  pixel_data_format: PixelDataFormat, pixel_data_type: PixelDataType, pixel_data: Option<PData>) -> Texture {
-        let mut handle: GLuint = 0;
-        unsafe {
-            gl::GenTextures(1, &mut handle);
-            gl::BindTexture(gl::TEXTURE_2D, handle);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+impl TextureFormat {
+    pub fn internal_format(self) -> InternalPixelFormat {
+        match self {
+            TextureFormat::R8 => InternalPixelFormat::Red,
+            TextureFormat::RG8 => InternalPixelFormat::RG,
+            TextureFormat::RGB8 => InternalPixelFormat::RGB,
+            TextureFormat::RGBA8 => InternalPixelFormat::RGBA,
+            TextureFormat::SRGB8 => InternalPixelFormat::SRGB,
+            TextureFormat::SRGBA8 => InternalPixelFormat::SRGBA,
+            TextureFormat::Depth24 => InternalPixelFormat::Depth,
+            TextureFormat::Depth24Stencil8 => InternalPixelFormat::DepthStencil,
+            TextureFormat::R32UI => InternalPixelFormat::R32UI,
+        }
+    }
 
-            if let Some(data) = pixel_data {
-                let pixel_data_ptr = data.get_data_ptr();
-                gl::TexImage2D(gl::TEXTURE_2D, 0, internal_format.as_gl() as _, width as _, height as _, 0, pixel_data_format.as_gl(), pixel_data_type.as_gl(), mem::transmute(pixel_data_ptr));
-            } else {
-                gl::TexImage2D(gl::TEXTURE_2D, 0, internal_format.as_gl() as _, width as _, height as _, 0, pixel_data_format.as_gl(), pixel_data_type.as_gl(), ptr::null());
-            }
+    pub fn data_format(self) -> PixelDataFormat {
+        match self {
+            TextureFormat::R8 => PixelDataFormat::Red,
+            TextureFormat::RG8 => PixelDataFormat::RG,
+            TextureFormat::RGB8 | TextureFormat::SRGB8 => PixelDataFormat::RGB,
+            TextureFormat::RGBA8 | TextureFormat::SRGBA8 => PixelDataFormat::RGBA,
+            TextureFormat::Depth24 => PixelDataFormat::Depth,
+            TextureFormat::Depth24Stencil8 => PixelDataFormat::DepthStencil,
+            TextureFormat::R32UI => PixelDataFormat::RedInteger,
         }
+    }
 
-        Texture {
-            handle,
-            width,
-            height
+    pub fn data_type(self) -> PixelDataType {
+        match self {
+            TextureFormat::Depth24 => PixelDataType::Float,
+            TextureFormat::Depth24Stencil8 => PixelDataType::UnsignedInt_24_8,
+            TextureFormat::R32UI => PixelDataType::UnsignedInt,
+            _ => PixelDataType::UnsignedByte,
         }
     }
+}
 
-    pub fn get_width(&mut self) -> u32 {
-        self.width
+/// The formats that pixel data is uploaded to a texture with.
+#[derive(Copy, Clone, Debug)]
+struct UploadFormat {
+    internal_format:    InternalPixelFormat,
+    data_format:        PixelDataFormat,
+    data_type:          PixelDataType,
+}
+
+/// How a texture is sampled when it's drawn smaller (minified) or larger (magnified) than its
+/// size in pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureFilter {
+    /// Uses the closest pixel. Pixel art should be drawn with this.
+    Nearest,
+    /// Blends the four closest pixels.
+    Linear,
+    /// Blends the four closest pixels of the two closest mipmap levels when minifying, so
+    /// heavily downscaled textures don't shimmer. Textures without mipmaps are sampled as if
+    /// this were `Linear`.
+    Trilinear,
+}
+
+impl TextureFilter {
+    /// The magnification filter.
+    pub(crate) fn as_gl(self) -> GLuint {
+        match self {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear | TextureFilter::Trilinear => gl::LINEAR,
+        }
     }
 
-    pub fn get_height(&mut self) -> u32 {
-        self.height
+    /// The minification filter for a texture with or without mipmaps.
+    pub(crate) fn min_as_gl(self, mipmaps: bool) -> GLuint {
+        match (self, mipmaps) {
+            (TextureFilter::Nearest, false) => gl::NEAREST,
+            (TextureFilter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+            (TextureFilter::Linear, false) | (TextureFilter::Trilinear, false) => gl::LINEAR,
+            (TextureFilter::Linear, true) => gl::LINEAR_MIPMAP_NEAREST,
+            (TextureFilter::Trilinear, true) => gl::LINEAR_MIPMAP_LINEAR,
+        }
     }
 }
 
-impl Drop for Texture {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteTextures(1, &mut self.handle);
+/// What happens when a texture is sampled outside of the [0, 1] range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureWrap {
+    /// Uses the closest edge pixel.
+    ClampToEdge,
+    /// Tiles the texture.
+    Repeat,
+    /// Tiles the texture, mirroring every other tile.
+    MirroredRepeat,
+}
+
+impl TextureWrap {
+    pub(crate) fn as_gl(self) -> GLuint {
+        match self {
+            TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            TextureWrap::Repeat => gl::REPEAT,
+            TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
         }
     }
 }
 
-pub trait PixelData {
-    fn get_data_ptr(&self) -> *mut u8;
+/// Parameters that control how a texture is sampled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerParams {
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+    /// Whether the texture has mipmaps to sample from.
+    pub mipmaps: bool,
 }
 
-impl PixelData for &[u32] {
-    fn get_data_ptr(&self) -> *mut u8 {
+impl SamplerParams {
+    pub fn new(filter: TextureFilter, wrap: TextureWrap) -> SamplerParams {
+        SamplerParams { filter, wrap, mipmaps: false }
+    }
+
+    pub fn with_mipmaps(self, mipmaps: bool) -> SamplerParams {
+        SamplerParams { mipmaps, ..self }
+    }
+
+    /// Sets the parameters of the texture bound to `TEXTURE_2D`.
+    pub fn apply_to_bound_texture(&self) {
+        apply_texture_filter(self.filter, self.mipmaps);
         unsafe {
-            mem::transmute(self.as_ptr())
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, self.wrap.as_gl() as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, self.wrap.as_gl() as _);
         }
     }
 }
 
-impl PixelData for &[u16] {
-    fn get_data_ptr(&self) -> *mut u8 {
+/// A sampler object (GL 3.3 and GLES 3.0). While it's bound to a texture unit, its parameters
+/// are used instead of those of the texture bound there, so one texture can be drawn with
+/// different filtering and wrapping without changing the texture.
+pub struct Sampler(GLuint, Option<ContextId>);
+
+impl Sampler {
+    /// Whether the current context has sampler objects.
+    pub fn is_supported() -> bool {
+        gl::GenSamplers::is_loaded() && gl::BindSampler::is_loaded()
+    }
+
+    pub fn new(params: SamplerParams) -> Sampler {
+        let mut handle: GLuint = 0;
         unsafe {
-            mem::transmute(self.as_ptr())
+            gl::GenSamplers(1, &mut handle);
         }
+        let sampler = Sampler(handle, ContextId::current());
+        sampler.set_params(params);
+        sampler
     }
-}
 
-impl PixelData for &[u8] {
-    fn get_data_ptr(&self) -> *mut u8 {
+    pub fn set_params(&self, params: SamplerParams) {
         unsafe {
-            mem::transmute(self.as_ptr())
+            gl::SamplerParameteri(self.0, gl::TEXTURE_MIN_FILTER, params.filter.min_as_gl(params.mipmaps) as _);
+            gl::SamplerParameteri(self.0, gl::TEXTURE_MAG_FILTER, params.filter.as_gl() as _);
+            gl::SamplerParameteri(self.0, gl::TEXTURE_WRAP_S, params.wrap.as_gl() as _);
+            gl::SamplerParameteri(self.0, gl::TEXTURE_WRAP_T, params.wrap.as_gl() as _);
         }
     }
-}
 
-pub const FULLSCREEN_BUFFER_DATA: [f32; 24] = [
-    /* Position */  /* UV */
-    -1.0, -1.0,      0.0,  1.0, // bottom left
-    -1.0,  1.0,      0.0,  0.0, // top left
-     1.0, -1.0,      1.0,  1.0, // bottom right
+    /// Names the sampler for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        set_object_label(gl::SAMPLER, self.0, label);
+    }
 
-     1.0, -1.0,      1.0,  1.0, // bottom left
-     1.0,  1.0,      1.0,  0.0, // top right
-    -1.0,  1.0,      0.0,  0.0, // top left
-];
+    /// Binds the sampler to texture unit `unit`.
+    pub fn bind(&self, unit: u32) {
+        bindings::bind_sampler(unit, self.0);
+    }
 
-pub const SIMPLE_VERTEX_SHADER: &str   = "\
-#version 130
+    /// Goes back to sampling the texture bound to `unit` with its own parameters.
+    pub fn unbind(unit: u32) {
+        bindings::bind_sampler(unit, 0);
+    }
 
-in  vec2 Position;
-in  vec2 UV;
-out vec2 FragUV;
+    pub fn handle(&self) -> GLuint {
+        self.0
+    }
+}
 
-void main() {
-    FragUV = UV;
-    gl_Position = vec4(Position.xy, 0, 1);
-}\0";
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        delete_shared(self.1, GlObject::Sampler(self.0));
+    }
+}
 
-pub const SIMPLE_FRAGMENT_SHADER: &str = "\
-#version 130
+/// One sampler object for every combination of parameters that has been used, created on
+/// demand. There are only a few of them, since each parameter has a handful of values.
+#[derive(Default)]
+pub struct SamplerCache {
+    samplers: RefCell<HashMap<SamplerParams, Sampler>>,
+}
 
-uniform sampler2D Texture;
-in  vec2 FragUV;
-out vec4 OutColor;
+impl SamplerCache {
+    pub fn new() -> SamplerCache {
+        SamplerCache { samplers: RefCell::new(HashMap::new()) }
+    }
+
+    /// Binds a sampler with `params` to texture unit `unit`.
+    pub fn bind(&self, unit: u32, params: SamplerParams) {
+        self.samplers.borrow_mut()
+            .entry(params)
+            .or_insert_with(|| Sampler::new(params))
+            .bind(unit);
+    }
+}
+
+/// Block compressed formats that textures can be uploaded in without being decompressed.
+/// All of them encode 4x4 blocks of pixels. Not every format is available everywhere, so check
+/// `is_supported` first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// BC1 / DXT1 without alpha.
+    Bc1Rgb,
+    /// BC1 / DXT1 with 1-bit alpha.
+    Bc1Rgba,
+    /// BC2 / DXT3.
+    Bc2,
+    /// BC3 / DXT5.
+    Bc3,
+    /// BC4 / RGTC1, a single red channel.
+    Bc4,
+    /// BC5 / RGTC2, red and green channels.
+    Bc5,
+    /// BC7 / BPTC.
+    Bc7,
+    Etc2Rgb,
+    /// ETC2 with 1-bit alpha.
+    Etc2RgbPunchthroughAlpha,
+    /// ETC2 with EAC alpha.
+    Etc2Rgba,
+}
+
+impl CompressedFormat {
+    pub(crate) fn as_gl(self) -> GLuint {
+        match self {
+            // from EXT_texture_compression_s3tc, which isn't part of core GL.
+            CompressedFormat::Bc1Rgb => 0x83F0,
+            CompressedFormat::Bc1Rgba => 0x83F1,
+            CompressedFormat::Bc2 => 0x83F2,
+            CompressedFormat::Bc3 => 0x83F3,
+            CompressedFormat::Bc4 => gl::COMPRESSED_RED_RGTC1,
+            CompressedFormat::Bc5 => gl::COMPRESSED_RG_RGTC2,
+            CompressedFormat::Bc7 => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            CompressedFormat::Etc2Rgb => gl::COMPRESSED_RGB8_ETC2,
+            CompressedFormat::Etc2RgbPunchthroughAlpha => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            CompressedFormat::Etc2Rgba => gl::COMPRESSED_RGBA8_ETC2_EAC,
+        }
+    }
+
+    /// Size in bytes of one compressed 4x4 block.
+    pub fn bytes_per_block(self) -> usize {
+        match self {
+            CompressedFormat::Bc1Rgb
+            | CompressedFormat::Bc1Rgba
+            | CompressedFormat::Bc4
+            | CompressedFormat::Etc2Rgb
+            | CompressedFormat::Etc2RgbPunchthroughAlpha => 8,
+            _ => 16,
+        }
+    }
+
+    /// Size in bytes of an image of the given size in this format.
+    pub fn data_size(self, width: u32, height: u32) -> usize {
+        let blocks_x = width.div_ceil(4) as usize;
+        let blocks_y = height.div_ceil(4) as usize;
+        blocks_x * blocks_y * self.bytes_per_block()
+    }
+
+    /// Whether the current context can create textures in this format, either because the
+    /// extension that adds it is available or because the driver lists it as supported.
+    pub fn is_supported(self) -> bool {
+        let extension = match self {
+            CompressedFormat::Bc1Rgb | CompressedFormat::Bc1Rgba | CompressedFormat::Bc2 | CompressedFormat::Bc3 => "GL_EXT_texture_compression_s3tc",
+            // RGTC is core since GL 3.0.
+            CompressedFormat::Bc4 | CompressedFormat::Bc5 => return true,
+            CompressedFormat::Bc7 => "GL_ARB_texture_compression_bptc",
+            CompressedFormat::Etc2Rgb | CompressedFormat::Etc2RgbPunchthroughAlpha | CompressedFormat::Etc2Rgba => "GL_ARB_ES3_compatibility",
+        };
+        has_extension(extension) || compressed_texture_formats().contains(&(self.as_gl() as GLint))
+    }
+}
+
+/// The GL name of a texture, see `Texture::handle`.
+pub type TextureId = GLuint;
+
+pub struct Texture {
+    handle: GLuint,
+    width:  u32,
+    height: u32,
+    /// None if the filter should be picked by whatever draws the texture.
+    filter: Option<TextureFilter>,
+    wrap:   TextureWrap,
+    mipmaps: bool,
+    /// False for textures that were created elsewhere and must not be deleted on drop.
+    owned:  bool,
+    /// Whether the colors are already multiplied by alpha.
+    premultiplied: bool,
+    /// None for compressed textures and textures that were created elsewhere, which can't be
+    /// uploaded to.
+    upload_format: Option<UploadFormat>,
+    owner:  Option<ContextId>,
+}
+
+impl Texture {
+    pub fn new<PData: PixelData>(width: u32, height: u32, internal_format: InternalPixelFormat,  pixel_data_format: PixelDataFormat, pixel_data_type: PixelDataType, pixel_data: Option<PData>) -> Texture {
+        let mut handle: GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut handle);
+            bindings::bind_texture(handle);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+
+            if let Some(data) = pixel_data {
+                let pixel_data_ptr = data.get_data_ptr();
+                gl::TexImage2D(gl::TEXTURE_2D, 0, internal_format.as_gl() as _, width as _, height as _, 0, pixel_data_format.as_gl(), pixel_data_type.as_gl(), pixel_data_ptr as *const _);
+            } else {
+                gl::TexImage2D(gl::TEXTURE_2D, 0, internal_format.as_gl() as _, width as _, height as _, 0, pixel_data_format.as_gl(), pixel_data_type.as_gl(), ptr::null());
+            }
+        }
+
+        Texture {
+            handle,
+            width,
+            height,
+            filter: None,
+            wrap: TextureWrap::ClampToEdge,
+            mipmaps: false,
+            owned: true,
+            premultiplied: false,
+            upload_format: Some(UploadFormat { internal_format, data_format: pixel_data_format, data_type: pixel_data_type }),
+            owner: ContextId::current(),
+        }
+    }
+
+    /// Creates a texture with undefined contents, which can be filled with `upload` or by
+    /// attaching it to a `Framebuffer`.
+    pub fn with_format(width: u32, height: u32, format: TextureFormat) -> Texture {
+        Texture::new::<&[u8]>(width, height, format.internal_format(), format.data_format(), format.data_type(), None)
+    }
+
+    /// Wraps a `TEXTURE_2D` that was created by other GL code so that it can be drawn like any
+    /// other texture. If `owned` is true the texture is deleted when this is dropped, otherwise
+    /// whoever created it keeps it alive and deletes it.
+    ///
+    /// The texture's sampling parameters are left alone until it is drawn; the wrap mode is
+    /// assumed to be clamp to edge and the texture is assumed to have no mipmaps.
+    pub fn from_raw_gl_id(handle: GLuint, width: u32, height: u32, owned: bool) -> Texture {
+        Texture {
+            handle,
+            width,
+            height,
+            filter: None,
+            wrap: TextureWrap::ClampToEdge,
+            mipmaps: false,
+            owned,
+            premultiplied: false,
+            upload_format: None,
+            owner: ContextId::current(),
+        }
+    }
+
+    /// Marks the texture's colors as already multiplied by alpha, like everything a `Context`
+    /// draws into a `Surface`, so that they aren't multiplied again when it's drawn.
+    pub fn set_premultiplied(&mut self, premultiplied: bool) {
+        self.premultiplied = premultiplied;
+    }
+
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    /// Whether the texture is deleted when this is dropped.
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    /// Generates mipmaps from the texture's current contents so that it can be minified
+    /// smoothly. This has to be called again after the contents change.
+    pub fn generate_mipmaps(&mut self) {
+        self.mipmaps = true;
+        self.bind();
+        unsafe {
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
+    }
+
+    pub fn has_mipmaps(&self) -> bool {
+        self.mipmaps
+    }
+
+    /// Creates a texture from block compressed data. `levels` holds the base image followed by
+    /// any number of its mipmaps, each half the size of the previous one.
+    pub fn new_compressed(width: u32, height: u32, format: CompressedFormat, levels: &[&[u8]]) -> Result<Texture, String> {
+        if levels.is_empty() {
+            return Err("no compressed image data".to_string());
+        }
+        if !format.is_supported() {
+            return Err(format!("compressed texture format {:?} is not supported", format));
+        }
+        for (level, data) in levels.iter().enumerate() {
+            let (w, h) = ((width >> level).max(1), (height >> level).max(1));
+            if data.len() != format.data_size(w, h) {
+                return Err(format!("mipmap level {} is {} bytes but a {}x{} {:?} image is {} bytes",
+                    level, data.len(), w, h, format, format.data_size(w, h)));
+            }
+        }
+
+        let mut handle: GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut handle);
+            bindings::bind_texture(handle);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+            // an incomplete mipmap chain is fine as long as GL doesn't look for the rest of it.
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, (levels.len() - 1) as _);
+
+            for (level, data) in levels.iter().enumerate() {
+                let (w, h) = ((width >> level).max(1), (height >> level).max(1));
+                gl::CompressedTexImage2D(gl::TEXTURE_2D, level as _, format.as_gl(), w as _, h as _, 0, data.len() as _, data.as_ptr() as *const _);
+            }
+        }
+
+        Ok(Texture {
+            handle,
+            width,
+            height,
+            filter: None,
+            wrap: TextureWrap::ClampToEdge,
+            mipmaps: levels.len() > 1,
+            owned: true,
+            premultiplied: false,
+            upload_format: None,
+            owner: ContextId::current(),
+        })
+    }
+
+    /// Sets how the texture is sampled outside of its edges in both directions.
+    pub fn set_wrap(&mut self, wrap: TextureWrap) {
+        self.wrap = wrap;
+        self.bind();
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap.as_gl() as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap.as_gl() as _);
+        }
+    }
+
+    pub fn wrap(&self) -> TextureWrap {
+        self.wrap
+    }
+
+    /// Sets the filter used for both minification and magnification. This overrides the
+    /// default filter of a `Context` that draws this texture.
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.filter = Some(filter);
+        self.bind();
+        apply_texture_filter(filter, self.mipmaps);
+    }
+
+    /// Goes back to using the default filter of the `Context` that draws the texture.
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+    }
+
+    pub fn filter(&self) -> Option<TextureFilter> {
+        self.filter
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// The width and height in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The storage format, or None for compressed textures and textures that were created
+    /// elsewhere.
+    pub fn internal_format(&self) -> Option<InternalPixelFormat> {
+        self.upload_format.map(|f| f.internal_format)
+    }
+
+    /// The format and type of the pixel data that `upload` and `sub_upload` expect.
+    pub fn data_format(&self) -> Option<(PixelDataFormat, PixelDataType)> {
+        self.upload_format.map(|f| (f.data_format, f.data_type))
+    }
+
+    /// Replaces the contents of the whole texture with tightly packed rows of pixels in the
+    /// texture's data format, bottom row first the way GL stores them.
+    pub fn upload<PData: PixelData>(&self, pixels: PData) {
+        self.sub_upload(0, 0, self.width, self.height, pixels);
+    }
+
+    /// Replaces a region of the texture. `(x, y)` is the corner of the region with the lowest
+    /// texture coordinates and `pixels` holds its tightly packed rows in the texture's data
+    /// format. Mipmaps are regenerated if the texture has them.
+    pub fn sub_upload<PData: PixelData>(&self, x: u32, y: u32, width: u32, height: u32, pixels: PData) {
+        let format = self.upload_format.expect("only uncompressed textures created by this crate can be uploaded to");
+        let inside = x.checked_add(width).is_some_and(|right| right <= self.width)
+            && y.checked_add(height).is_some_and(|top| top <= self.height);
+        assert!(inside, "region is outside of the {}x{} texture", self.width, self.height);
+        let size = format.data_type.bytes_per_pixel(format.data_format) * width as usize * height as usize;
+        assert!(pixels.byte_len() >= size, "not enough pixel data for a {}x{} region", width, height);
+
+        self.bind();
+        // rows aren't padded to 4 bytes, which is GL's default expectation.
+        with_unpack_alignment(1, || unsafe {
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D, 0, x as _, y as _, width as _, height as _,
+                format.data_format.as_gl(), format.data_type.as_gl(), pixels.get_data_ptr() as *const _);
+        });
+        unsafe {
+            if self.mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+        }
+    }
+
+    /// Reallocates the texture with a new size. Its contents are undefined afterwards and it no
+    /// longer has mipmaps.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let format = self.upload_format.expect("only uncompressed textures created by this crate can be resized");
+        self.bind();
+        unsafe {
+            gl::TexImage2D(
+                gl::TEXTURE_2D, 0, format.internal_format.as_gl() as _, width as _, height as _, 0,
+                format.data_format.as_gl(), format.data_type.as_gl(), ptr::null());
+        }
+        self.width = width;
+        self.height = height;
+        if self.mipmaps {
+            self.mipmaps = false;
+            if let Some(filter) = self.filter {
+                apply_texture_filter(filter, false);
+            }
+        }
+    }
+
+    /// Binds the texture to `TEXTURE_2D` of texture unit `unit`, for sampling it in a shader
+    /// through a uniform set to `TextureUnit(unit)`. The unit is left active.
+    pub fn bind_unit(&self, unit: u32) {
+        bind_texture_unit(unit, self.handle);
+    }
+
+    pub fn handle(&self) -> GLuint {
+        self.handle
+    }
+
+    /// Binds the texture to `TEXTURE_2D` of the active texture unit.
+    pub fn bind(&self) {
+        bindings::bind_texture(self.handle);
+    }
+
+    /// Names the texture for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        let _texture = self.bind_scoped();
+        set_object_label(gl::TEXTURE, self.handle, label);
+    }
+
+    /// Binds the texture to `TEXTURE_2D` of the active texture unit until the returned guard is
+    /// dropped, which binds the previous texture of that unit again and makes it active.
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        let unit = bindings::active_texture_unit();
+        let previous = bindings::texture();
+        self.bind();
+        BindGuard::new(Binding::Texture(unit, previous))
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        if !self.owned { return }
+        delete_shared(self.owner, GlObject::Texture(self.handle));
+    }
+}
+
+/// Sets the filter of the texture bound to `TEXTURE_2D`. `mipmaps` must only be true if the
+/// texture has mipmaps, otherwise it can't be sampled.
+pub fn apply_texture_filter(filter: TextureFilter, mipmaps: bool) {
+    unsafe {
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter.min_as_gl(mipmaps) as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter.as_gl() as _);
+    }
+}
+
+/// A buffer of a framebuffer that a texture or renderbuffer can be attached to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Attachment {
+    /// The color attachment with the given index, which is where fragment shader output with
+    /// that index goes.
+    Color(u32),
+    Depth,
+    Stencil,
+    DepthStencil,
+}
+
+impl Attachment {
+    fn as_gl(self) -> GLuint {
+        match self {
+            Attachment::Color(index) => gl::COLOR_ATTACHMENT0 + index,
+            Attachment::Depth => gl::DEPTH_ATTACHMENT,
+            Attachment::Stencil => gl::STENCIL_ATTACHMENT,
+            Attachment::DepthStencil => gl::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+}
+
+/// A framebuffer object, which can be rendered into instead of the window once textures or
+/// renderbuffers are attached to it.
+pub struct Framebuffer(GLuint, Option<ContextId>, Cell<u32>);
+
+impl Framebuffer {
+    pub fn new() -> Framebuffer {
+        let mut handle: GLuint = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut handle);
+        }
+        Framebuffer(handle, ContextId::current(), Cell::new(0))
+    }
+
+    pub fn handle(&self) -> GLuint {
+        self.0
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.0);
+        }
+    }
+
+    /// Names the framebuffer for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        let _framebuffer = self.bind_scoped();
+        set_object_label(gl::FRAMEBUFFER, self.0, label);
+    }
+
+    /// Binds the framebuffer until the returned guard is dropped. Use `bind_with_viewport` to
+    /// restore the viewport as well.
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        let previous = Framebuffer::current_binding();
+        self.bind();
+        BindGuard::new(Binding::Framebuffer(previous))
+    }
+
+    /// Binds the default framebuffer.
+    pub fn unbind() {
+        Framebuffer::bind_handle(0);
+    }
+
+    pub fn bind_handle(handle: GLuint) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, handle);
+        }
+    }
+
+    /// The handle of the currently bound framebuffer, which is 0 for the default framebuffer.
+    pub fn current_binding() -> GLuint {
+        let mut handle: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut handle);
+        }
+        handle as GLuint
+    }
+
+    /// Binds this framebuffer and the viewport to `(0, 0, width, height)`. Both are restored to
+    /// what they were when the returned guard is dropped.
+    pub fn bind_with_viewport(&self, width: u32, height: u32) -> FramebufferBinding {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let binding = FramebufferBinding { framebuffer: Framebuffer::current_binding(), viewport };
+        self.bind();
+        unsafe {
+            gl::Viewport(0, 0, width as _, height as _);
+        }
+        binding
+    }
+
+    /// Binds this framebuffer and attaches the base level of `texture` to `attachment`.
+    pub fn attach_texture(&self, attachment: Attachment, texture: &Texture) {
+        self.bind();
+        unsafe {
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment.as_gl(), gl::TEXTURE_2D, texture.handle, 0);
+        }
+        self.attached(attachment, true);
+    }
+
+    /// Binds this framebuffer and attaches `renderbuffer` to `attachment`.
+    pub fn attach_renderbuffer(&self, attachment: Attachment, renderbuffer: &Renderbuffer) {
+        self.bind();
+        unsafe {
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment.as_gl(), gl::RENDERBUFFER, renderbuffer.0);
+        }
+        self.attached(attachment, true);
+    }
+
+    /// Binds this framebuffer and removes whatever is attached to `attachment`.
+    pub fn detach(&self, attachment: Attachment) {
+        self.bind();
+        unsafe {
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment.as_gl(), gl::RENDERBUFFER, 0);
+        }
+        self.attached(attachment, false);
+    }
+
+    /// The indices of the color attachments that something is attached to, in order.
+    pub fn color_attachments(&self) -> Vec<u32> {
+        let mask = self.2.get();
+        (0..32).filter(|index| mask & (1 << index) != 0).collect()
+    }
+
+    /// Binds this framebuffer and makes color attachment `index` the one that pixels are read
+    /// from, e.g. by `read_pixels_uint`.
+    pub fn read_from(&self, index: u32) {
+        self.bind();
+        unsafe {
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + index);
+        }
+    }
+
+    /// How many color attachments a framebuffer can have on the current context. At least 4 on
+    /// GL 3.0 and GLES 3.0, and usually 8.
+    pub fn max_color_attachments() -> u32 {
+        let mut max: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut max);
+        }
+        max.max(1) as u32
+    }
+
+    /// Keeps track of the color attachments and draws into every one of them. Fragment shader
+    /// output `N` goes to color attachment `N`, so attachments that are skipped get `NONE`.
+    fn attached(&self, attachment: Attachment, attached: bool) {
+        let index = match attachment {
+            Attachment::Color(index) if index < 32 => index,
+            _ => return,
+        };
+        let mask = if attached { self.2.get() | (1 << index) } else { self.2.get() & !(1 << index) };
+        self.2.set(mask);
+
+        let count = 32 - mask.leading_zeros();
+        let buffers: Vec<GLuint> = (0..count.max(1))
+            .map(|index| if mask & (1 << index) != 0 { gl::COLOR_ATTACHMENT0 + index } else { gl::NONE })
+            .collect();
+        unsafe {
+            gl::DrawBuffers(buffers.len() as _, buffers.as_ptr());
+        }
+    }
+
+    /// Binds this framebuffer and clears color attachment `index`, which must have a normalized
+    /// or floating point format, to `color`. Unlike `glClear` this leaves the other attachments
+    /// alone, so e.g. an object ID buffer can be cleared to a different value than the image.
+    pub fn clear_color_attachment(&self, index: u32, color: [f32; 4]) {
+        self.bind();
+        unsafe {
+            gl::ClearBufferfv(gl::COLOR, index as _, color.as_ptr());
+        }
+    }
+
+    /// Like `clear_color_attachment`, for color attachments with an unsigned integer format.
+    pub fn clear_color_attachment_uint(&self, index: u32, value: [u32; 4]) {
+        self.bind();
+        unsafe {
+            gl::ClearBufferuiv(gl::COLOR, index as _, value.as_ptr());
+        }
+    }
+
+    /// Binds this framebuffer and attaches the texture as its first color attachment.
+    pub fn attach_color_texture(&self, texture: &Texture) {
+        self.attach_texture(Attachment::Color(0), texture);
+    }
+
+    /// Binds this framebuffer and attaches a combined depth and stencil buffer to it.
+    pub fn attach_depth_stencil(&self, renderbuffer: &Renderbuffer) {
+        self.attach_renderbuffer(Attachment::DepthStencil, renderbuffer);
+    }
+
+    /// Binds this framebuffer and checks that it can be rendered to.
+    pub fn check_status(&self) -> Result<(), String> {
+        self.bind();
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        match status {
+            gl::FRAMEBUFFER_COMPLETE => Ok(()),
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => Err("FRAMEBUFFER_INCOMPLETE_ATTACHMENT".to_string()),
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => Err("FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT".to_string()),
+            gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => Err("FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER".to_string()),
+            gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => Err("FRAMEBUFFER_INCOMPLETE_READ_BUFFER".to_string()),
+            gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => Err("FRAMEBUFFER_INCOMPLETE_MULTISAMPLE".to_string()),
+            gl::FRAMEBUFFER_UNDEFINED => Err("FRAMEBUFFER_UNDEFINED".to_string()),
+            gl::FRAMEBUFFER_UNSUPPORTED => Err("FRAMEBUFFER_UNSUPPORTED".to_string()),
+            other => Err(format!("framebuffer incomplete (0x{:X})", other)),
+        }
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Framebuffer {
+        Framebuffer::new()
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        delete_unshared(self.1, GlObject::Framebuffer(self.0));
+    }
+}
+
+/// Restores the framebuffer and viewport that were bound before `Framebuffer::bind_with_viewport`
+/// when dropped.
+pub struct FramebufferBinding {
+    framebuffer: GLuint,
+    viewport: [GLint; 4],
+}
+
+impl Drop for FramebufferBinding {
+    fn drop(&mut self) {
+        Framebuffer::bind_handle(self.framebuffer);
+        unsafe {
+            gl::Viewport(self.viewport[0], self.viewport[1], self.viewport[2], self.viewport[3]);
+        }
+    }
+}
+
+/// The storage format of a `Renderbuffer`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderbufferFormat {
+    RGBA8,
+    /// RGBA with the color stored in sRGB.
+    SRGBA8,
+    Depth24,
+    Stencil8,
+    Depth24Stencil8,
+}
+
+impl RenderbufferFormat {
+    fn as_gl(self) -> GLuint {
+        match self {
+            RenderbufferFormat::RGBA8 => gl::RGBA8,
+            RenderbufferFormat::SRGBA8 => gl::SRGB8_ALPHA8,
+            RenderbufferFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            RenderbufferFormat::Stencil8 => gl::STENCIL_INDEX8,
+            RenderbufferFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+        }
+    }
+}
+
+/// Storage for a framebuffer attachment that is never sampled, like a stencil buffer.
+pub struct Renderbuffer(GLuint, Option<ContextId>);
+
+impl Renderbuffer {
+    pub fn new(format: RenderbufferFormat, width: u32, height: u32) -> Renderbuffer {
+        let mut handle: GLuint = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut handle);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, handle);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, format.as_gl(), width as _, height as _);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+        Renderbuffer(handle, ContextId::current())
+    }
+
+    /// Names the renderbuffer for debuggers like RenderDoc and for debug messages. Does
+    /// nothing without `KHR_debug`.
+    pub fn set_label(&self, label: &str) {
+        set_object_label(gl::RENDERBUFFER, self.0, label);
+    }
+
+    /// A renderbuffer with a 24-bit depth and 8-bit stencil buffer.
+    pub fn depth_stencil(width: u32, height: u32) -> Renderbuffer {
+        Renderbuffer::new(RenderbufferFormat::Depth24Stencil8, width, height)
+    }
+
+    pub fn handle(&self) -> GLuint {
+        self.0
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        delete_shared(self.1, GlObject::Renderbuffer(self.0));
+    }
+}
+
+/// Reads RGBA8 pixels from the bound framebuffer. `(x, y)` is the bottom left corner of the
+/// region and the rows are returned bottom row first, the way GL stores them.
+pub fn read_pixels_rgba(x: i32, y: i32, width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    with_pack_alignment(1, || unsafe {
+        gl::ReadPixels(x, y, width as _, height as _, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut _);
+    });
+    pixels
+}
+
+/// Runs `read` with `PACK_ALIGNMENT` set to `alignment` and restores the previous alignment
+/// afterwards, since it's state that the application may rely on.
+fn with_pack_alignment<R>(alignment: GLint, read: impl FnOnce() -> R) -> R {
+    let mut previous: GLint = 4;
+    unsafe {
+        gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut previous);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, alignment);
+    }
+    let result = read();
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, previous);
+    }
+    result
+}
+
+/// Runs `upload` with `UNPACK_ALIGNMENT` set to `alignment` and restores the previous
+/// alignment afterwards, like `with_pack_alignment`.
+pub(crate) fn with_unpack_alignment<R>(alignment: GLint, upload: impl FnOnce() -> R) -> R {
+    let mut previous: GLint = 4;
+    unsafe {
+        gl::GetIntegerv(gl::UNPACK_ALIGNMENT, &mut previous);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
+    }
+    let result = upload();
+    unsafe {
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, previous);
+    }
+    result
+}
+
+/// Reads the red channel of an unsigned integer color attachment, like an `R32UI` ID buffer,
+/// from the bound framebuffer's read buffer. `(x, y)` is the bottom left corner of the region.
+pub fn read_pixels_uint(x: i32, y: i32, width: u32, height: u32) -> Vec<u32> {
+    let mut values = vec![0u32; width as usize * height as usize];
+    with_pack_alignment(4, || unsafe {
+        gl::ReadPixels(x, y, width as _, height as _, gl::RED_INTEGER, gl::UNSIGNED_INT, values.as_mut_ptr() as *mut _);
+    });
+    values
+}
+
+/// Starts reading RGBA8 pixels from the bound framebuffer into `buffer`, a pixel pack buffer,
+/// without waiting for them. Read them with `Buffer::read_bytes` once a fence inserted after
+/// this is signaled.
+pub fn read_pixels_rgba_into(buffer: &Buffer, x: i32, y: i32, width: u32, height: u32) {
+    let size = width as usize * height as usize * 4;
+    buffer.bind();
+    buffer.allocate(size, BufferUsage::StreamRead);
+    with_pack_alignment(1, || unsafe {
+        gl::ReadPixels(x, y, width as _, height as _, gl::RGBA, gl::UNSIGNED_BYTE, ptr::null_mut());
+    });
+    buffer.unbind();
+}
+
+/// Reverses the order of the rows of a tightly packed RGBA8 image.
+pub fn flip_rows_rgba(pixels: &mut [u8], width: u32) {
+    let row_len = width as usize * 4;
+    if row_len == 0 { return }
+    let rows = pixels.len() / row_len;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - row - 1) * row_len);
+        top[row * row_len..(row + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QueryType {
+    /// The number of samples that passed the depth and stencil tests.
+    SamplesPassed,
+    /// 1 if any sample passed the depth and stencil tests, 0 otherwise.
+    AnySamplesPassed,
+    /// The number of primitives that were sent to the rasterizer.
+    PrimitivesGenerated,
+    /// The GPU time in nanoseconds taken by the commands issued while the query was active.
+    TimeElapsed,
+}
+
+impl QueryType {
+    fn as_gl(self) -> GLuint {
+        match self {
+            QueryType::SamplesPassed => gl::SAMPLES_PASSED,
+            QueryType::AnySamplesPassed => gl::ANY_SAMPLES_PASSED,
+            QueryType::PrimitivesGenerated => gl::PRIMITIVES_GENERATED,
+            QueryType::TimeElapsed => gl::TIME_ELAPSED,
+        }
+    }
+}
+
+/// A GL query object. Only one query of each type can be active at a time. Results are
+/// produced asynchronously so polling `try_result` a frame or two later avoids stalling.
+pub struct Query {
+    /// 0 if the context doesn't support the query type.
+    handle: GLuint,
+    query_type: QueryType,
+    /// True once the query has been ended at least once so that it has a result to wait for.
+    has_result: bool,
+    owner: Option<ContextId>,
+}
+
+impl Query {
+    /// Creates a query in the current context. If the context doesn't support `query_type`,
+    /// see `Query::is_supported`, the query does nothing and never has a result.
+    pub fn new(query_type: QueryType) -> Query {
+        let mut handle: GLuint = 0;
+        if Query::is_supported(query_type) {
+            unsafe {
+                gl::GenQueries(1, &mut handle);
+            }
+        }
+        Query { handle, query_type, has_result: false, owner: ContextId::current() }
+    }
+
+    /// Whether the current context supports queries of `query_type`. Timer queries need GL 3.3
+    /// or `GL_ARB_timer_query`.
+    pub fn is_supported(query_type: QueryType) -> bool {
+        match query_type {
+            QueryType::TimeElapsed => GlCapabilities::current().timer_queries,
+            _ => true,
+        }
+    }
+
+    pub fn query_type(&self) -> QueryType {
+        self.query_type
+    }
+
+    pub fn begin(&mut self) {
+        if self.handle == 0 { return }
+        unsafe {
+            gl::BeginQuery(self.query_type.as_gl(), self.handle);
+        }
+    }
+
+    pub fn end(&mut self) {
+        if self.handle == 0 { return }
+        unsafe {
+            gl::EndQuery(self.query_type.as_gl());
+        }
+        self.has_result = true;
+    }
+
+    /// Begins the query and ends it when the returned guard is dropped.
+    pub fn scoped(&mut self) -> ActiveQuery<'_> {
+        self.begin();
+        ActiveQuery { query: self }
+    }
+
+    /// True if the result of the last query can be read without waiting for the GPU.
+    pub fn is_result_available(&self) -> bool {
+        if !self.has_result { return false }
+        let mut available: GLuint = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.handle, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        available != 0
+    }
+
+    /// The result of the last query if it is ready.
+    pub fn try_result(&self) -> Option<u64> {
+        if self.is_result_available() {
+            self.result()
+        } else {
+            None
+        }
+    }
+
+    /// Waits for the GPU to finish the last query and returns its result. Returns None if the
+    /// query was never ended.
+    pub fn result(&self) -> Option<u64> {
+        if !self.has_result { return None }
+        let mut result: u64 = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(self.handle, gl::QUERY_RESULT, &mut result);
+        }
+        Some(result)
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        if self.handle == 0 { return }
+        delete_unshared(self.owner, GlObject::Query(self.handle));
+    }
+}
+
+/// A GL sync object that is signaled once the GPU has finished every command that was issued
+/// before it was inserted.
+pub struct Fence(gl::types::GLsync, Option<ContextId>);
+
+impl Fence {
+    pub fn insert() -> Fence {
+        Fence(unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) }, ContextId::current())
+    }
+
+    /// Checks whether the fence has been signaled without waiting.
+    pub fn is_signaled(&self) -> bool {
+        self.wait(0)
+    }
+
+    /// Waits up to `timeout_ns` nanoseconds for the fence to be signaled and returns whether it
+    /// was. Pending commands are flushed first so that the fence is reached at all.
+    pub fn wait(&self, timeout_ns: u64) -> bool {
+        let status = unsafe { gl::ClientWaitSync(self.0, gl::SYNC_FLUSH_COMMANDS_BIT, timeout_ns) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        delete_shared(self.1, GlObject::Fence(self.0));
+    }
+}
+
+/// Ends a query when dropped.
+pub struct ActiveQuery<'q> {
+    query: &'q mut Query,
+}
+
+impl Drop for ActiveQuery<'_> {
+    fn drop(&mut self) {
+        self.query.end();
+    }
+}
+
+/// A snapshot of the GL state that `Context` changes when it draws: the bound program, vertex
+/// array, buffers and textures, and the blend, stencil, color mask and scissor state. Used to
+/// embed a `Context` in an application that does its own GL rendering.
+///
+/// Stencil state is only read for front faces and restored for both.
+#[derive(Copy, Clone, Debug)]
+pub struct GlState {
+    program: GLint,
+    vertex_array: GLint,
+    array_buffer: GLint,
+    element_array_buffer: GLint,
+    active_texture: GLint,
+    /// The `TEXTURE_2D` bindings of texture units 0 and 1.
+    textures: [GLint; 2],
+    /// The sampler bound to texture unit 0, if sampler objects are available.
+    sampler: Option<GLint>,
+    blend: bool,
+    /// Source RGB, destination RGB, source alpha and destination alpha factors.
+    blend_func: [GLint; 4],
+    stencil_test: bool,
+    /// Function, reference value and value mask.
+    stencil_func: [GLint; 3],
+    /// Stencil fail, depth fail and depth pass operations.
+    stencil_op: [GLint; 3],
+    stencil_write_mask: GLint,
+    color_mask: [GLboolean; 4],
+    scissor_test: bool,
+    scissor_box: [GLint; 4],
+}
+
+impl GlState {
+    /// Reads the state from the current context. This stalls on some drivers, so it shouldn't be
+    /// done more than a few times per frame.
+    pub fn capture() -> GlState {
+        let get = |name: gl::types::GLenum| {
+            let mut value: GLint = 0;
+            unsafe {
+                gl::GetIntegerv(name, &mut value);
+            }
+            value
+        };
+        let enabled = |cap: gl::types::GLenum| unsafe { gl::IsEnabled(cap) == gl::TRUE };
+
+        let active_texture = get(gl::ACTIVE_TEXTURE);
+        let mut textures = [0; 2];
+        for (unit, texture) in textures.iter_mut().enumerate() {
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + unit as GLuint);
+            }
+            *texture = get(gl::TEXTURE_BINDING_2D);
+        }
+        // the sampler binding of the active unit is the one that can be queried.
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+        let sampler = if Sampler::is_supported() { Some(get(gl::SAMPLER_BINDING)) } else { None };
+        unsafe {
+            gl::ActiveTexture(active_texture as _);
+        }
+
+        let mut color_mask = [gl::TRUE; 4];
+        let mut scissor_box = [0; 4];
+        unsafe {
+            gl::GetBooleanv(gl::COLOR_WRITEMASK, color_mask.as_mut_ptr());
+            gl::GetIntegerv(gl::SCISSOR_BOX, scissor_box.as_mut_ptr());
+        }
+
+        GlState {
+            program: get(gl::CURRENT_PROGRAM),
+            vertex_array: get(gl::VERTEX_ARRAY_BINDING),
+            array_buffer: get(gl::ARRAY_BUFFER_BINDING),
+            element_array_buffer: get(gl::ELEMENT_ARRAY_BUFFER_BINDING),
+            active_texture,
+            textures,
+            sampler,
+            blend: enabled(gl::BLEND),
+            blend_func: [get(gl::BLEND_SRC_RGB), get(gl::BLEND_DST_RGB), get(gl::BLEND_SRC_ALPHA), get(gl::BLEND_DST_ALPHA)],
+            stencil_test: enabled(gl::STENCIL_TEST),
+            stencil_func: [get(gl::STENCIL_FUNC), get(gl::STENCIL_REF), get(gl::STENCIL_VALUE_MASK)],
+            stencil_op: [get(gl::STENCIL_FAIL), get(gl::STENCIL_PASS_DEPTH_FAIL), get(gl::STENCIL_PASS_DEPTH_PASS)],
+            stencil_write_mask: get(gl::STENCIL_WRITEMASK),
+            color_mask,
+            scissor_test: enabled(gl::SCISSOR_TEST),
+            scissor_box,
+        }
+    }
+
+    /// Makes the current context's state what it was when this was captured. This also
+    /// invalidates the binding cache (see `invalidate_binding_cache`).
+    pub fn restore(&self) {
+        invalidate_binding_cache();
+
+        let set_enabled = |cap: gl::types::GLenum, enabled: bool| unsafe {
+            if enabled { gl::Enable(cap) } else { gl::Disable(cap) }
+        };
+
+        unsafe {
+            gl::UseProgram(self.program as _);
+            // the element array buffer binding belongs to the vertex array, so it has to be
+            // bound after it.
+            gl::BindVertexArray(self.vertex_array as _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.array_buffer as _);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.element_array_buffer as _);
+
+            for (unit, &texture) in self.textures.iter().enumerate() {
+                gl::ActiveTexture(gl::TEXTURE0 + unit as GLuint);
+                gl::BindTexture(gl::TEXTURE_2D, texture as _);
+            }
+            if let Some(sampler) = self.sampler {
+                gl::BindSampler(0, sampler as _);
+            }
+            gl::ActiveTexture(self.active_texture as _);
+
+            let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.blend_func;
+            gl::BlendFuncSeparate(src_rgb as _, dst_rgb as _, src_alpha as _, dst_alpha as _);
+            let [func, reference, mask] = self.stencil_func;
+            gl::StencilFunc(func as _, reference, mask as _);
+            let [fail, depth_fail, depth_pass] = self.stencil_op;
+            gl::StencilOp(fail as _, depth_fail as _, depth_pass as _);
+            gl::StencilMask(self.stencil_write_mask as _);
+            let [r, g, b, a] = self.color_mask;
+            gl::ColorMask(r, g, b, a);
+            let [x, y, w, h] = self.scissor_box;
+            gl::Scissor(x, y, w, h);
+        }
+
+        set_enabled(gl::BLEND, self.blend);
+        set_enabled(gl::STENCIL_TEST, self.stencil_test);
+        set_enabled(gl::SCISSOR_TEST, self.scissor_test);
+    }
+}
+
+/// Captures the GL state when it's created and restores it when it's dropped.
+pub struct StateGuard {
+    state: GlState,
+}
+
+impl StateGuard {
+    pub fn new() -> StateGuard {
+        StateGuard { state: GlState::capture() }
+    }
+
+    /// The state that will be restored.
+    pub fn state(&self) -> &GlState {
+        &self.state
+    }
+}
+
+impl Default for StateGuard {
+    fn default() -> StateGuard {
+        StateGuard::new()
+    }
+}
+
+impl Drop for StateGuard {
+    fn drop(&mut self) {
+        self.state.restore();
+    }
+}
+
+/// Identifies a GL context that was registered as current on this thread, which `Window` does
+/// for the context it creates. Objects remember the context they were created in. Dropping one
+/// while another context is current queues it until `make_current` is called for its own.
+/// Once that's destroyed, deleting them would go to no context or a different one, and the
+/// driver has already freed them along with it.
+///
+/// Contexts that share objects belong to the same share group. Buffers, textures, shaders and
+/// the like can be deleted while any context of their group is current, but vertex arrays,
+/// framebuffers and queries are never shared and need the exact context they were created in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ContextId(u64, u64);
+
+thread_local! {
+    static CURRENT_CONTEXT: Cell<Option<ContextId>> = const { Cell::new(None) };
+    static NEXT_CONTEXT_ID: Cell<u64> = const { Cell::new(1) };
+    static LIVE_CONTEXTS: RefCell<Vec<ContextId>> = const { RefCell::new(Vec::new()) };
+    /// Objects that were dropped while a context that could delete them wasn't current.
+    static PENDING_DELETES: RefCell<Vec<PendingDelete>> = const { RefCell::new(Vec::new()) };
+}
+
+impl ContextId {
+    /// Registers the context that was just made current on this thread. Code that creates its
+    /// own GL contexts should call this after making one current, and `unregister` before
+    /// destroying it. Without any registered context objects are always deleted on drop.
+    pub fn register() -> ContextId {
+        let id = ContextId::next_id();
+        ContextId::add(ContextId(id, id))
+    }
+
+    /// Like `register`, for a context that was created to share objects with `other`.
+    pub fn register_shared(other: ContextId) -> ContextId {
+        ContextId::add(ContextId(ContextId::next_id(), other.1))
+    }
+
+    fn next_id() -> u64 {
+        NEXT_CONTEXT_ID.with(|next| {
+            let id = next.get();
+            next.set(id + 1);
+            id
+        })
+    }
+
+    fn add(id: ContextId) -> ContextId {
+        LIVE_CONTEXTS.with(|live| live.borrow_mut().push(id));
+        id.make_current();
+        id
+    }
+
+    /// Records that the context was made current on this thread again, after another one was.
+    /// The bindings that were cached for the other context are forgotten, and objects that
+    /// were dropped while this context couldn't delete them are deleted now.
+    pub fn make_current(self) {
+        CURRENT_CONTEXT.with(|current| current.set(Some(self)));
+        bindings::invalidate();
+        delete_pending(self);
+    }
+
+    /// Marks the context as destroyed, so objects that were created in it are left alone when
+    /// they're dropped.
+    pub fn unregister(self) {
+        LIVE_CONTEXTS.with(|live| live.borrow_mut().retain(|&id| id != self));
+        PENDING_DELETES.with(|queue| queue.borrow_mut().retain(PendingDelete::is_deletable));
+        CURRENT_CONTEXT.with(|current| {
+            if current.get() == Some(self) {
+                current.set(None);
+            }
+        });
+        bindings::invalidate();
+    }
+
+    /// The registered context that is current on this thread.
+    pub fn current() -> Option<ContextId> {
+        CURRENT_CONTEXT.with(|current| current.get())
+    }
+
+    /// Whether the context is the current one, i.e. it can be used right now.
+    pub fn is_current(self) -> bool {
+        ContextId::current() == Some(self)
+    }
+
+    /// Whether the context hasn't been destroyed yet, even if another one is current.
+    pub fn is_alive(self) -> bool {
+        LIVE_CONTEXTS.with(|live| live.borrow().contains(&self))
+    }
+
+    /// Whether the current context shares objects with this one, which includes being it.
+    pub fn shares_with_current(self) -> bool {
+        ContextId::current().map(|current| current.1 == self.1).unwrap_or(false)
+    }
+}
+
+/// A GL object that is deleted once a context that can delete it is current.
+enum GlObject {
+    Buffer(GLuint),
+    VertexArray(GLuint),
+    Shader(GLuint),
+    Program(GLuint),
+    Sampler(GLuint),
+    Texture(GLuint),
+    Framebuffer(GLuint),
+    Renderbuffer(GLuint),
+    Query(GLuint),
+    Fence(gl::types::GLsync),
+}
+
+impl GlObject {
+    fn delete(self) {
+        unsafe {
+            match self {
+                GlObject::Buffer(handle) => {
+                    bindings::forget_buffer(handle);
+                    gl::DeleteBuffers(1, &handle);
+                },
+                GlObject::VertexArray(handle) => {
+                    bindings::forget_vertex_array(handle);
+                    gl::DeleteVertexArrays(1, &handle);
+                },
+                GlObject::Shader(handle) => gl::DeleteShader(handle),
+                GlObject::Program(handle) => {
+                    bindings::forget_program(handle);
+                    gl::DeleteProgram(handle);
+                },
+                GlObject::Sampler(handle) => {
+                    bindings::forget_sampler(handle);
+                    gl::DeleteSamplers(1, &handle);
+                },
+                GlObject::Texture(handle) => {
+                    bindings::forget_texture(handle);
+                    gl::DeleteTextures(1, &handle);
+                },
+                GlObject::Framebuffer(handle) => gl::DeleteFramebuffers(1, &handle),
+                GlObject::Renderbuffer(handle) => gl::DeleteRenderbuffers(1, &handle),
+                GlObject::Query(handle) => gl::DeleteQueries(1, &handle),
+                GlObject::Fence(sync) => gl::DeleteSync(sync),
+            }
+        }
+    }
+}
+
+/// An object that was dropped while its context wasn't current, along with whether any context
+/// of the owner's share group can delete it.
+struct PendingDelete {
+    owner:  ContextId,
+    shared: bool,
+    object: GlObject,
+}
+
+impl PendingDelete {
+    fn can_delete(&self, current: ContextId) -> bool {
+        if self.shared { current.1 == self.owner.1 } else { current == self.owner }
+    }
+
+    /// Whether a context that can delete the object is still alive. The object was freed along
+    /// with its context otherwise.
+    fn is_deletable(&self) -> bool {
+        LIVE_CONTEXTS.with(|live| live.borrow().iter().any(|&id| self.can_delete(id)))
+    }
+}
+
+/// Deletes an object that only exists in `owner`, like a vertex array, or queues it until
+/// `owner` is current again.
+fn delete_unshared(owner: Option<ContextId>, object: GlObject) {
+    delete_or_queue(owner, false, object);
+}
+
+/// Deletes an object that is shared with every context in `owner`'s share group, or queues it
+/// until one of them is current again.
+fn delete_shared(owner: Option<ContextId>, object: GlObject) {
+    delete_or_queue(owner, true, object);
+}
+
+fn delete_or_queue(owner: Option<ContextId>, shared: bool, object: GlObject) {
+    let owner = match owner {
+        Some(owner) => owner,
+        // created outside of any registered context, so there's nothing to check against.
+        None => return object.delete(),
+    };
+    let pending = PendingDelete { owner, shared, object };
+    match ContextId::current() {
+        Some(current) if pending.can_delete(current) => pending.object.delete(),
+        _ if pending.is_deletable() => PENDING_DELETES.with(|queue| queue.borrow_mut().push(pending)),
+        _ => { /* NOP */ },
+    }
+}
+
+/// Deletes the queued objects that `current` can delete.
+fn delete_pending(current: ContextId) {
+    let deletable = PENDING_DELETES.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let (deletable, rest) = queue.drain(..).partition(|pending: &PendingDelete| pending.can_delete(current));
+        *queue = rest;
+        deletable
+    });
+    for pending in deletable {
+        pending.object.delete();
+    }
+}
+
+/// Sets the debug label of a GL object, which must already exist.
+fn set_object_label(identifier: gl::types::GLenum, handle: GLuint, label: &str) {
+    if !gl::ObjectLabel::is_loaded() { return }
+    unsafe {
+        gl::ObjectLabel(identifier, handle, label.len() as _, label.as_ptr() as *const _);
+    }
+}
+
+/// A binding that a `bind_scoped` method replaced.
+#[derive(Copy, Clone)]
+enum Binding {
+    Buffer(BufferType, GLuint),
+    VertexArray(GLuint),
+    Program(GLuint),
+    /// The texture unit along with the texture that was bound to it.
+    Texture(u32, GLuint),
+    Framebuffer(GLuint),
+}
+
+/// Binds what was bound before a `bind_scoped` call again when dropped. It borrows the object
+/// that was bound, so that can't be deleted while the guard is alive. Guards of the same kind of
+/// binding must be dropped in the reverse order they were created in, which scopes do anyway.
+#[must_use = "the previous binding is restored as soon as the guard is dropped"]
+pub struct BindGuard<'a> {
+    previous:   Binding,
+    _object:    std::marker::PhantomData<&'a ()>,
+}
+
+impl BindGuard<'_> {
+    fn new(previous: Binding) -> Self {
+        BindGuard { previous, _object: std::marker::PhantomData }
+    }
+}
+
+impl Drop for BindGuard<'_> {
+    fn drop(&mut self) {
+        match self.previous {
+            Binding::Buffer(buffer_type, handle) => bindings::bind_buffer(buffer_type, handle),
+            Binding::VertexArray(handle) => bindings::bind_vertex_array(handle, None),
+            Binding::Program(handle) => bindings::use_program(handle),
+            Binding::Texture(unit, handle) => {
+                bindings::active_texture(unit);
+                bindings::bind_texture(handle);
+            },
+            Binding::Framebuffer(handle) => Framebuffer::bind_handle(handle),
+        }
+    }
+}
+
+/// Binds `handle` to `TEXTURE_2D` of texture unit `unit`, which is left active.
+pub fn bind_texture_unit(unit: u32, handle: GLuint) {
+    bindings::active_texture(unit);
+    bindings::bind_texture(handle);
+}
+
+/// Forgets which objects are bound. The wrappers in this module remember what they last bound
+/// on the current thread and skip binding it again, so this has to be called after binding
+/// programs, vertex arrays, buffers or textures (or changing the active texture unit) with raw
+/// GL calls, before drawing with a `Context` again.
+pub fn invalidate_binding_cache() {
+    bindings::invalidate();
+}
+
+/// Remembers the objects that are bound on the current thread's context so that rebinding them
+/// can be skipped. `None` means that the binding isn't known.
+mod bindings {
+    use super::{ BufferType, GLuint };
+    use std::cell::Cell;
+
+    const TEXTURE_UNITS: usize = 8;
+
+    #[derive(Copy, Clone)]
+    struct Bindings {
+        program:                Option<GLuint>,
+        vertex_array:           Option<GLuint>,
+        array_buffer:           Option<GLuint>,
+        /// Part of the vertex array's state, so it's forgotten when the vertex array changes.
+        element_array_buffer:   Option<GLuint>,
+        /// Index of the active texture unit.
+        active_texture:         Option<u32>,
+        textures:               [Option<GLuint>; TEXTURE_UNITS],
+        samplers:               [Option<GLuint>; TEXTURE_UNITS],
+    }
+
+    const UNKNOWN: Bindings = Bindings {
+        program:                None,
+        vertex_array:           None,
+        array_buffer:           None,
+        element_array_buffer:   None,
+        active_texture:         None,
+        textures:               [None; TEXTURE_UNITS],
+        samplers:               [None; TEXTURE_UNITS],
+    };
+
+    thread_local! {
+        static BINDINGS: Cell<Bindings> = const { Cell::new(UNKNOWN) };
+    }
+
+    fn update<F: FnOnce(&mut Bindings)>(change: F) {
+        BINDINGS.with(|cell| {
+            let mut bindings = cell.get();
+            change(&mut bindings);
+            cell.set(bindings);
+        });
+    }
+
+    pub fn invalidate() {
+        BINDINGS.with(|cell| cell.set(UNKNOWN));
+    }
+
+    fn get() -> Bindings {
+        BINDINGS.with(|cell| cell.get())
+    }
+
+    /// Reads a binding from GL, for when it isn't known.
+    fn query(name: gl::types::GLenum) -> GLuint {
+        let mut value = 0;
+        unsafe {
+            gl::GetIntegerv(name, &mut value);
+        }
+        value as GLuint
+    }
+
+    // these return what's currently bound, from the cache if possible.
+
+    pub fn program() -> GLuint {
+        get().program.unwrap_or_else(|| query(gl::CURRENT_PROGRAM))
+    }
+
+    pub fn vertex_array() -> GLuint {
+        get().vertex_array.unwrap_or_else(|| query(gl::VERTEX_ARRAY_BINDING))
+    }
+
+    pub fn buffer(buffer_type: BufferType) -> GLuint {
+        let b = get();
+        match buffer_type {
+            BufferType::ArrayBuffer => b.array_buffer.unwrap_or_else(|| query(gl::ARRAY_BUFFER_BINDING)),
+            BufferType::ElementArrayBuffer => b.element_array_buffer.unwrap_or_else(|| query(gl::ELEMENT_ARRAY_BUFFER_BINDING)),
+            BufferType::PixelUnpackBuffer => query(gl::PIXEL_UNPACK_BUFFER_BINDING),
+            BufferType::PixelPackBuffer => query(gl::PIXEL_PACK_BUFFER_BINDING),
+        }
+    }
+
+    pub fn active_texture_unit() -> u32 {
+        get().active_texture.unwrap_or_else(|| query(gl::ACTIVE_TEXTURE) - gl::TEXTURE0)
+    }
+
+    /// The texture bound to the active texture unit.
+    pub fn texture() -> GLuint {
+        let b = get();
+        let cached = b.active_texture.and_then(|unit| b.textures.get(unit as usize).copied().flatten());
+        cached.unwrap_or_else(|| query(gl::TEXTURE_BINDING_2D))
+    }
+
+    pub fn use_program(handle: GLuint) {
+        update(|b| {
+            if b.program == Some(handle) { return }
+            unsafe {
+                gl::UseProgram(handle);
+            }
+            b.program = Some(handle);
+        });
+    }
+
+    /// `elements` is the element buffer recorded in the vertex array if it's known.
+    pub fn bind_vertex_array(handle: GLuint, elements: Option<GLuint>) {
+        update(|b| {
+            if b.vertex_array == Some(handle) { return }
+            unsafe {
+                gl::BindVertexArray(handle);
+            }
+            b.vertex_array = Some(handle);
+            b.element_array_buffer = elements;
+        });
+    }
+
+    pub fn bind_buffer(buffer_type: BufferType, handle: GLuint) {
+        update(|b| {
+            let binding = match buffer_type {
+                BufferType::ArrayBuffer => &mut b.array_buffer,
+                BufferType::ElementArrayBuffer => &mut b.element_array_buffer,
+                // bound only briefly, so they're always bound and unbound explicitly.
+                BufferType::PixelUnpackBuffer | BufferType::PixelPackBuffer => {
+                    unsafe {
+                        gl::BindBuffer(buffer_type.as_gl(), handle);
+                    }
+                    return;
+                },
+            };
+            if *binding == Some(handle) { return }
+            unsafe {
+                gl::BindBuffer(buffer_type.as_gl(), handle);
+            }
+            *binding = Some(handle);
+        });
+    }
+
+    pub fn active_texture(unit: u32) {
+        update(|b| {
+            if b.active_texture == Some(unit) { return }
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + unit);
+            }
+            b.active_texture = Some(unit);
+        });
+    }
+
+    /// Binds to the active texture unit.
+    pub fn bind_texture(handle: GLuint) {
+        update(|b| {
+            let binding = match b.active_texture {
+                Some(unit) if (unit as usize) < TEXTURE_UNITS => Some(&mut b.textures[unit as usize]),
+                _ => None,
+            };
+            match binding {
+                Some(binding) if *binding == Some(handle) => {},
+                Some(binding) => {
+                    unsafe {
+                        gl::BindTexture(gl::TEXTURE_2D, handle);
+                    }
+                    *binding = Some(handle);
+                },
+                None => unsafe {
+                    gl::BindTexture(gl::TEXTURE_2D, handle);
+                },
+            }
+        });
+    }
+
+    /// Sampler bindings belong to a texture unit but don't depend on the active one.
+    pub fn bind_sampler(unit: u32, handle: GLuint) {
+        update(|b| {
+            let binding = b.samplers.get_mut(unit as usize);
+            if let Some(&mut Some(bound)) = binding {
+                if bound == handle { return }
+            }
+            unsafe {
+                gl::BindSampler(unit, handle);
+            }
+            if let Some(binding) = b.samplers.get_mut(unit as usize) {
+                *binding = Some(handle);
+            }
+        });
+    }
+
+    // deleting a bound object binds 0 in its place, except for programs which stay in use
+    // until another one is.
+
+    pub fn forget_program(handle: GLuint) {
+        update(|b| {
+            if b.program == Some(handle) {
+                b.program = None;
+            }
+        });
+    }
+
+    pub fn forget_vertex_array(handle: GLuint) {
+        update(|b| {
+            if b.vertex_array == Some(handle) {
+                b.vertex_array = Some(0);
+                b.element_array_buffer = None;
+            }
+        });
+    }
+
+    pub fn forget_buffer(handle: GLuint) {
+        update(|b| {
+            for binding in [&mut b.array_buffer, &mut b.element_array_buffer] {
+                if *binding == Some(handle) {
+                    *binding = Some(0);
+                }
+            }
+        });
+    }
+
+    pub fn forget_texture(handle: GLuint) {
+        update(|b| {
+            for binding in b.textures.iter_mut() {
+                if *binding == Some(handle) {
+                    *binding = Some(0);
+                }
+            }
+        });
+    }
+
+    pub fn forget_sampler(handle: GLuint) {
+        update(|b| {
+            for binding in b.samplers.iter_mut() {
+                if *binding == Some(handle) {
+                    *binding = Some(0);
+                }
+            }
+        });
+    }
+}
+
+pub trait PixelData {
+    fn get_data_ptr(&self) -> *mut u8;
+    /// The size of the data in bytes.
+    fn byte_len(&self) -> usize;
+}
+
+impl PixelData for &[u32] {
+    fn get_data_ptr(&self) -> *mut u8 {
+        self.as_ptr() as *mut u8
+    }
+
+    fn byte_len(&self) -> usize {
+        mem::size_of_val(*self)
+    }
+}
+
+impl PixelData for &[u16] {
+    fn get_data_ptr(&self) -> *mut u8 {
+        self.as_ptr() as *mut u8
+    }
+
+    fn byte_len(&self) -> usize {
+        mem::size_of_val(*self)
+    }
+}
+
+impl PixelData for &[u8] {
+    fn get_data_ptr(&self) -> *mut u8 {
+        self.as_ptr() as *mut u8
+    }
+
+    fn byte_len(&self) -> usize {
+        mem::size_of_val(*self)
+    }
+}
+
+pub const FULLSCREEN_BUFFER_DATA: [f32; 24] = [
+    /* Position */  /* UV */
+    -1.0, -1.0,      0.0,  1.0, // bottom left
+    -1.0,  1.0,      0.0,  0.0, // top left
+     1.0, -1.0,      1.0,  1.0, // bottom right
+
+     1.0, -1.0,      1.0,  1.0, // bottom left
+     1.0,  1.0,      1.0,  0.0, // top right
+    -1.0,  1.0,      0.0,  0.0, // top left
+];
+
+pub const SIMPLE_VERTEX_SHADER: &str   = "\
+#version 130
+
+in  vec2 Position;
+in  vec2 UV;
+out vec2 FragUV;
+
+void main() {
+    FragUV = UV;
+    gl_Position = vec4(Position.xy, 0, 1);
+}\0";
+
+pub const SIMPLE_FRAGMENT_SHADER: &str = "\
+#version 130
+
+uniform sampler2D Texture;
+in  vec2 FragUV;
+out vec4 OutColor;
 
 void main() {
     OutColor = texture(Texture, FragUV.st);
@@ -553,3 +2747,357 @@ pub fn check_gl_errors<F: Fn(GLErrorType)>(on_error: F) -> bool {
     }
     error_occurred
 }
+
+/// Identifies the OpenGL implementation of the current context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlInfo {
+    pub version: String,
+    pub shading_language_version: String,
+    pub vendor: String,
+    pub renderer: String,
+    pub capabilities: GlCapabilities,
+}
+
+impl GlInfo {
+    /// Queries the current context.
+    pub fn query() -> GlInfo {
+        let version = get_gl_string(gl::VERSION);
+        GlInfo {
+            capabilities: GlCapabilities::query(&version),
+            version,
+            shading_language_version: get_gl_string(gl::SHADING_LANGUAGE_VERSION),
+            vendor: get_gl_string(gl::VENDOR),
+            renderer: get_gl_string(gl::RENDERER),
+        }
+    }
+}
+
+/// The version and profile of the current context and the features this crate uses that
+/// depend on them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GlCapabilities {
+    pub major: u32,
+    pub minor: u32,
+    /// Whether this is OpenGL ES rather than desktop GL.
+    pub es: bool,
+    /// Whether this is a desktop core profile context, without any of the deprecated API.
+    pub core_profile: bool,
+    pub vertex_arrays: bool,
+    pub instancing: bool,
+    /// Whether `GL_FRAMEBUFFER_SRGB` can be toggled.
+    pub srgb_framebuffer: bool,
+    pub debug_output: bool,
+    /// Whether fences (`glFenceSync`) are available.
+    pub sync_objects: bool,
+    /// Whether sampler objects are available. Sampling parameters are set on textures
+    /// otherwise.
+    pub sampler_objects: bool,
+    /// Whether `QueryType::TimeElapsed` queries are available.
+    pub timer_queries: bool,
+}
+
+impl GlCapabilities {
+    /// Queries the current context.
+    pub fn current() -> GlCapabilities {
+        GlCapabilities::query(&get_gl_string(gl::VERSION))
+    }
+
+    /// Queries the current context, whose `GL_VERSION` string is `version`. Features are
+    /// derived from the version and the extension list, since `glXGetProcAddress` and
+    /// `wglGetProcAddress` hand out entry points that the context doesn't actually support.
+    pub fn query(version: &str) -> GlCapabilities {
+        let es = version.starts_with("OpenGL ES");
+        let (major, minor) = parse_gl_version(version);
+        let at_least = |m: u32, n: u32| (major, minor) >= (m, n);
+        // the first version of desktop GL and GL ES that have a feature in core, if any.
+        let core_in = |desktop: (u32, u32), es_version: Option<(u32, u32)>| {
+            if es { es_version.map(|(m, n)| at_least(m, n)).unwrap_or(false) } else { at_least(desktop.0, desktop.1) }
+        };
+        let extensions = extension_list();
+        let has = |name: &str| extensions.iter().any(|extension| extension == name);
+
+        let core_profile = !es && at_least(3, 2) && {
+            let mut mask: GLint = 0;
+            unsafe {
+                gl::GetIntegerv(gl::CONTEXT_PROFILE_MASK, &mut mask);
+            }
+            mask as GLuint & gl::CONTEXT_CORE_PROFILE_BIT != 0
+        };
+
+        GlCapabilities {
+            major,
+            minor,
+            es,
+            core_profile,
+            vertex_arrays: (core_in((3, 0), Some((3, 0))) || has("GL_ARB_vertex_array_object"))
+                && gl::GenVertexArrays::is_loaded(),
+            instancing: (core_in((3, 3), Some((3, 0)))
+                    || (has("GL_ARB_instanced_arrays") && (at_least(3, 1) || has("GL_ARB_draw_instanced"))))
+                && gl::DrawElementsInstanced::is_loaded() && gl::VertexAttribDivisor::is_loaded(),
+            srgb_framebuffer: !es && at_least(3, 0),
+            debug_output: (core_in((4, 3), Some((3, 2))) || has("GL_KHR_debug"))
+                && gl::DebugMessageCallback::is_loaded(),
+            sync_objects: (core_in((3, 2), Some((3, 0))) || has("GL_ARB_sync"))
+                && gl::FenceSync::is_loaded() && gl::ClientWaitSync::is_loaded(),
+            sampler_objects: (core_in((3, 3), Some((3, 0))) || has("GL_ARB_sampler_objects"))
+                && Sampler::is_supported(),
+            timer_queries: !es && (at_least(3, 3) || has("GL_ARB_timer_query"))
+                && gl::GetQueryObjectui64v::is_loaded(),
+        }
+    }
+
+    /// The line that the built-in shaders start with on this context, or None if it can't run
+    /// them. They need GLSL 1.30 or GLSL ES 3.00.
+    pub fn glsl_version_line(&self) -> Option<&'static str> {
+        let version = (self.major, self.minor);
+        if self.es {
+            if version >= (3, 0) { Some("#version 300 es\nprecision highp float;") } else { None }
+        } else if self.core_profile {
+            if version >= (3, 3) { Some("#version 330 core") } else { Some("#version 150 core") }
+        } else if version >= (3, 0) {
+            Some("#version 130")
+        } else {
+            None
+        }
+    }
+
+    /// Whether shaders can give attributes explicit locations with `layout(location = N)`,
+    /// which GLSL 3.30 and GLSL ES 3.00 can.
+    pub fn explicit_attribute_locations(&self) -> bool {
+        match self.glsl_version_line() {
+            Some(line) => line.starts_with("#version 330") || line.starts_with("#version 300 es"),
+            None => false,
+        }
+    }
+
+    /// Rewrites the `#version 130` line of a built-in shader for this context. Shaders with
+    /// any other version line are returned as they are. Where attribute locations can be
+    /// explicit, the attributes in `ATTRIBUTE_LOCATIONS` are given theirs, so every program has
+    /// the same layout and strict core profile drivers don't have to pick one.
+    pub fn shader_source<'s>(&self, source: &'s str) -> Cow<'s, str> {
+        let rest = match (source.strip_prefix("#version 130\n"), self.glsl_version_line()) {
+            (Some(rest), Some(line)) if line != "#version 130" => rest,
+            _ => return Cow::from(source),
+        };
+        let line = self.glsl_version_line().unwrap_or_default();
+        if !self.explicit_attribute_locations() {
+            return Cow::from(format!("{}\n{}", line, rest));
+        }
+
+        let mut adapted = String::with_capacity(source.len() + 64);
+        adapted.push_str(line);
+        adapted.push('\n');
+        for declaration in rest.split_inclusive('\n') {
+            match attribute_location(declaration) {
+                Some(location) => adapted.push_str(&format!("layout(location = {}) {}", location, declaration.trim_start())),
+                None => adapted.push_str(declaration),
+            }
+        }
+        Cow::from(adapted)
+    }
+}
+
+/// The locations of the vertex attributes that the built-in shaders use. Attributes are still
+/// looked up by name when vertex arrays are set up, so these only have to be distinct.
+pub const ATTRIBUTE_LOCATIONS: [(&str, u32); 6] = [
+    ("Position", 0),
+    ("UV", 1),
+    ("Color", 2),
+    ("InstanceRect", 3),
+    ("InstanceColor", 4),
+    ("InstanceRotation", 5),
+];
+
+/// The location of the attribute declared by a line like `in vec2 Position;`, if it's one of
+/// `ATTRIBUTE_LOCATIONS`.
+fn attribute_location(line: &str) -> Option<u32> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("in") { return None }
+    let _ty = words.next()?;
+    let name = words.next()?.strip_suffix(';')?;
+    if words.next().is_some() { return None }
+    ATTRIBUTE_LOCATIONS.iter().find(|&&(attribute, _)| attribute == name).map(|&(_, location)| location)
+}
+
+impl std::fmt::Display for GlCapabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let profile = if self.es { "ES" } else if self.core_profile { "core" } else { "compatibility" };
+        write!(f, "GL {}.{} {}", self.major, self.minor, profile)?;
+        let features = [
+            ("vertex arrays", self.vertex_arrays),
+            ("instancing", self.instancing),
+            ("sRGB framebuffer", self.srgb_framebuffer),
+            ("debug output", self.debug_output),
+            ("sync objects", self.sync_objects),
+            ("sampler objects", self.sampler_objects),
+            ("timer queries", self.timer_queries),
+        ];
+        for (name, available) in features.iter() {
+            if !available {
+                write!(f, ", no {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the `major.minor` version at the start of a `GL_VERSION` string, after the
+/// `OpenGL ES` prefix of ES contexts. Returns `(0, 0)` if there isn't one.
+fn parse_gl_version(version: &str) -> (u32, u32) {
+    let version = version.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let mut numbers = version.split(|c: char| !c.is_ascii_digit()).map(|n| n.parse::<u32>().ok());
+    match (numbers.next().flatten(), numbers.next().flatten()) {
+        (Some(major), Some(minor)) => (major, minor),
+        (Some(major), None) => (major, 0),
+        _ => (0, 0),
+    }
+}
+
+impl std::fmt::Display for GlInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "GL version:   {}", self.version)?;
+        writeln!(f, "GLSL version: {}", self.shading_language_version)?;
+        writeln!(f, "GL vendor:    {}", self.vendor)?;
+        writeln!(f, "GL renderer:  {}", self.renderer)?;
+        write!(f, "Capabilities: {}", self.capabilities)
+    }
+}
+
+/// Returns an empty string if the implementation doesn't know `name`.
+fn get_gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(string as *const _).to_string_lossy().into_owned()
+        }
+    }
+}
+
+/// The names of all extensions of the current context.
+fn extension_list() -> Vec<String> {
+    let mut count: GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    }
+    (0..count.max(0) as GLuint).filter_map(|index| unsafe {
+        let extension = gl::GetStringi(gl::EXTENSIONS, index);
+        if extension.is_null() { return None }
+        Some(CStr::from_ptr(extension as *const _).to_string_lossy().into_owned())
+    }).collect()
+}
+
+/// Whether the current context supports the extension called `name`, e.g.
+/// `"GL_EXT_texture_compression_s3tc"`.
+pub fn has_extension(name: &str) -> bool {
+    let mut count: GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    }
+    (0..count.max(0) as GLuint).any(|index| unsafe {
+        let extension = gl::GetStringi(gl::EXTENSIONS, index);
+        !extension.is_null() && CStr::from_ptr(extension as *const _).to_bytes() == name.as_bytes()
+    })
+}
+
+/// The compressed formats that the driver lists as supported.
+fn compressed_texture_formats() -> Vec<GLint> {
+    let mut count: GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count);
+    }
+    let mut formats = vec![0; count.max(0) as usize];
+    if !formats.is_empty() {
+        unsafe {
+            gl::GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr());
+        }
+    }
+    formats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(major: u32, minor: u32, es: bool, core_profile: bool) -> GlCapabilities {
+        GlCapabilities {
+            major,
+            minor,
+            es,
+            core_profile,
+            vertex_arrays: true,
+            instancing: true,
+            srgb_framebuffer: !es,
+            debug_output: false,
+            sync_objects: true,
+            sampler_objects: true,
+            timer_queries: false,
+        }
+    }
+
+    const SHADER: &str = "#version 130\nin vec2 Position;\nin vec4 Color;\nin vec2 Other;\nout vec4 FragColor;\n";
+
+    #[test]
+    fn flip_rows_reverses_rows() {
+        let mut pixels: Vec<u8> = (0..24).collect();
+        flip_rows_rgba(&mut pixels, 2);
+        let expected: Vec<u8> = (16..24).chain(8..16).chain(0..8).collect();
+        assert_eq!(pixels, expected);
+
+        // zero width or a single row leaves the pixels alone.
+        let mut row: Vec<u8> = (0..8).collect();
+        flip_rows_rgba(&mut row, 2);
+        assert_eq!(row, (0..8).collect::<Vec<u8>>());
+        flip_rows_rgba(&mut [], 0);
+    }
+
+    #[test]
+    fn attribute_locations_come_from_the_table() {
+        assert_eq!(attribute_location("in vec2 Position;\n"), Some(0));
+        assert_eq!(attribute_location("  in vec4 Color;"), Some(2));
+        assert_eq!(attribute_location("in vec4 InstanceRect;"), Some(3));
+        assert_eq!(attribute_location("in vec2 Other;"), None);
+        assert_eq!(attribute_location("out vec4 Color;"), None);
+        assert_eq!(attribute_location("in vec2 Position"), None);
+        assert_eq!(attribute_location("in vec2 Position; // comment"), None);
+    }
+
+    #[test]
+    fn shader_source_keeps_glsl_130() {
+        let source = capabilities(3, 0, false, false).shader_source(SHADER);
+        assert!(matches!(source, Cow::Borrowed(_)));
+        assert_eq!(source, SHADER);
+    }
+
+    #[test]
+    fn shader_source_for_core_profiles() {
+        let source = capabilities(3, 2, false, true).shader_source(SHADER);
+        assert_eq!(source, SHADER.replacen("#version 130", "#version 150 core", 1));
+
+        let source = capabilities(4, 5, false, true).shader_source(SHADER);
+        assert_eq!(source, "#version 330 core\nlayout(location = 0) in vec2 Position;\nlayout(location = 2) in vec4 Color;\nin vec2 Other;\nout vec4 FragColor;\n");
+    }
+
+    #[test]
+    fn shader_source_for_gles() {
+        let source = capabilities(3, 0, true, false).shader_source(SHADER);
+        assert_eq!(source, "#version 300 es\nprecision highp float;\nlayout(location = 0) in vec2 Position;\nlayout(location = 2) in vec4 Color;\nin vec2 Other;\nout vec4 FragColor;\n");
+    }
+
+    #[test]
+    fn shader_source_leaves_unsupported_and_custom_versions_alone() {
+        // GL 2.1 can't run the built-in shaders at all.
+        assert_eq!(capabilities(2, 1, false, false).shader_source(SHADER), SHADER);
+        let custom = "#version 450\nin vec2 Position;\n";
+        assert_eq!(capabilities(4, 5, false, true).shader_source(custom), custom);
+    }
+
+    #[test]
+    fn parses_version_strings() {
+        assert_eq!(parse_gl_version("4.6.0 NVIDIA 535.54"), (4, 6));
+        assert_eq!(parse_gl_version("OpenGL ES 3.2 Mesa 23.1"), (3, 2));
+        assert_eq!(parse_gl_version("3"), (3, 0));
+        assert_eq!(parse_gl_version(""), (0, 0));
+    }
+}
@@ -0,0 +1,98 @@
+use super::math::Color;
+use super::opengl::{
+    Texture,
+    InternalPixelFormat,
+    PixelDataFormat,
+    PixelDataType,
+};
+
+/// 256 colors that `Context::draw_indexed_image` looks up the 8-bit indices of an indexed
+/// texture in. The colors live in a 256x1 texture, so swapping or animating a palette only
+/// uploads 1KB instead of a whole RGBA frame.
+pub struct Palette {
+    texture: Texture,
+    colors: Vec<Color>,
+}
+
+impl Palette {
+    pub const SIZE: usize = 256;
+
+    /// A palette where every entry is transparent black.
+    pub fn new() -> Palette {
+        let texture = Texture::new::<&[u8]>(Palette::SIZE as u32, 1, InternalPixelFormat::RGBA, PixelDataFormat::RGBA, PixelDataType::UnsignedByte, None);
+        texture.set_label("canvas.palette");
+        let palette = Palette {
+            texture,
+            colors: vec![Color { r: 0.0, g: 0.0, b: 0.0, a: 0.0 }; Palette::SIZE],
+        };
+        palette.upload(0, Palette::SIZE);
+        palette
+    }
+
+    /// A palette that starts with `colors`. Any remaining entries are transparent black.
+    pub fn from_colors(colors: &[Color]) -> Palette {
+        let mut palette = Palette::new();
+        palette.set_colors(0, colors);
+        palette
+    }
+
+    /// Replaces the entries starting at `first`. Colors that don't fit into the palette are
+    /// ignored.
+    pub fn set_colors(&mut self, first: usize, colors: &[Color]) {
+        let first = first.min(Palette::SIZE);
+        let count = colors.len().min(Palette::SIZE - first);
+        self.colors[first..(first + count)].copy_from_slice(&colors[..count]);
+        self.upload(first, count);
+    }
+
+    pub fn set_color(&mut self, index: u8, color: Color) {
+        self.set_colors(index as usize, &[color]);
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    fn upload(&self, first: usize, count: usize) {
+        if count == 0 { return }
+
+        let to_byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        let mut pixels = Vec::with_capacity(count * 4);
+        for c in self.colors[first..(first + count)].iter() {
+            pixels.extend_from_slice(&[to_byte(c.r), to_byte(c.g), to_byte(c.b), to_byte(c.a)]);
+        }
+
+        self.texture.sub_upload(first as u32, 0, count as u32, 1, &pixels[..]);
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::new()
+    }
+}
+
+/// Looks up the 8-bit index stored in the red channel of `Texture` in the 256x1 `Palette`
+/// texture. Both are read with `texelFetch` so neither is ever filtered.
+pub const PALETTE_FRAGMENT_SHADER: &str = "\
+#version 130
+
+uniform sampler2D Texture;
+uniform sampler2D Palette;
+in  vec2 FragUV;
+in  vec4 FragColor;
+out vec4 OutColor;
+
+void main() {
+    ivec2 size = textureSize(Texture, 0);
+    ivec2 texel = clamp(ivec2(floor(FragUV * vec2(size))), ivec2(0), size - 1);
+    int index = int(texelFetch(Texture, texel, 0).r * 255.0 + 0.5);
+    vec4 color = texelFetch(Palette, ivec2(index, 0), 0);
+    OutColor = FragColor * vec4(srgb_to_linear(color.rgb), color.a);
+    OutColor.a *= clip_coverage();
+    OutColor.rgb *= OutColor.a;
+}\0";
@@ -0,0 +1,170 @@
+use super::math::{ Mat4f, Vec4f, Rect };
+use super::context::Vert;
+use super::vertex_layout::VertexFormat;
+use super::program_cache::build_program;
+use super::opengl::{
+    VertexArray,
+    Buffer,
+    ShaderError,
+    GlCapabilities,
+    TextureUnit,
+    Program,
+};
+use gl::types::GLint;
+use std::cell::Cell;
+
+/// The coverage of the rounded clip at the current fragment, from 0.0 outside of it to 1.0
+/// inside, along with the uniforms and input it reads. `Pipeline::new` adds this to every
+/// fragment shader so the built-in shaders and effects share one definition.
+pub(crate) const CLIP_COVERAGE_GLSL: &str = "\
+uniform vec4  ClipRect;
+uniform float ClipRadius;
+uniform bool  ClipEnabled;
+in  vec2 FragScreenPos;
+
+float clip_coverage() {
+    if (!ClipEnabled) return 1.0;
+    vec2 half_size = ClipRect.zw * 0.5;
+    float radius = min(ClipRadius, min(half_size.x, half_size.y));
+    vec2 d = abs(FragScreenPos - (ClipRect.xy + half_size)) - half_size + radius;
+    float dist = length(max(d, 0.0)) + min(max(d.x, d.y), 0.0) - radius;
+    float aa = max(fwidth(dist), 1e-4);
+    return 1.0 - smoothstep(-aa, aa, dist);
+}
+
+";
+
+/// Converts an sRGB color to linear when the context draws in linear space, see
+/// `Context::set_srgb`. `Pipeline::new` adds this to both shaders of every pipeline.
+pub(crate) const SRGB_TO_LINEAR_GLSL: &str = "\
+uniform bool  LinearColors;
+
+vec3 srgb_to_linear(vec3 c) {
+    return LinearColors ? mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c)) : c;
+}
+
+";
+
+/// Inserts `snippets` after the first line of `source`, which is its `#version` line.
+fn with_snippets(source: &str, snippets: &[&str]) -> String {
+    let split = source.find('\n').map(|i| i + 1).unwrap_or(source.len());
+    let mut combined = String::with_capacity(source.len() + snippets.iter().map(|s| s.len()).sum::<usize>());
+    combined.push_str(&source[..split]);
+    for snippet in snippets {
+        combined.push_str(snippet);
+    }
+    combined.push_str(&source[split..]);
+    combined
+}
+
+/// The uniforms that `Pipeline::bind` sets.
+#[derive(Copy, Clone, PartialEq)]
+struct SharedUniforms {
+    projection:     Mat4f,
+    transform:      Mat4f,
+    rounded_clip:   Option<(Rect, f32)>,
+    linear_colors:  bool,
+}
+
+/// A linked program along with a vertex array for each of the context's stream buffers that
+/// feeds it `Vert`s from that buffer.
+pub(crate) struct Pipeline {
+    pub program: Program,
+    pub vertex_arrays: Vec<VertexArray>,
+
+    pub uniform_projmtx: GLint,
+    pub uniform_transform: GLint,
+    pub uniform_texture: GLint,
+    pub uniform_clip_rect: GLint,
+    pub uniform_clip_radius: GLint,
+    pub uniform_clip_enabled: GLint,
+    pub uniform_linear_colors: GLint,
+
+    /// The values that `bind` last set the shared uniforms to, so that setting them again can
+    /// be skipped. Uniforms are part of the program's state so these stay valid until the
+    /// program is linked again.
+    last_uniforms: Cell<Option<SharedUniforms>>,
+}
+
+impl Pipeline {
+    /// The sources are built-in shaders, which are adapted to the GLSL version of the context.
+    /// Both shaders get `SRGB_TO_LINEAR_GLSL` and the fragment shader also gets
+    /// `CLIP_COVERAGE_GLSL` added after their version lines.
+    /// `stream_buffers` are the vertex and element buffers of each stream, which are recorded
+    /// in the stream's vertex array.
+    pub fn new(vertex_source: &str, fragment_source: &str, stream_buffers: &[(&Buffer, &Buffer)], capabilities: &GlCapabilities) -> Result<Pipeline, ShaderError> {
+        let vertex_source = with_snippets(vertex_source, &[SRGB_TO_LINEAR_GLSL]);
+        let fragment_source = with_snippets(fragment_source, &[SRGB_TO_LINEAR_GLSL, CLIP_COVERAGE_GLSL]);
+        let program = build_program(&capabilities.shader_source(&vertex_source), &capabilities.shader_source(&fragment_source))?;
+        let vertex_arrays = stream_buffers.iter().map(|&(vertex_buffer, element_buffer)| {
+            let vertex_array = VertexArray::new();
+            attach_vertex_buffer(&program, &vertex_array, vertex_buffer);
+            vertex_array.set_element_buffer(element_buffer);
+            vertex_array
+        }).collect();
+
+        Ok(Pipeline {
+            uniform_projmtx: program.uniform_location("ProjMtx\0"),
+            uniform_transform: program.uniform_location("Transform\0"),
+            uniform_texture: program.uniform_location("Texture\0"),
+            uniform_clip_rect: program.uniform_location("ClipRect\0"),
+            uniform_clip_radius: program.uniform_location("ClipRadius\0"),
+            uniform_clip_enabled: program.uniform_location("ClipEnabled\0"),
+            uniform_linear_colors: program.uniform_location("LinearColors\0"),
+
+            program,
+            vertex_arrays,
+            last_uniforms: Cell::new(None),
+        })
+    }
+
+    /// Labels the program as `name` and its vertex arrays as `name.vertex_array<stream>` for
+    /// GL debuggers.
+    pub fn set_label(&self, name: &str) {
+        self.program.set_label(name);
+        for (stream, vertex_array) in self.vertex_arrays.iter().enumerate() {
+            vertex_array.set_label(&format!("{}.vertex_array{}", name, stream));
+        }
+    }
+
+    /// Creates a vertex array that feeds `Vert`s from `vertex_buffer` to this pipeline's
+    /// program, for buffers other than the context's shared one.
+    pub fn vertex_array_for(&self, vertex_buffer: &Buffer) -> VertexArray {
+        let vertex_array = VertexArray::new();
+        attach_vertex_buffer(&self.program, &vertex_array, vertex_buffer);
+        vertex_array
+    }
+
+    /// Binds the program and the vertex array of the stream buffer with index `stream` and sets
+    /// the uniforms shared by every pipeline.
+    /// `rounded_clip` is a rectangle in display coordinates and a corner radius that fragments
+    /// are clipped to. `linear_colors` converts sRGB vertex and uniform colors to linear ones.
+    pub fn bind(&self, stream: usize, projection: &Mat4f, transform: &Mat4f, rounded_clip: Option<(Rect, f32)>, linear_colors: bool) {
+        self.program.bind();
+        self.vertex_arrays[stream].bind();
+
+        let uniforms = SharedUniforms { projection: *projection, transform: *transform, rounded_clip, linear_colors };
+        if self.last_uniforms.get() == Some(uniforms) { return }
+        self.last_uniforms.set(Some(uniforms));
+
+        let program = &self.program;
+        program.set_uniform(self.uniform_projmtx, projection);
+        program.set_uniform(self.uniform_transform, transform);
+        program.set_uniform(self.uniform_texture, TextureUnit(0));
+        program.set_uniform(self.uniform_linear_colors, linear_colors);
+
+        if let Some((rect, radius)) = rounded_clip {
+            program.set_uniform(self.uniform_clip_enabled, true);
+            program.set_uniform(self.uniform_clip_rect, Vec4f::new(rect.x, rect.y, rect.width, rect.height));
+            program.set_uniform(self.uniform_clip_radius, radius);
+        } else {
+            program.set_uniform(self.uniform_clip_enabled, false);
+        }
+    }
+}
+
+/// Points the `Position`, `UV` and `Color` attributes of `program` at the `Vert`s in
+/// `vertex_buffer`. Leaves `vertex_array` bound.
+fn attach_vertex_buffer(program: &Program, vertex_array: &VertexArray, vertex_buffer: &Buffer) {
+    Vert::layout().attach(program, vertex_array, vertex_buffer);
+}
@@ -0,0 +1,62 @@
+use super::math::{ Color, Rect };
+
+/// How `Context::present_pixel_perfect` fits a low resolution image, like an emulator's
+/// framebuffer or a retro game's `Surface`, onto the display: scaled up by the largest whole
+/// number that fits, centered, with bars filling the rest.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IntegerScaling {
+    /// Width of a source pixel relative to its height. Use 1.0 for square pixels or something
+    /// like 8.0 / 7.0 to correct the aspect ratio of systems with non-square pixels. Only the
+    /// vertical scale is kept to a whole number when this isn't 1.0.
+    pub pixel_aspect:   f32,
+    pub bar_color:      Color,
+}
+
+impl IntegerScaling {
+    pub fn new() -> IntegerScaling {
+        IntegerScaling {
+            pixel_aspect: 1.0,
+            bar_color: Color::BLACK,
+        }
+    }
+
+    pub fn with_pixel_aspect(self, pixel_aspect: f32) -> IntegerScaling {
+        IntegerScaling {
+            pixel_aspect,
+            ..self
+        }
+    }
+
+    pub fn with_bar_color(self, bar_color: Color) -> IntegerScaling {
+        IntegerScaling {
+            bar_color,
+            ..self
+        }
+    }
+
+    /// The largest whole scale at which a `width` by `height` image fits on the display. This
+    /// is never less than 1, so images larger than the display are cropped.
+    pub fn scale(&self, width: u32, height: u32, display_width: f32, display_height: f32) -> u32 {
+        let aspect_width = width as f32 * self.pixel_aspect.max(f32::EPSILON);
+        let fit = (display_width / aspect_width).min(display_height / height as f32);
+        if fit.is_finite() { fit.floor().max(1.0) as u32 } else { 1 }
+    }
+
+    /// Where the image is drawn in display coordinates. The rect is aligned to whole pixels.
+    pub fn dest_rect(&self, width: u32, height: u32, display_width: f32, display_height: f32) -> Rect {
+        let scale = self.scale(width, height, display_width, display_height) as f32;
+        let dest_width = (width as f32 * self.pixel_aspect * scale).round();
+        let dest_height = height as f32 * scale;
+        Rect::new(
+            ((display_width - dest_width) / 2.0).floor(),
+            ((display_height - dest_height) / 2.0).floor(),
+            dest_width,
+            dest_height)
+    }
+}
+
+impl Default for IntegerScaling {
+    fn default() -> IntegerScaling {
+        IntegerScaling::new()
+    }
+}
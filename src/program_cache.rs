@@ -0,0 +1,121 @@
+//! Caches linked programs on disk so the built-in shaders, effects and custom pipelines don't
+//! have to be compiled again on the next start. Compiling takes a noticeable amount of time on
+//! some drivers.
+//!
+//! The cache is off until `enable` is called, which should happen before the `Context` is
+//! created. Binaries are only valid for the driver and GPU that produced them, so they're
+//! stored under a key that includes both and outdated ones are simply not found anymore.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use super::opengl::{ Shader, ShaderType, ShaderError, Program, ProgramBinary, GlInfo };
+
+thread_local! {
+    /// The directory of the cache along with the GL implementation that binaries are keyed
+    /// with, which is queried once the first program is built.
+    static CACHE: RefCell<Option<(PathBuf, Option<String>)>> = const { RefCell::new(None) };
+}
+
+/// Caches the programs that are built on this thread from now on in `dir`, which is created
+/// when the first binary is saved.
+pub fn enable<P: Into<PathBuf>>(dir: P) {
+    CACHE.with(|cache| *cache.borrow_mut() = Some((dir.into(), None)));
+}
+
+/// Goes back to compiling every program. Binaries that were saved are left on disk.
+pub fn disable() {
+    CACHE.with(|cache| *cache.borrow_mut() = None);
+}
+
+pub fn is_enabled() -> bool {
+    CACHE.with(|cache| cache.borrow().is_some())
+}
+
+/// Compiles and links a program from a vertex and a fragment shader, or loads it from the cache
+/// if it's enabled and has the program.
+pub(crate) fn build_program(vertex_source: &str, fragment_source: &str) -> Result<Program, ShaderError> {
+    build_program_with_outputs(vertex_source, fragment_source, &[])
+}
+
+/// Like `build_program`, binding the fragment shader's outputs like `Program::link_with_outputs`.
+pub(crate) fn build_program_with_outputs(vertex_source: &str, fragment_source: &str, outputs: &[&str]) -> Result<Program, ShaderError> {
+    let path = CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let (dir, implementation) = cache.as_mut()?;
+        let implementation = implementation.get_or_insert_with(|| {
+            let info = GlInfo::query();
+            format!("{}\n{}\n{}", info.vendor, info.renderer, info.version)
+        });
+        let outputs = outputs.join(",");
+        let mut parts = vec![implementation.as_bytes(), vertex_source.as_bytes(), fragment_source.as_bytes()];
+        // left out without outputs, so that programs cached before they existed stay valid.
+        if !outputs.is_empty() {
+            parts.push(outputs.as_bytes());
+        }
+        let key = hash(&parts);
+        Some(dir.join(format!("{:016x}.bin", key)))
+    });
+
+    if let Some(ref path) = path {
+        let cached = std::fs::read(path).ok()
+            .and_then(|bytes| ProgramBinary::from_bytes(&bytes))
+            .and_then(|binary| Program::from_binary(&binary));
+        if let Some(program) = cached {
+            return Ok(program);
+        }
+    }
+
+    let vertex_shader = Shader::compile(ShaderType::Vertex, vertex_source)?;
+    let fragment_shader = Shader::compile(ShaderType::Fragment, fragment_source)?;
+    let program = Program::link_with_outputs(&[&vertex_shader, &fragment_shader], outputs)?;
+
+    if let (Some(path), Some(binary)) = (path, program.binary()) {
+        // the cache is only an optimization, so failing to write it isn't an error.
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = std::fs::write(&path, binary.to_bytes());
+    }
+    Ok(program)
+}
+
+/// 64-bit FNV-1a over `parts`, which unlike the standard library's hasher is the same in
+/// every build, so keys stay valid across versions of the program.
+fn hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts.iter() {
+        for &byte in part.iter().chain(&[0xFF]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_is_stable_and_separates_parts() {
+        // FNV-1a of the empty input, which keys must not depend on the build for.
+        assert_eq!(hash(&[]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(&[b"vertex", b"fragment"]), hash(&[b"vertex", b"fragment"]));
+        assert_ne!(hash(&[b"ab", b"c"]), hash(&[b"a", b"bc"]));
+        assert_ne!(hash(&[b"vertex", b"fragment"]), hash(&[b"fragment", b"vertex"]));
+    }
+
+    #[test]
+    fn binaries_round_trip_through_bytes() {
+        let binary = ProgramBinary { format: 0x8741, data: vec![1, 2, 3, 4, 5] };
+        let bytes = binary.to_bytes();
+        assert_eq!(&bytes[..4], &0x8741u32.to_le_bytes());
+        assert_eq!(ProgramBinary::from_bytes(&bytes), Some(binary));
+    }
+
+    #[test]
+    fn binaries_without_data_are_rejected() {
+        assert_eq!(ProgramBinary::from_bytes(&[]), None);
+        assert_eq!(ProgramBinary::from_bytes(&[1, 0, 0, 0]), None);
+    }
+}
@@ -0,0 +1,273 @@
+use glutin::{
+    ElementState,
+    KeyboardInput,
+    ModifiersState,
+    VirtualKeyCode,
+};
+use super::window::Window;
+
+/// A key plus the exact set of modifiers that must be held for it to trigger.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub key: VirtualKeyCode,
+    pub modifiers: ModifiersState,
+}
+
+impl Chord {
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> Chord {
+        Chord { key, modifiers }
+    }
+
+    /// Parses a descriptor like "Ctrl+Shift+S" or "CmdOrCtrl+Z".
+    ///
+    /// Modifier names are case insensitive. `Ctrl`, `Shift`, `Alt` and `Cmd` (also `Super`, `Logo`,
+    /// `Meta`) map directly to a modifier while `CmdOrCtrl` (also `Mod`, `Primary`) maps to the
+    /// command key on macOS and to control everywhere else.
+    ///
+    /// `Plus` is the key that types a plus sign on US layouts, which is `=` with shift, so it
+    /// adds shift to the modifiers. `NumpadPlus` is the plus key of the numeric keypad.
+    pub fn parse(descriptor: &str) -> Result<Chord, ChordParseError> {
+        let mut modifiers = ModifiersState::default();
+        let mut key = None;
+
+        for part in descriptor.split('+').map(str::trim) {
+            if part.is_empty() {
+                return Err(ChordParseError::Empty(descriptor.to_string()));
+            }
+
+            if key.is_some() {
+                // only the final component of a chord may be a key.
+                return Err(ChordParseError::UnexpectedKey(descriptor.to_string()));
+            }
+
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" | "option" => modifiers.alt = true,
+                "cmd" | "command" | "super" | "logo" | "meta" | "win" => modifiers.logo = true,
+                "cmdorctrl" | "mod" | "primary" => {
+                    if cfg!(target_os = "macos") {
+                        modifiers.logo = true;
+                    } else {
+                        modifiers.ctrl = true;
+                    }
+                },
+                _ => {
+                    let (parsed, shift) = parse_key(part).ok_or_else(|| ChordParseError::UnknownKey(part.to_string()))?;
+                    modifiers.shift |= shift;
+                    key = Some(parsed);
+                },
+            }
+        }
+
+        key.map(|key| Chord { key, modifiers })
+            .ok_or_else(|| ChordParseError::MissingKey(descriptor.to_string()))
+    }
+
+    /// Returns true if a key press matches this chord exactly.
+    pub fn matches(&self, input: &KeyboardInput) -> bool {
+        input.state == ElementState::Pressed &&
+            input.virtual_keycode == Some(self.key) &&
+            input.modifiers == self.modifiers
+    }
+}
+
+impl std::str::FromStr for Chord {
+    type Err = ChordParseError;
+
+    fn from_str(descriptor: &str) -> Result<Chord, ChordParseError> {
+        Chord::parse(descriptor)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChordParseError {
+    /// The descriptor (or one of its components) was empty.
+    Empty(String),
+    /// The descriptor only contained modifiers.
+    MissingKey(String),
+    /// A key was followed by further components.
+    UnexpectedKey(String),
+    /// A component was not a modifier or a recognized key name.
+    UnknownKey(String),
+}
+
+impl std::fmt::Display for ChordParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ChordParseError::Empty(d) => write!(f, "empty key chord component in `{}`", d),
+            ChordParseError::MissingKey(d) => write!(f, "key chord `{}` has no key", d),
+            ChordParseError::UnexpectedKey(d) => write!(f, "key chord `{}` must end with its only key", d),
+            ChordParseError::UnknownKey(k) => write!(f, "unknown key `{}`", k),
+        }
+    }
+}
+
+impl std::error::Error for ChordParseError {}
+
+/// Maps key chords to application defined actions.
+pub struct Shortcuts<A> {
+    bindings: Vec<(Chord, A)>,
+}
+
+impl<A> Shortcuts<A> {
+    pub fn new() -> Shortcuts<A> {
+        Shortcuts {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds a chord descriptor (see `Chord::parse`) to an action, replacing any action that
+    /// was previously bound to the same chord.
+    pub fn bind(&mut self, descriptor: &str, action: A) -> Result<(), ChordParseError> {
+        let chord = Chord::parse(descriptor)?;
+        self.bind_chord(chord, action);
+        Ok(())
+    }
+
+    pub fn bind_chord(&mut self, chord: Chord, action: A) {
+        if let Some(binding) = self.bindings.iter_mut().find(|(c, _)| *c == chord) {
+            binding.1 = action;
+        } else {
+            self.bindings.push((chord, action));
+        }
+    }
+
+    /// Removes the binding for a chord, returning its action if there was one.
+    pub fn unbind(&mut self, descriptor: &str) -> Result<Option<A>, ChordParseError> {
+        let chord = Chord::parse(descriptor)?;
+        Ok(self.bindings.iter().position(|(c, _)| *c == chord).map(|idx| self.bindings.remove(idx).1))
+    }
+
+    /// Returns the action bound to a single keyboard input, if any.
+    pub fn match_input(&self, input: &KeyboardInput) -> Option<&A> {
+        self.bindings.iter()
+            .find(|(chord, _)| chord.matches(input))
+            .map(|(_, action)| action)
+    }
+
+    /// Returns the actions triggered by the keyboard input the window received during its last
+    /// call to `handle_events`, in the order the keys were pressed.
+    pub fn triggered<'a>(&'a self, window: &'a Window) -> impl Iterator<Item = &'a A> + 'a {
+        window.keyboard_input().iter().filter_map(move |input| self.match_input(input))
+    }
+}
+
+impl<A> Default for Shortcuts<A> {
+    fn default() -> Shortcuts<A> {
+        Shortcuts::new()
+    }
+}
+
+/// Returns the key with a name and whether typing it takes shift.
+fn parse_key(name: &str) -> Option<(VirtualKeyCode, bool)> {
+    use VirtualKeyCode::*;
+
+    if name.eq_ignore_ascii_case("plus") {
+        return Some((Equals, true));
+    }
+
+    const LETTERS: [VirtualKeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const FUNCTION_KEYS: [VirtualKeyCode; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+    let lower = name.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+
+    if bytes.len() == 1 {
+        let c = bytes[0];
+        let key = match c {
+            b'a'..=b'z' => Some(LETTERS[(c - b'a') as usize]),
+            b'0'..=b'9' => Some(DIGITS[(c - b'0') as usize]),
+            b'-' => Some(Minus),
+            b'=' => Some(Equals),
+            b',' => Some(Comma),
+            b'.' => Some(Period),
+            b'/' => Some(Slash),
+            b'\\' => Some(Backslash),
+            b';' => Some(Semicolon),
+            b'\'' => Some(Apostrophe),
+            b'`' => Some(Grave),
+            b'[' => Some(LBracket),
+            b']' => Some(RBracket),
+            _ => None,
+        };
+        return key.map(|key| (key, false));
+    }
+
+    if bytes[0] == b'f' {
+        if let Ok(n) = lower[1..].parse::<usize>() {
+            return if n >= 1 && n <= FUNCTION_KEYS.len() { Some((FUNCTION_KEYS[n - 1], false)) } else { None };
+        }
+    }
+
+    let key = match lower.as_str() {
+        "esc" | "escape" => Some(Escape),
+        "enter" | "return" => Some(Return),
+        "space" => Some(Space),
+        "tab" => Some(Tab),
+        "backspace" => Some(Back),
+        "del" | "delete" => Some(Delete),
+        "ins" | "insert" => Some(Insert),
+        "home" => Some(Home),
+        "end" => Some(End),
+        "pageup" => Some(PageUp),
+        "pagedown" => Some(PageDown),
+        "up" => Some(Up),
+        "down" => Some(Down),
+        "left" => Some(Left),
+        "right" => Some(Right),
+        "numpadplus" => Some(Add),
+        "minus" => Some(Minus),
+        "equals" => Some(Equals),
+        "comma" => Some(Comma),
+        "period" => Some(Period),
+        _ => None,
+    };
+    key.map(|key| (key, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifiers(ctrl: bool, shift: bool, alt: bool, logo: bool) -> ModifiersState {
+        ModifiersState { ctrl, shift, alt, logo }
+    }
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        let chord = Chord::parse("Ctrl+Shift+S").unwrap();
+        assert_eq!(chord, Chord::new(VirtualKeyCode::S, modifiers(true, true, false, false)));
+
+        let chord: Chord = "alt + f4".parse().unwrap();
+        assert_eq!(chord, Chord::new(VirtualKeyCode::F4, modifiers(false, false, true, false)));
+    }
+
+    #[test]
+    fn cmd_or_ctrl_follows_the_platform() {
+        let chord = Chord::parse("CmdOrCtrl+Z").unwrap();
+        let expected = if cfg!(target_os = "macos") { modifiers(false, false, false, true) } else { modifiers(true, false, false, false) };
+        assert_eq!(chord.modifiers, expected);
+    }
+
+    #[test]
+    fn plus_is_the_main_row_key() {
+        let chord = Chord::parse("Ctrl+Plus").unwrap();
+        assert_eq!(chord, Chord::new(VirtualKeyCode::Equals, modifiers(true, true, false, false)));
+
+        let chord = Chord::parse("Ctrl+NumpadPlus").unwrap();
+        assert_eq!(chord, Chord::new(VirtualKeyCode::Add, modifiers(true, false, false, false)));
+    }
+
+    #[test]
+    fn rejects_malformed_descriptors() {
+        assert_eq!(Chord::parse("Ctrl++"), Err(ChordParseError::Empty("Ctrl++".to_string())));
+        assert_eq!(Chord::parse("A+B"), Err(ChordParseError::UnexpectedKey("A+B".to_string())));
+        assert_eq!(Chord::parse("Ctrl+Nope"), Err(ChordParseError::UnknownKey("Nope".to_string())));
+        assert_eq!(Chord::parse("Ctrl+Shift"), Err(ChordParseError::MissingKey("Ctrl+Shift".to_string())));
+        assert_eq!(Chord::parse("F13"), Err(ChordParseError::UnknownKey("F13".to_string())));
+    }
+}
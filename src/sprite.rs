@@ -0,0 +1,50 @@
+use super::math::{ Vec2f, vec2f };
+
+/// Per-draw adjustments for `Context::draw_image_with`. These change the image's vertices
+/// instead of the context's transform, so consecutive sprites still share a draw call.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteTransform {
+    /// Mirrors the image horizontally inside its destination rect.
+    pub flip_x:     bool,
+    /// Mirrors the image vertically inside its destination rect.
+    pub flip_y:     bool,
+    /// Rotation in radians around `pivot`, clockwise on screen.
+    pub rotation:   f32,
+    /// The point the image rotates around relative to its destination rect, where `(0, 0)` is
+    /// the top left corner and `(1, 1)` is the bottom right corner.
+    pub pivot:      Vec2f,
+}
+
+impl SpriteTransform {
+    /// Draws the image as is.
+    pub fn new() -> SpriteTransform {
+        SpriteTransform {
+            flip_x: false,
+            flip_y: false,
+            rotation: 0.0,
+            pivot: vec2f(0.5, 0.5),
+        }
+    }
+
+    pub fn with_flip(self, flip_x: bool, flip_y: bool) -> SpriteTransform {
+        SpriteTransform {
+            flip_x,
+            flip_y,
+            ..self
+        }
+    }
+
+    pub fn with_rotation(self, rotation: f32, pivot: Vec2f) -> SpriteTransform {
+        SpriteTransform {
+            rotation,
+            pivot,
+            ..self
+        }
+    }
+}
+
+impl Default for SpriteTransform {
+    fn default() -> SpriteTransform {
+        SpriteTransform::new()
+    }
+}
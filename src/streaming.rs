@@ -0,0 +1,119 @@
+use super::opengl::{
+    Buffer,
+    BufferType,
+    Texture,
+    InternalPixelFormat,
+    PixelDataFormat,
+    PixelDataType,
+    with_unpack_alignment,
+};
+
+/// A texture whose entire contents are replaced from CPU memory every frame, like the
+/// framebuffer of an emulator. Storage is allocated once and updates only upload pixels.
+///
+/// Derefs to `Texture` so it can be passed straight to `Context::draw_image_scaled`.
+pub struct StreamingTexture {
+    texture: Texture,
+    format: PixelDataFormat,
+    data_type: PixelDataType,
+    /// Pixels are staged here before they're copied into the texture if set.
+    pixel_buffer: Option<Buffer>,
+}
+
+impl StreamingTexture {
+    pub fn new(width: u32, height: u32, internal_format: InternalPixelFormat, format: PixelDataFormat, data_type: PixelDataType) -> StreamingTexture {
+        StreamingTexture {
+            texture: Texture::new::<&[u8]>(width, height, internal_format, format, data_type, None),
+            format,
+            data_type,
+            pixel_buffer: None,
+        }
+    }
+
+    /// Stages updates in a pixel buffer object. The texture is then filled from the buffer by
+    /// the GPU after the update returns, instead of the driver waiting until the texture is no
+    /// longer in use by the previous frame's draws. Every update orphans the buffer's storage,
+    /// so it never has to wait for the previous upload either.
+    pub fn with_pixel_buffer(mut self) -> StreamingTexture {
+        self.pixel_buffer = Some(Buffer::new(BufferType::PixelUnpackBuffer));
+        self
+    }
+
+    /// A streaming texture that is updated with tightly packed RGBA8 pixels.
+    pub fn rgba(width: u32, height: u32) -> StreamingTexture {
+        StreamingTexture::new(width, height, InternalPixelFormat::RGBA, PixelDataFormat::RGBA, PixelDataType::UnsignedByte)
+    }
+
+    /// A streaming texture that is updated with one byte palette indices per pixel, for
+    /// `Context::draw_indexed_image`.
+    pub fn indexed(width: u32, height: u32) -> StreamingTexture {
+        StreamingTexture::new(width, height, InternalPixelFormat::Red, PixelDataFormat::Red, PixelDataType::UnsignedByte)
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.data_type.bytes_per_pixel(self.format)
+    }
+
+    /// Replaces the contents of the texture with tightly packed rows of pixels, top row first.
+    pub fn update(&mut self, pixels: &[u8]) {
+        let row_len = self.texture.get_width() as usize * self.bytes_per_pixel();
+        self.update_with_stride(pixels, row_len);
+    }
+
+    /// Replaces the contents of the texture with rows of pixels that start `stride` bytes apart,
+    /// top row first. `stride` must be a multiple of the size of a pixel.
+    pub fn update_with_stride(&mut self, pixels: &[u8], stride: usize) {
+        let (width, height) = (self.texture.get_width() as usize, self.texture.get_height() as usize);
+        let bpp = self.bytes_per_pixel();
+        assert!(stride >= width * bpp && stride % bpp == 0, "row stride of {} bytes is invalid for {} pixels of {} bytes", stride, width, bpp);
+        assert!(height == 0 || pixels.len() >= stride * (height - 1) + width * bpp, "not enough pixel data for a {}x{} texture", width, height);
+
+        // with a pixel buffer bound, the pointer is an offset into the buffer instead.
+        let source = match self.pixel_buffer {
+            Some(ref buffer) => {
+                let len = if height == 0 { 0 } else { stride * (height - 1) + width * bpp };
+                buffer.bind();
+                buffer.stream_data(&pixels[..len]);
+                std::ptr::null()
+            },
+            None => pixels.as_ptr(),
+        };
+
+        self.texture.bind();
+        // rows aren't padded to 4 bytes, which is GL's default expectation.
+        with_unpack_alignment(1, || unsafe {
+            let mut row_length = 0;
+            gl::GetIntegerv(gl::UNPACK_ROW_LENGTH, &mut row_length);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, (stride / bpp) as _);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D, 0, 0, 0, width as _, height as _,
+                self.format.as_gl(), self.data_type.as_gl(), source as *const _);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, row_length);
+        });
+
+        if let Some(ref buffer) = self.pixel_buffer {
+            buffer.unbind();
+        }
+
+        if self.texture.has_mipmaps() {
+            self.texture.generate_mipmaps();
+        }
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Gives the texture mipmaps, which are regenerated by every update from then on.
+    pub fn generate_mipmaps(&mut self) {
+        self.texture.generate_mipmaps();
+    }
+}
+
+impl std::ops::Deref for StreamingTexture {
+    type Target = Texture;
+
+    fn deref(&self) -> &Texture {
+        &self.texture
+    }
+}
@@ -0,0 +1,78 @@
+use super::opengl::{
+    Framebuffer,
+    Renderbuffer,
+    Texture,
+    InternalPixelFormat,
+    PixelDataFormat,
+    PixelDataType,
+    read_pixels_rgba,
+};
+use super::image::Image;
+
+/// An offscreen RGBA texture that a `Context` can draw into with `Context::begin_target`. Once
+/// drawing has ended, the texture can be drawn like any other image.
+pub struct Surface {
+    framebuffer: Framebuffer,
+    texture: Texture,
+    /// Lets clip masks work while drawing into the surface.
+    _depth_stencil: Renderbuffer,
+}
+
+impl Surface {
+    pub fn new(width: u32, height: u32) -> Result<Surface, String> {
+        let mut texture = Texture::new::<&[u8]>(width, height, InternalPixelFormat::RGBA, PixelDataFormat::RGBA, PixelDataType::UnsignedByte, None);
+        // everything a context draws has premultiplied alpha.
+        texture.set_premultiplied(true);
+        let previous_framebuffer = Framebuffer::current_binding();
+        let framebuffer = Framebuffer::new();
+        framebuffer.attach_color_texture(&texture);
+        let depth_stencil = Renderbuffer::depth_stencil(width, height);
+        framebuffer.attach_depth_stencil(&depth_stencil);
+        let status = framebuffer.check_status();
+        Framebuffer::bind_handle(previous_framebuffer);
+        status?;
+
+        framebuffer.set_label("canvas.surface");
+        texture.set_label("canvas.surface.color");
+        depth_stencil.set_label("canvas.surface.depth_stencil");
+        Ok(Surface { framebuffer, texture, _depth_stencil: depth_stencil })
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Regenerates the mipmaps of the surface's texture from what has been drawn into it so
+    /// far. Call this after `Context::end_target` if the surface is drawn downscaled.
+    pub fn generate_mipmaps(&mut self) {
+        self.texture.generate_mipmaps();
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.get_width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.get_height()
+    }
+
+    /// Reads back RGBA8 pixels from the surface, top row first. The region is in pixels and
+    /// is clamped to the surface. Anything a `Context` is still batching for this surface must
+    /// have been flushed first; `Context::read_pixels` does that when the surface is the current
+    /// target. Colors drawn by a `Context` have premultiplied alpha.
+    pub fn read_pixels(&self, x: u32, y: u32, width: u32, height: u32) -> Image {
+        let width = width.min(self.width().saturating_sub(x));
+        let height = height.min(self.height().saturating_sub(y));
+
+        let previous_framebuffer = Framebuffer::current_binding();
+        self.framebuffer.bind();
+        // surfaces are drawn upside down so their rows are already in top to bottom order.
+        let pixels = read_pixels_rgba(x as _, y as _, width, height);
+        Framebuffer::bind_handle(previous_framebuffer);
+        Image { width, height, pixels }
+    }
+}
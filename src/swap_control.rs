@@ -0,0 +1,157 @@
+use std::ffi::CStr;
+use std::os::raw::{ c_char, c_void };
+use glutin::{
+    ContextTrait,
+    WindowedContext,
+};
+
+/// Sets the swap interval of `context`, which must be current, through the platform's swap
+/// control extension: WGL_EXT_swap_control on Windows, GLX_EXT_swap_control or
+/// GLX_MESA_swap_control with GLX and `eglSwapInterval` with EGL, e.g. on Wayland. Returns
+/// false if the platform doesn't have one, in which case the interval can only be chosen when
+/// the context is created.
+pub(crate) fn set_interval(context: &WindowedContext, interval: i32) -> bool {
+    unsafe { platform::set_interval(context, interval) }
+}
+
+/// Resolves a function through the context, None if the driver doesn't have it. `F` must be
+/// the function's pointer type.
+unsafe fn proc_address<F: Copy>(context: &WindowedContext, name: &str) -> Option<F> {
+    debug_assert_eq!(std::mem::size_of::<F>(), std::mem::size_of::<*const c_void>());
+    let address = context.get_proc_address(name) as *const c_void;
+    if address.is_null() {
+        None
+    } else {
+        Some(std::mem::transmute_copy(&address))
+    }
+}
+
+/// Whether a space separated extension string lists `name`. Drivers may export the functions
+/// of extensions they don't support, so they're only resolved after checking this.
+unsafe fn has_extension(extensions: *const c_char, name: &str) -> bool {
+    !extensions.is_null() && CStr::from_ptr(extensions).to_string_lossy().split_whitespace().any(|ext| ext == name)
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+mod platform {
+    use std::os::raw::{ c_char, c_int, c_uint, c_ulong, c_void };
+    use glutin::WindowedContext;
+    use glutin::os::ContextTraitExt;
+    use glutin::os::unix::RawHandle;
+    use super::{ has_extension, proc_address };
+
+    const GLX_SCREEN: c_int = 0x800C;
+
+    pub unsafe fn set_interval(context: &WindowedContext, interval: i32) -> bool {
+        match context.context().raw_handle() {
+            RawHandle::Glx(_) => set_glx_interval(context, interval),
+            RawHandle::Egl(_) => set_egl_interval(context, interval),
+        }
+    }
+
+    unsafe fn set_glx_interval(context: &WindowedContext, interval: i32) -> bool {
+        type GetCurrentDisplay = extern "C" fn() -> *mut c_void;
+        type GetCurrentContext = extern "C" fn() -> *mut c_void;
+        type GetCurrentDrawable = extern "C" fn() -> c_ulong;
+        type QueryContext = extern "C" fn(*mut c_void, *mut c_void, c_int, *mut c_int) -> c_int;
+        type QueryExtensionsString = extern "C" fn(*mut c_void, c_int) -> *const c_char;
+        type SwapIntervalExt = extern "C" fn(*mut c_void, c_ulong, c_int);
+        type SwapIntervalMesa = extern "C" fn(c_uint) -> c_int;
+
+        let (get_display, get_context, get_drawable, query_context, query_extensions) = match (
+            proc_address::<GetCurrentDisplay>(context, "glXGetCurrentDisplay"),
+            proc_address::<GetCurrentContext>(context, "glXGetCurrentContext"),
+            proc_address::<GetCurrentDrawable>(context, "glXGetCurrentDrawable"),
+            proc_address::<QueryContext>(context, "glXQueryContext"),
+            proc_address::<QueryExtensionsString>(context, "glXQueryExtensionsString"),
+        ) {
+            (Some(a), Some(b), Some(c), Some(d), Some(e)) => (a, b, c, d, e),
+            _ => return false,
+        };
+
+        let display = get_display();
+        let mut screen = 0;
+        if display.is_null() || query_context(display, get_context(), GLX_SCREEN, &mut screen) != 0 {
+            return false;
+        }
+        let extensions = query_extensions(display, screen);
+
+        if has_extension(extensions, "GLX_EXT_swap_control") {
+            if let Some(swap_interval) = proc_address::<SwapIntervalExt>(context, "glXSwapIntervalEXT") {
+                swap_interval(display, get_drawable(), interval);
+                return true;
+            }
+        }
+        if has_extension(extensions, "GLX_MESA_swap_control") {
+            if let Some(swap_interval) = proc_address::<SwapIntervalMesa>(context, "glXSwapIntervalMESA") {
+                return swap_interval(interval as c_uint) == 0;
+            }
+        }
+        false
+    }
+
+    unsafe fn set_egl_interval(context: &WindowedContext, interval: i32) -> bool {
+        type SwapInterval = extern "C" fn(*const c_void, c_int) -> c_uint;
+
+        let display = match context.context().get_egl_display() {
+            Some(display) => display,
+            None => return false,
+        };
+        match proc_address::<SwapInterval>(context, "eglSwapInterval") {
+            Some(swap_interval) => swap_interval(display, interval) != 0,
+            None => false,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::os::raw::{ c_char, c_int, c_void };
+    use glutin::WindowedContext;
+    use super::{ has_extension, proc_address };
+
+    pub unsafe fn set_interval(context: &WindowedContext, interval: i32) -> bool {
+        type GetExtensionsStringExt = extern "system" fn() -> *const c_char;
+        type GetExtensionsStringArb = extern "system" fn(*mut c_void) -> *const c_char;
+        type GetCurrentDc = extern "system" fn() -> *mut c_void;
+        type SwapInterval = extern "system" fn(c_int) -> c_int;
+
+        let extensions = match (
+            proc_address::<GetExtensionsStringArb>(context, "wglGetExtensionsStringARB"),
+            proc_address::<GetCurrentDc>(context, "wglGetCurrentDC"),
+            proc_address::<GetExtensionsStringExt>(context, "wglGetExtensionsStringEXT"),
+        ) {
+            (Some(get_extensions), Some(get_dc), _) => get_extensions(get_dc()),
+            (_, _, Some(get_extensions)) => get_extensions(),
+            _ => return false,
+        };
+        if !has_extension(extensions, "WGL_EXT_swap_control") { return false }
+
+        match proc_address::<SwapInterval>(context, "wglSwapIntervalEXT") {
+            Some(swap_interval) => swap_interval(interval) != 0,
+            None => false,
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "windows"
+)))]
+mod platform {
+    use glutin::WindowedContext;
+
+    pub unsafe fn set_interval(_context: &WindowedContext, _interval: i32) -> bool {
+        false
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::mpsc::{ self, Sender, RecvTimeoutError };
+use std::time::{ Duration, Instant };
+
+/// The shortest interval of a repeating timer. Shorter ones are clamped to it so that a zero
+/// interval can't keep the run loop from ever waiting.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+struct Timer {
+    id:         TimerId,
+    deadline:   Instant,
+    /// Timers with an interval are rescheduled after they fire instead of being removed.
+    interval:   Option<Duration>,
+    callback:   Box<dyn FnMut()>,
+}
+
+/// A set of one-shot and repeating callbacks that are fired by the owner's run loop.
+pub struct Timers {
+    next_id: u64,
+    timers: Vec<Timer>,
+}
+
+impl Timers {
+    pub fn new() -> Timers {
+        Timers {
+            next_id: 0,
+            timers: Vec::new(),
+        }
+    }
+
+    /// Schedules `callback` to be called once after `delay` has passed.
+    pub fn after<F: FnMut() + 'static>(&mut self, delay: Duration, callback: F) -> TimerId {
+        self.schedule(delay, None, Box::new(callback))
+    }
+
+    /// Schedules `callback` to be called every `interval` until the timer is cancelled.
+    /// Intervals shorter than `MIN_INTERVAL`, including zero, are clamped to it.
+    pub fn every<F: FnMut() + 'static>(&mut self, interval: Duration, callback: F) -> TimerId {
+        let interval = interval.max(MIN_INTERVAL);
+        self.schedule(interval, Some(interval), Box::new(callback))
+    }
+
+    /// Returns true if the timer was still scheduled.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let len = self.timers.len();
+        self.timers.retain(|t| t.id != id);
+        self.timers.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// The earliest instant at which a timer is due.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers.iter().map(|t| t.deadline).min()
+    }
+
+    /// Calls every timer that is due at `now` and returns how many were called. A repeating
+    /// timer is called at most once per call to this even if several intervals have passed.
+    pub fn fire_due(&mut self, now: Instant) -> usize {
+        let mut fired = 0;
+        let mut idx = 0;
+        while idx < self.timers.len() {
+            if self.timers[idx].deadline > now {
+                idx += 1;
+                continue;
+            }
+
+            fired += 1;
+            (self.timers[idx].callback)();
+
+            if let Some(interval) = self.timers[idx].interval {
+                let timer = &mut self.timers[idx];
+                // skip missed intervals rather than calling the timer repeatedly to catch up.
+                while timer.deadline <= now {
+                    timer.deadline += interval;
+                }
+                idx += 1;
+            } else {
+                self.timers.remove(idx);
+            }
+        }
+        fired
+    }
+
+    fn schedule(&mut self, delay: Duration, interval: Option<Duration>, callback: Box<dyn FnMut()>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id,
+            deadline: Instant::now() + delay,
+            interval,
+            callback,
+        });
+        id
+    }
+}
+
+impl Default for Timers {
+    fn default() -> Timers {
+        Timers::new()
+    }
+}
+
+/// Wakes a blocked events loop when a deadline passes.
+pub(crate) struct DeadlineWaker {
+    sender: Sender<Option<Instant>>,
+}
+
+impl DeadlineWaker {
+    pub fn new(proxy: glutin::EventsLoopProxy) -> DeadlineWaker {
+        let (sender, receiver) = mpsc::channel::<Option<Instant>>();
+
+        std::thread::spawn(move || {
+            let mut deadline: Option<Instant> = None;
+            loop {
+                let message = match deadline {
+                    Some(d) => receiver.recv_timeout(d.saturating_duration_since(Instant::now())),
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+
+                match message {
+                    Ok(new_deadline) => deadline = new_deadline,
+                    Err(RecvTimeoutError::Timeout) => {
+                        deadline = None;
+                        if proxy.wakeup().is_err() { break }
+                    },
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        DeadlineWaker { sender }
+    }
+
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        let _ = self.sender.send(deadline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn counter() -> (Rc<Cell<u32>>, impl FnMut() + 'static) {
+        let count = Rc::new(Cell::new(0));
+        let counted = count.clone();
+        (count, move || counted.set(counted.get() + 1))
+    }
+
+    #[test]
+    fn one_shot_timers_fire_once() {
+        let mut timers = Timers::new();
+        let (count, callback) = counter();
+        timers.after(Duration::from_millis(10), callback);
+
+        let start = Instant::now();
+        assert_eq!(timers.fire_due(start), 0);
+        assert_eq!(timers.fire_due(start + Duration::from_millis(20)), 1);
+        assert_eq!(timers.fire_due(start + Duration::from_millis(40)), 0);
+        assert_eq!(count.get(), 1);
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn repeating_timers_skip_missed_intervals() {
+        let mut timers = Timers::new();
+        let (count, callback) = counter();
+        let id = timers.every(Duration::from_millis(10), callback);
+
+        let start = Instant::now();
+        assert_eq!(timers.fire_due(start + Duration::from_millis(15)), 1);
+        // several intervals passed, but the timer is only called once.
+        assert_eq!(timers.fire_due(start + Duration::from_millis(100)), 1);
+        assert!(timers.next_deadline().unwrap() > start + Duration::from_millis(100));
+        assert_eq!(count.get(), 2);
+
+        assert!(timers.cancel(id));
+        assert!(!timers.cancel(id));
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn zero_intervals_are_clamped() {
+        let mut timers = Timers::new();
+        let (count, callback) = counter();
+        timers.every(Duration::from_secs(0), callback);
+
+        let now = Instant::now() + MIN_INTERVAL;
+        assert_eq!(timers.fire_due(now), 1);
+        assert!(!timers.is_empty(), "a zero interval must not turn the timer into a one-shot one");
+        assert!(timers.next_deadline().unwrap() > now);
+        assert_eq!(timers.fire_due(now), 0);
+        assert_eq!(count.get(), 1);
+    }
+}
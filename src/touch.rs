@@ -0,0 +1,209 @@
+use glutin::{
+    TouchPhase,
+    WindowEvent,
+};
+
+/// A finger on a touchscreen or trackpad that reports touches. Positions are in logical pixels
+/// relative to the top left corner of the window's client area.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Touch {
+    /// Stays the same from when the finger touches until it's lifted. Ids are reused after
+    /// that.
+    pub id:         u64,
+    pub position:   (f32, f32),
+    /// Where the touch started.
+    pub start:      (f32, f32),
+    /// How far the touch moved this frame.
+    pub delta:      (f32, f32),
+}
+
+/// The touches on a window, as of the last call to `Window::handle_events` or
+/// `Window::wait_events`. "This frame" means since the call before that, like for `Mouse`.
+/// Only platforms that report touches have any; desktop platforms mostly turn them into mouse
+/// events instead.
+#[derive(Clone, Debug)]
+pub struct Touches {
+    active:     Vec<Touch>,
+    /// Ids of the active touches that started this frame.
+    started:    Vec<u64>,
+    ended:      Vec<Touch>,
+    /// Whether the touches that ended this frame were cancelled, one per touch in `ended`.
+    cancelled:  Vec<bool>,
+}
+
+impl Touches {
+    pub(crate) fn new() -> Touches {
+        Touches {
+            active:     Vec::new(),
+            started:    Vec::new(),
+            ended:      Vec::new(),
+            cancelled:  Vec::new(),
+        }
+    }
+
+    /// Forgets what happened during the previous frame.
+    pub(crate) fn begin_frame(&mut self) {
+        for touch in self.active.iter_mut() {
+            touch.delta = (0.0, 0.0);
+        }
+        self.started.clear();
+        self.ended.clear();
+        self.cancelled.clear();
+    }
+
+    pub(crate) fn handle_event(&mut self, event: &WindowEvent) {
+        let touch = match *event {
+            WindowEvent::Touch(touch) => touch,
+            _ => return,
+        };
+        let position = (touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.active.retain(|t| t.id != touch.id);
+                self.active.push(Touch { id: touch.id, position, start: position, delta: (0.0, 0.0) });
+                self.started.push(touch.id);
+            },
+            TouchPhase::Moved => {
+                if let Some(active) = self.active.iter_mut().find(|t| t.id == touch.id) {
+                    active.delta.0 += position.0 - active.position.0;
+                    active.delta.1 += position.1 - active.position.1;
+                    active.position = position;
+                }
+            },
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(index) = self.active.iter().position(|t| t.id == touch.id) {
+                    let mut ended = self.active.remove(index);
+                    ended.delta.0 += position.0 - ended.position.0;
+                    ended.delta.1 += position.1 - ended.position.1;
+                    ended.position = position;
+                    self.ended.push(ended);
+                    self.cancelled.push(touch.phase == TouchPhase::Cancelled);
+                    self.started.retain(|&id| id != touch.id);
+                }
+            },
+        }
+    }
+
+    /// The touches that are down, in the order they started.
+    pub fn iter(&self) -> impl Iterator<Item = &Touch> {
+        self.active.iter()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Touch> {
+        self.active.iter().find(|t| t.id == id)
+    }
+
+    pub fn count(&self) -> usize {
+        self.active.len()
+    }
+
+    /// The touches that started this frame and are still down.
+    pub fn started(&self) -> impl Iterator<Item = &Touch> {
+        self.active.iter().filter(move |t| self.started.contains(&t.id))
+    }
+
+    /// The touches that were lifted or cancelled this frame, with their last position.
+    pub fn ended(&self) -> &[Touch] {
+        &self.ended
+    }
+
+    /// Whether a touch that ended this frame was cancelled by the system rather than lifted,
+    /// in which case it shouldn't count as a tap.
+    pub fn was_cancelled(&self, id: u64) -> bool {
+        self.ended.iter().zip(&self.cancelled).any(|(t, &cancelled)| t.id == id && cancelled)
+    }
+
+    /// The touches that were already down in the previous frame, which are the ones that
+    /// panning and zooming follow.
+    fn moving(&self) -> impl Iterator<Item = &Touch> {
+        self.active.iter().filter(move |t| !self.started.contains(&t.id))
+    }
+
+    /// How far the center of the touches moved this frame, for panning with any number of
+    /// fingers.
+    pub fn pan(&self) -> (f32, f32) {
+        let count = self.moving().count();
+        if count == 0 { return (0.0, 0.0) }
+        let sum = self.moving().fold((0.0, 0.0), |sum, t| (sum.0 + t.delta.0, sum.1 + t.delta.1));
+        (sum.0 / count as f32, sum.1 / count as f32)
+    }
+
+    /// How much the touches spread apart this frame as a factor, for pinch zooming. 1.0 with
+    /// fewer than two touches.
+    pub fn zoom(&self) -> f32 {
+        let before = self.spread(|t| (t.position.0 - t.delta.0, t.position.1 - t.delta.1));
+        let after = self.spread(|t| t.position);
+        if before > 0.0 { after / before } else { 1.0 }
+    }
+
+    /// The average distance of the touches from their center, 0.0 with fewer than two.
+    fn spread(&self, position: impl Fn(&Touch) -> (f32, f32)) -> f32 {
+        let count = self.moving().count();
+        if count < 2 { return 0.0 }
+        let center = self.moving().fold((0.0, 0.0), |sum, t| {
+            let p = position(t);
+            (sum.0 + p.0 / count as f32, sum.1 + p.1 / count as f32)
+        });
+        self.moving().map(|t| {
+            let p = position(t);
+            ((p.0 - center.0).powi(2) + (p.1 - center.1).powi(2)).sqrt()
+        }).sum::<f32>() / count as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glutin::dpi::LogicalPosition;
+
+    fn touch(touches: &mut Touches, id: u64, phase: TouchPhase, x: f64, y: f64) {
+        touches.handle_event(&WindowEvent::Touch(glutin::Touch {
+            // never compared, touches are told apart by their id.
+            device_id: unsafe { glutin::DeviceId::dummy() },
+            phase,
+            location: LogicalPosition::new(x, y),
+            id,
+        }));
+    }
+
+    #[test]
+    fn pan_follows_the_center_of_moving_touches() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 1, TouchPhase::Started, 0.0, 0.0);
+        touch(&mut touches, 2, TouchPhase::Started, 10.0, 0.0);
+        // touches that just started don't pan yet.
+        assert_eq!(touches.pan(), (0.0, 0.0));
+
+        touches.begin_frame();
+        touch(&mut touches, 1, TouchPhase::Moved, 3.0, 4.0);
+        touch(&mut touches, 2, TouchPhase::Moved, 13.0, 4.0);
+        assert_eq!(touches.pan(), (3.0, 4.0));
+        assert_eq!(touches.zoom(), 1.0);
+    }
+
+    #[test]
+    fn zoom_is_the_change_in_spread() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 1, TouchPhase::Started, 40.0, 50.0);
+        touch(&mut touches, 2, TouchPhase::Started, 60.0, 50.0);
+        touches.begin_frame();
+        touch(&mut touches, 1, TouchPhase::Moved, 30.0, 50.0);
+        touch(&mut touches, 2, TouchPhase::Moved, 70.0, 50.0);
+        assert!((touches.zoom() - 2.0).abs() < 1e-6);
+        assert_eq!(touches.pan(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn zoom_needs_two_touches() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 1, TouchPhase::Started, 0.0, 0.0);
+        touches.begin_frame();
+        touch(&mut touches, 1, TouchPhase::Moved, 5.0, 0.0);
+        assert_eq!(touches.zoom(), 1.0);
+        assert_eq!(touches.pan(), (5.0, 0.0));
+
+        touch(&mut touches, 1, TouchPhase::Cancelled, 5.0, 0.0);
+        assert_eq!(touches.count(), 0);
+        assert!(touches.was_cancelled(1));
+    }
+}
@@ -0,0 +1,264 @@
+use super::math::Mat4f;
+use super::program_cache::build_program_with_outputs;
+use super::opengl::{
+    Buffer,
+    BufferType,
+    BufferDataType,
+    VertexArray,
+    ShaderError,
+    Program,
+};
+use gl::types::{ GLenum, GLint };
+
+/// The type of each component of a vertex attribute.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AttribType {
+    Float,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl AttribType {
+    fn as_gl(self) -> GLenum {
+        match self {
+            AttribType::Float => gl::FLOAT,
+            AttribType::Byte => gl::BYTE,
+            AttribType::UnsignedByte => gl::UNSIGNED_BYTE,
+            AttribType::Short => gl::SHORT,
+            AttribType::UnsignedShort => gl::UNSIGNED_SHORT,
+            AttribType::Int => gl::INT,
+            AttribType::UnsignedInt => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// How the components of an attribute reach the shader.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AttribConversion {
+    /// Converted to floats as they are.
+    Float,
+    /// Integers are mapped to `0.0..=1.0` (or `-1.0..=1.0` if signed), e.g. for 8-bit colors.
+    Normalized,
+    /// Kept as integers, for `int`/`uint` shader inputs like flags.
+    Integer,
+}
+
+/// One attribute of a vertex format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexAttrib {
+    /// The name of the shader input.
+    pub name:       String,
+    pub ty:         AttribType,
+    /// The number of components, from 1 to 4.
+    pub count:      i32,
+    /// Offset in bytes from the start of the vertex.
+    pub offset:     usize,
+    pub conversion: AttribConversion,
+}
+
+/// Describes where each shader input is found in a vertex. Built with `VertexLayout::new` and
+/// one call per attribute:
+///
+/// ```ignore
+/// VertexLayout::new(std::mem::size_of::<MyVert>())
+///     .float("Position", 2, 0)
+///     .float("UV", 2, 8)
+///     .integer("Mode", AttribType::UnsignedInt, 1, 16)
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexLayout {
+    /// The size of a vertex in bytes.
+    pub stride:     usize,
+    pub attribs:    Vec<VertexAttrib>,
+}
+
+impl VertexLayout {
+    pub fn new(stride: usize) -> VertexLayout {
+        VertexLayout { stride, attribs: Vec::new() }
+    }
+
+    pub fn attrib(mut self, name: &str, ty: AttribType, count: i32, offset: usize, conversion: AttribConversion) -> VertexLayout {
+        debug_assert!((1..=4).contains(&count), "vertex attributes have 1 to 4 components");
+        self.attribs.push(VertexAttrib { name: name.to_string(), ty, count, offset, conversion });
+        self
+    }
+
+    /// Adds `count` floats.
+    pub fn float(self, name: &str, count: i32, offset: usize) -> VertexLayout {
+        self.attrib(name, AttribType::Float, count, offset, AttribConversion::Float)
+    }
+
+    /// Adds `count` integers that the shader sees as normalized floats.
+    pub fn normalized(self, name: &str, ty: AttribType, count: i32, offset: usize) -> VertexLayout {
+        self.attrib(name, ty, count, offset, AttribConversion::Normalized)
+    }
+
+    /// Adds `count` integers that the shader sees as integers.
+    pub fn integer(self, name: &str, ty: AttribType, count: i32, offset: usize) -> VertexLayout {
+        self.attrib(name, ty, count, offset, AttribConversion::Integer)
+    }
+
+    /// Points the attributes of `program` at the vertices in `vertex_buffer` and records that
+    /// in `vertex_array`, which is left bound. Attributes the program doesn't use are skipped
+    /// since the shader compiler may have optimized them out.
+    pub fn attach(&self, program: &Program, vertex_array: &VertexArray, vertex_buffer: &Buffer) {
+        vertex_array.bind();
+        let _buffer = vertex_buffer.bind_scoped();
+
+        for attrib in self.attribs.iter() {
+            let location = program.attrib_location(&attrib.name);
+            if location < 0 { continue }
+            let (location, ty, stride, offset) = (location as _, attrib.ty.as_gl(), self.stride as GLint, attrib.offset as *const _);
+            unsafe {
+                gl::EnableVertexAttribArray(location);
+                match attrib.conversion {
+                    AttribConversion::Float => gl::VertexAttribPointer(location, attrib.count, ty, gl::FALSE, stride, offset),
+                    AttribConversion::Normalized => gl::VertexAttribPointer(location, attrib.count, ty, gl::TRUE, stride, offset),
+                    AttribConversion::Integer => gl::VertexAttribIPointer(location, attrib.count, ty, stride, offset),
+                }
+            }
+        }
+    }
+}
+
+/// A vertex type with a known layout, which can be batched by a `CustomPipeline`. The type
+/// should be `#[repr(C)]` so that the offsets in its layout are stable.
+pub trait VertexFormat: BufferDataType + Copy {
+    fn layout() -> VertexLayout;
+}
+
+/// A user shader along with a batch of vertices of a custom format that it draws. Vertices
+/// are collected with `push_verts` and drawn with `Context::draw_custom`, which applies the
+/// context's transform, clips, stencil masks and blend mode.
+///
+/// If the vertex shader has `mat4` uniforms called `ProjMtx` and `Transform` they are set like
+/// they are for the built-in shaders, so `ProjMtx * Transform * vec4(pos, 0.0, 1.0)` gives
+/// the position on the display. Fragments should be output with premultiplied alpha.
+///
+/// A pipeline created with `with_outputs` can draw into several color attachments of a
+/// `Framebuffer` at once, e.g. the image and an object ID buffer for picking.
+pub struct CustomPipeline<V: VertexFormat> {
+    program:            Program,
+    /// The fragment shader outputs, in the order of the color attachments they're written to.
+    outputs:            Vec<String>,
+    /// What the GL objects are labeled as, so a reloaded program gets the same label.
+    label:              String,
+    vertex_array:       VertexArray,
+    vertex_buffer:      Buffer,
+    elems_buffer:       Buffer,
+    uniform_projmtx:    GLint,
+    uniform_transform:  GLint,
+    vertices:           Vec<V>,
+    elements:           Vec<u32>,
+}
+
+impl<V: VertexFormat> CustomPipeline<V> {
+    /// Compiles the shaders, or loads the program from the `program_cache` if it's enabled.
+    pub fn new(vertex_source: &str, fragment_source: &str) -> Result<CustomPipeline<V>, ShaderError> {
+        CustomPipeline::with_outputs(vertex_source, fragment_source, &[])
+    }
+
+    /// Like `new`, for a fragment shader whose `out` variables named in `outputs` are written
+    /// to the color attachments with the same index.
+    pub fn with_outputs(vertex_source: &str, fragment_source: &str, outputs: &[&str]) -> Result<CustomPipeline<V>, ShaderError> {
+        let program = build_program_with_outputs(vertex_source, fragment_source, outputs)?;
+
+        let vertex_buffer = Buffer::new(BufferType::ArrayBuffer);
+        let elems_buffer = Buffer::new(BufferType::ElementArrayBuffer);
+        let vertex_array = VertexArray::new();
+        V::layout().attach(&program, &vertex_array, &vertex_buffer);
+        vertex_array.set_element_buffer(&elems_buffer);
+
+        let mut pipeline = CustomPipeline {
+            uniform_projmtx: program.uniform_location("ProjMtx\0"),
+            uniform_transform: program.uniform_location("Transform\0"),
+            program,
+            outputs: outputs.iter().map(|name| name.to_string()).collect(),
+            label: String::new(),
+            vertex_array,
+            vertex_buffer,
+            elems_buffer,
+            vertices: Vec::new(),
+            elements: Vec::new(),
+        };
+        pipeline.set_label("canvas.custom_pipeline");
+        Ok(pipeline)
+    }
+
+    /// Labels the program as `name` and its vertex array and buffers with `name` followed by
+    /// `.vertex_array`, `.vertices` and `.elements` for GL debuggers.
+    pub fn set_label(&mut self, name: &str) {
+        self.label = name.to_string();
+        self.program.set_label(name);
+        self.vertex_array.set_label(&format!("{}.vertex_array", name));
+        self.vertex_buffer.set_label(&format!("{}.vertices", name));
+        self.elems_buffer.set_label(&format!("{}.elements", name));
+    }
+
+    /// Recompiles the pipeline from new sources, e.g. after a `hot_reload::FileWatcher` saw
+    /// their files change. Pending vertices are kept. If the shaders don't compile the error is
+    /// returned and the old program stays in use. Uniforms have to be set again afterwards.
+    pub fn reload(&mut self, vertex_source: &str, fragment_source: &str) -> Result<(), ShaderError> {
+        let outputs: Vec<&str> = self.outputs.iter().map(|name| name.as_str()).collect();
+        let program = build_program_with_outputs(vertex_source, fragment_source, &outputs)?;
+
+        let vertex_array = VertexArray::new();
+        V::layout().attach(&program, &vertex_array, &self.vertex_buffer);
+        vertex_array.set_element_buffer(&self.elems_buffer);
+
+        self.uniform_projmtx = program.uniform_location("ProjMtx\0");
+        self.uniform_transform = program.uniform_location("Transform\0");
+        self.program = program;
+        self.vertex_array = vertex_array;
+        let label = std::mem::take(&mut self.label);
+        self.set_label(&label);
+        Ok(())
+    }
+
+    /// The linked program, for setting the shader's own uniforms with `Program::set_uniform`.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn push_verts(&mut self, verts: &[V], elems: &[u32]) {
+        debug_assert!(elems.len() % 3 == 0, "number of elements must be a multiple of 3");
+        let elem_delta = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(verts);
+        self.elements.extend(elems.iter().map(|e| {
+            debug_assert!(*e < verts.len() as u32, "element is larger than the number of vertices provided");
+            e + elem_delta
+        }));
+    }
+
+    pub fn has_vertices(&self) -> bool {
+        !self.vertices.is_empty()
+    }
+
+    pub(crate) fn pending(&self) -> (usize, usize) {
+        (self.vertices.len(), self.elements.len())
+    }
+
+    /// Draws the pending vertices and clears them. The rest of the GL state has to be set up
+    /// already.
+    pub(crate) fn submit(&mut self, projection: &Mat4f, transform: &Mat4f) {
+        self.program.bind();
+        self.program.set_uniform(self.uniform_projmtx, projection);
+        self.program.set_uniform(self.uniform_transform, transform);
+
+        self.vertex_array.bind();
+        self.vertex_buffer.bind();
+        self.elems_buffer.bind();
+        self.vertex_buffer.stream_data(&self.vertices);
+        self.elems_buffer.stream_data(&self.elements);
+        unsafe {
+            gl::DrawElements(gl::TRIANGLES, self.elements.len() as _, gl::UNSIGNED_INT, std::ptr::null());
+        }
+
+        self.vertices.clear();
+        self.elements.clear();
+    }
+}
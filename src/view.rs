@@ -0,0 +1,23 @@
+use super::math::{ Mat4f, Rect, transform };
+
+/// A region of the display that `Context::draw_views` draws the scene into, seen through its
+/// own camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct View {
+    /// The region of the display in display coordinates.
+    pub viewport: Rect,
+    /// Applied to the scene after the context's transform. Maps the scene into the viewport
+    /// where `(0, 0)` is the viewport's top left corner.
+    pub camera: Mat4f,
+}
+
+impl View {
+    pub fn new(viewport: Rect, camera: Mat4f) -> View {
+        View { viewport, camera }
+    }
+
+    /// A view of the scene without any camera transform.
+    pub fn with_viewport(viewport: Rect) -> View {
+        View::new(viewport, transform::identity())
+    }
+}
@@ -3,84 +3,1289 @@ use glutin::{
     WindowedContext,
     ContextTrait,
 };
+use std::cell::{ Cell, RefCell };
+use std::rc::Rc;
+use std::path::PathBuf;
+use std::time::{ Duration, Instant };
+use super::timer::{ Timers, TimerId, DeadlineWaker };
+use super::mouse::Mouse;
+#[cfg(feature = "gamepad")]
+use super::gamepad::Gamepads;
+use super::touch::Touches;
+use super::frame_clock::{ FrameClock, FixedTimestep };
+use super::event::Event;
+use super::context::Context;
+use super::clipboard::Clipboard;
+use super::swap_control;
+use super::opengl::{ GlInfo, ContextId };
+use super::gl_debug::{ self, DebugSeverity };
+
+/// How long the window has to stay at a size before it's snapped to its aspect ratio and
+/// integer scale, so that it isn't snapped in the middle of the user dragging its border.
+const SNAP_DELAY: Duration = Duration::from_millis(200);
+
+/// What the window's GL context ended up being created with, for diagnostics screens and bug
+/// reports. Displaying it gives a readable summary.
+#[derive(Clone, Debug)]
+pub struct DisplayInfo {
+    pub gl: GlInfo,
+    pub api: glutin::Api,
+    pub pixel_format: glutin::PixelFormat,
+    /// Whether vsync was requested, at creation or with `Window::set_vsync`. There's no way to
+    /// ask the driver whether it is honored.
+    pub vsync: bool,
+}
+
+impl std::fmt::Display for DisplayInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let pf = &self.pixel_format;
+        writeln!(f, "{}", self.gl)?;
+        writeln!(f, "API:          {:?}", self.api)?;
+        writeln!(f, "Color bits:   {} (alpha {})", pf.color_bits, pf.alpha_bits)?;
+        writeln!(f, "Depth bits:   {}", pf.depth_bits)?;
+        writeln!(f, "Stencil bits: {}", pf.stencil_bits)?;
+        writeln!(f, "Samples:      {}", pf.multisampling.unwrap_or(0))?;
+        writeln!(f, "sRGB:         {}", pf.srgb)?;
+        writeln!(f, "Double buffered: {}, hardware accelerated: {}", pf.double_buffer, pf.hardware_accelerated)?;
+        write!(f, "Vsync:        {}", if self.vsync { "requested" } else { "off" })
+    }
+}
+
+/// The events loop that a window and the windows built alongside it receive their events from.
+struct SharedEvents {
+    /// Declared before the loop, since on Wayland it uses the loop's connection.
+    clipboard: Clipboard,
+    events_loop: RefCell<EventsLoop>,
+    /// Events for other windows that arrived while one window was handling its events, until
+    /// those windows handle theirs.
+    pending: RefCell<Vec<(glutin::WindowId, glutin::WindowEvent)>>,
+    /// Raw mouse motion that each window hasn't handled yet. Device events aren't for any one
+    /// window, so every window gets all of them.
+    raw_motion: RefCell<Vec<(glutin::WindowId, (f64, f64))>>,
+}
+
+impl SharedEvents {
+    fn add_raw_motion(&self, dx: f64, dy: f64) {
+        for (_, delta) in self.raw_motion.borrow_mut().iter_mut() {
+            delta.0 += dx;
+            delta.1 += dy;
+        }
+    }
+
+    /// Starts collecting raw motion for a window, or stops if `old_id` is given and `id` isn't.
+    fn track_raw_motion(&self, old_id: Option<glutin::WindowId>, id: Option<glutin::WindowId>) {
+        let mut raw_motion = self.raw_motion.borrow_mut();
+        if let Some(old_id) = old_id {
+            raw_motion.retain(|&(window_id, _)| window_id != old_id);
+        }
+        if let Some(id) = id {
+            raw_motion.push((id, (0.0, 0.0)));
+        }
+    }
+
+    /// The raw motion since the window last took it.
+    fn take_raw_motion(&self, id: glutin::WindowId) -> (f64, f64) {
+        self.raw_motion.borrow_mut().iter_mut()
+            .find(|(window_id, _)| *window_id == id)
+            .map(|(_, delta)| std::mem::replace(delta, (0.0, 0.0)))
+            .unwrap_or((0.0, 0.0))
+    }
+}
 
 pub struct Window {
-    events_loop: Option<EventsLoop>,
+    events: Rc<SharedEvents>,
     win_context: WindowedContext,
     is_running: bool,
 
+    /// In physical pixels.
     win_size: (f32, f32),
+    dpi_factor: f32,
+    scale_policy: ScalePolicy,
+    /// The new factor if it changed during the last call to `handle_events`.
+    dpi_changed: Option<f32>,
+
+    /// Keyboard input received during the last call to `handle_events`.
+    keyboard_input: Vec<glutin::KeyboardInput>,
+    /// Text typed during the last call to `handle_events`, without control characters.
+    text_input: String,
+    mouse: Mouse,
+    #[cfg(feature = "gamepad")]
+    gamepads: Gamepads,
+    touches: Touches,
+    /// Every event received during the last call to `handle_events`.
+    received: Vec<Event>,
+    /// The new size if the window was resized during the last call to `handle_events`.
+    resized: Option<(f32, f32)>,
+    cursor_visible: bool,
+    cursor_grabbed: bool,
+    /// Files dropped on the window during the last call to `handle_events`.
+    dropped_files: Vec<PathBuf>,
+    /// Files being dragged over the window, which are reported one event per file.
+    hovered_files: Vec<PathBuf>,
+
+    /// In a cell so that `flip` can tick it through a shared reference.
+    frame_clock: Cell<FrameClock>,
+
+    timers: Timers,
+    /// Created the first time `wait_events` has to wait for a timer.
+    deadline_waker: Option<DeadlineWaker>,
+
+    display_info: DisplayInfo,
+
+    /// Unregistered on drop, before the GL context is destroyed.
+    context_id: ContextId,
+
+    fullscreen: FullscreenMode,
+    /// Where the window was and how large it was before it left `FullscreenMode::Windowed`.
+    windowed_rect: Option<(glutin::dpi::LogicalPosition, glutin::dpi::LogicalSize)>,
+    /// Width divided by height, which resizing snaps to since window systems can't enforce it.
+    aspect_ratio: Option<f64>,
+    /// Base size in physical pixels that the client area is kept at a multiple of.
+    integer_scale: Option<(f64, f64)>,
+    /// When the window is snapped to `aspect_ratio` and `integer_scale`, once the user has
+    /// stopped resizing it.
+    snap_at: Option<Instant>,
+    /// Whether the window has decorations while it's windowed.
+    decorations: bool,
+    always_on_top: bool,
+
+    /// The options the window was built with, kept up to date by the setters that change them,
+    /// so `set_vsync` can build it again.
+    builder: WindowBuilder,
 }
 
-impl Window {
-    pub fn new(title: &str, width: f64, height: f64) -> Window {
-        let el = EventsLoop::new();
-        let wb = glutin::WindowBuilder::new()
-            .with_title(title)
-            .with_dimensions(glutin::dpi::LogicalSize::new(width, height));
-        let windowed_context = glutin::ContextBuilder::new()
-            .build_windowed(wb, &el)
+/// What `Window::run` passes to its closure every frame.
+pub struct Frame<'a> {
+    window: &'a mut Window,
+    context: &'a mut Context,
+    exit: bool,
+}
+
+impl<'a> Frame<'a> {
+    pub fn window(&mut self) -> &mut Window {
+        self.window
+    }
+
+    /// The context for drawing into the window. Its display size is already synced and
+    /// whatever is drawn is flushed and shown after the closure returns.
+    pub fn context(&mut self) -> &mut Context {
+        self.context
+    }
+
+    /// How long the last frame took in seconds, see `Window::frame_time`.
+    pub fn delta_seconds(&self) -> f32 {
+        self.window.frame_time()
+    }
+
+    /// Stops `Window::run` after this frame, like closing the window.
+    pub fn exit(&mut self) {
+        self.exit = true;
+    }
+}
+
+/// The GL API and version that a window's context is requested with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GlVersion {
+    /// Whatever the platform picks, usually the newest desktop GL compatibility profile.
+    Latest,
+    /// Desktop GL of at least `major.minor`. With `core` a core profile is requested, which
+    /// macOS needs for anything newer than GL 2.1.
+    OpenGl { major: u8, minor: u8, core: bool },
+    /// GL ES of at least `major.minor`.
+    OpenGlEs { major: u8, minor: u8 },
+}
+
+impl GlVersion {
+    /// GL 3.3 core, which the built-in shaders run on everywhere including macOS.
+    pub const CORE_3_3: GlVersion = GlVersion::OpenGl { major: 3, minor: 3, core: true };
+
+    fn request(self) -> (glutin::GlRequest, Option<glutin::GlProfile>) {
+        match self {
+            GlVersion::Latest => (glutin::GlRequest::Latest, None),
+            GlVersion::OpenGl { major, minor, core } => {
+                let profile = if core { glutin::GlProfile::Core } else { glutin::GlProfile::Compatibility };
+                (glutin::GlRequest::Specific(glutin::Api::OpenGl, (major, minor)), Some(profile))
+            },
+            GlVersion::OpenGlEs { major, minor } => (glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (major, minor)), None),
+        }
+    }
+}
+
+/// How a window covers the screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// A normal window with decorations.
+    Windowed,
+    /// A window without decorations that covers its monitor, which switches to and from other
+    /// windows quickly.
+    Borderless,
+    /// The platform's fullscreen mode on the window's monitor, at the monitor's current video
+    /// mode. On macOS this moves the window into its own space.
+    Exclusive,
+}
+
+/// Which pixels a `Context` draws in, as set up by `Window::sync_display_size`. The window's
+/// framebuffer always has one pixel per physical pixel; this only decides the display size that
+/// canvas coordinates map onto.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScalePolicy {
+    /// One canvas unit is one physical pixel, so things are drawn 1:1 and look smaller on HiDPI
+    /// displays. Use `Mouse::physical_position` for the cursor.
+    Physical,
+    /// One canvas unit is one logical pixel, so things are as large on every display and
+    /// sharper on HiDPI ones. Mouse positions are already in logical pixels.
+    Logical,
+}
+
+/// Options for creating a `Window`. Created with `Window::builder`.
+#[derive(Clone, Debug)]
+pub struct WindowBuilder {
+    title: String,
+    width: f64,
+    height: f64,
+    srgb: bool,
+    msaa: u16,
+    debug: bool,
+    gl_version: GlVersion,
+    vsync: bool,
+    scale_policy: ScalePolicy,
+    min_size: Option<(f64, f64)>,
+    max_size: Option<(f64, f64)>,
+    resizable: bool,
+    aspect_ratio: Option<f64>,
+    integer_scale: Option<(f64, f64)>,
+    position: Option<(f64, f64)>,
+    transparent: bool,
+    decorations: bool,
+    always_on_top: bool,
+}
+
+impl WindowBuilder {
+    pub fn title(mut self, title: &str) -> WindowBuilder {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn size(mut self, width: f64, height: f64) -> WindowBuilder {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Requests an sRGB framebuffer. If one is obtained, sRGB conversion is turned on and a
+    /// `Context` created for the window renders sRGB-correct (see `Context::set_srgb`).
+    pub fn srgb(mut self, srgb: bool) -> WindowBuilder {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Requests a multisampled framebuffer with `samples` samples per pixel (a power of two, 0
+    /// to turn it off) so edges of rotated and diagonal shapes are antialiased. Falls back to no
+    /// multisampling if the driver doesn't have a matching pixel format.
+    pub fn msaa(mut self, samples: u16) -> WindowBuilder {
+        self.msaa = samples;
+        self
+    }
+
+    /// Makes the window composited with what is behind it by its alpha channel, for overlays.
+    /// Clear with a transparent color (see `Context::set_clear_color`) to see through it. Only
+    /// works where the platform has a compositor; elsewhere the window stays opaque.
+    pub fn transparent(mut self, transparent: bool) -> WindowBuilder {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Whether the window has a title bar and border. True by default; tool windows like
+    /// palettes often go without.
+    pub fn decorations(mut self, decorations: bool) -> WindowBuilder {
+        self.decorations = decorations;
+        self
+    }
+
+    /// Keeps the window above other windows, e.g. for overlays.
+    pub fn always_on_top(mut self, always_on_top: bool) -> WindowBuilder {
+        self.always_on_top = always_on_top;
+        self
+    }
+
+    /// Places the window's top left corner at a position in logical pixels on the desktop,
+    /// instead of wherever the window manager puts it.
+    pub fn position(mut self, x: f64, y: f64) -> WindowBuilder {
+        self.position = Some((x, y));
+        self
+    }
+
+    /// The smallest size in logical pixels that the user can resize the window to.
+    pub fn min_size(mut self, width: f64, height: f64) -> WindowBuilder {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    /// The largest size in logical pixels that the user can resize the window to.
+    pub fn max_size(mut self, width: f64, height: f64) -> WindowBuilder {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    /// Whether the user can resize the window. True by default.
+    pub fn resizable(mut self, resizable: bool) -> WindowBuilder {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Keeps the client area at `width / height`, see `Window::set_aspect_ratio`.
+    pub fn aspect_ratio(mut self, width: f64, height: f64) -> WindowBuilder {
+        self.aspect_ratio = Some(width / height);
+        self
+    }
+
+    /// Keeps the client area at a whole multiple of a base size, see
+    /// `Window::set_integer_scale`.
+    pub fn integer_scale(mut self, width: f64, height: f64) -> WindowBuilder {
+        self.integer_scale = Some((width, height));
+        self
+    }
+
+    /// How a `Context` synced to the window is scaled on HiDPI displays. `Physical` by default.
+    pub fn scale_policy(mut self, policy: ScalePolicy) -> WindowBuilder {
+        self.scale_policy = policy;
+        self
+    }
+
+    /// Synchronizes buffer swaps with the display's refresh, so `Window::flip` waits for the
+    /// next vertical blank instead of tearing. Off by default. See `Window::set_vsync` for
+    /// changing it later.
+    pub fn vsync(mut self, vsync: bool) -> WindowBuilder {
+        self.vsync = vsync;
+        self
+    }
+
+    /// Requests a debug context whose driver messages of medium severity and up are printed to
+    /// stderr synchronously, from inside of the GL call that caused them. Replace the callback
+    /// with `gl_debug::set_debug_callback` to handle them differently.
+    pub fn debug(mut self, debug: bool) -> WindowBuilder {
+        self.debug = debug;
+        self
+    }
+
+    /// Requests a GL version and profile for the window's context. The built-in shaders are
+    /// adapted to whatever it ends up being, see `GlCapabilities`. Creating the window panics if
+    /// the driver can't provide the version.
+    pub fn gl_version(mut self, version: GlVersion) -> WindowBuilder {
+        self.gl_version = version;
+        self
+    }
+
+    pub fn build(self) -> Window {
+        let events_loop = EventsLoop::new();
+        let events = SharedEvents {
+            clipboard: Clipboard::new(&events_loop),
+            events_loop: RefCell::new(events_loop),
+            pending: RefCell::new(Vec::new()),
+            raw_motion: RefCell::new(Vec::new()),
+        };
+        self.build_on(Rc::new(events), None)
+    }
+
+    /// Builds another window that receives its events from the same events loop as `window`,
+    /// for applications with more than one window. Each of them has to handle its events every
+    /// frame, since the events of the others are held until they do. `wait_events` on any of
+    /// them returns once one of them received an event.
+    ///
+    /// The new window's GL context is current afterwards; see `Window::make_current`.
+    pub fn build_alongside(self, window: &Window) -> Window {
+        self.build_on(window.events.clone(), None)
+    }
+
+    /// Like `build_alongside`, and the new window's GL context also shares buffers, textures
+    /// and shaders with `window`'s, so images and fonts only have to be loaded once. Each
+    /// window still needs its own `Context`. Sharing generally needs both windows to be built
+    /// with the same GL version and pixel format options.
+    pub fn build_shared(self, window: &Window) -> Window {
+        self.build_on(window.events.clone(), Some(window))
+    }
+
+    fn build_on(self, events: Rc<SharedEvents>, shared: Option<&Window>) -> Window {
+        let windowed_context = self.build_context(&events, shared.map(|window| window.win_context.context()))
             .expect("Failed to build windowed context.");
+        let gl_info = self.init_gl(&windowed_context);
+        events.track_raw_motion(None, Some(windowed_context.id()));
+        let context_id = match shared {
+            Some(window) => ContextId::register_shared(window.context_id),
+            None => ContextId::register(),
+        };
+
+        let dpi_factor = windowed_context.get_hidpi_factor() as f32;
+        // the requested size is logical, so it only matches the framebuffer at a factor of 1.
+        let win_size = windowed_context.get_inner_size()
+            .map(|size| size.to_physical(dpi_factor as f64))
+            .map(|size| (size.width as f32, size.height as f32))
+            .unwrap_or((self.width as f32 * dpi_factor, self.height as f32 * dpi_factor));
+        let display_info = DisplayInfo {
+            gl: gl_info,
+            api: windowed_context.get_api(),
+            pixel_format: windowed_context.get_pixel_format(),
+            vsync: self.vsync,
+        };
+
+        let mut window = Window {
+            events,
+            win_context: windowed_context,
+
+            is_running: true,
+            win_size,
+            dpi_factor,
+            scale_policy: self.scale_policy,
+            keyboard_input: Vec::new(),
+            text_input: String::new(),
+            mouse: Mouse::new(dpi_factor),
+            #[cfg(feature = "gamepad")]
+            gamepads: Gamepads::new(),
+            touches: Touches::new(),
+            received: Vec::new(),
+            resized: None,
+            dpi_changed: None,
+            cursor_visible: true,
+            cursor_grabbed: false,
+            dropped_files: Vec::new(),
+            hovered_files: Vec::new(),
+
+            frame_clock: Cell::new(FrameClock::new()),
+            timers: Timers::new(),
+            deadline_waker: None,
+
+            display_info,
+            context_id,
 
+            fullscreen: FullscreenMode::Windowed,
+            windowed_rect: None,
+            aspect_ratio: self.aspect_ratio,
+            integer_scale: self.integer_scale,
+            snap_at: None,
+            decorations: self.decorations,
+            always_on_top: self.always_on_top,
+
+            builder: self.clone(),
+        };
+        window.apply_min_size();
+        window.constrain_size();
+        if let Some((x, y)) = self.position {
+            window.set_position(x, y);
+        }
+        window
+    }
+
+    /// Creates the window and its GL context, without multisampling if the requested samples
+    /// aren't available.
+    fn build_context(&self, events: &SharedEvents, shared: Option<&glutin::Context>) -> Result<WindowedContext, glutin::CreationError> {
+        let wb = glutin::WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_dimensions(glutin::dpi::LogicalSize::new(self.width, self.height))
+            .with_resizable(self.resizable)
+            .with_transparency(self.transparent)
+            .with_decorations(self.decorations)
+            .with_always_on_top(self.always_on_top);
+        let wb = match self.min_size {
+            Some((width, height)) => wb.with_min_dimensions(glutin::dpi::LogicalSize::new(width, height)),
+            None => wb,
+        };
+        let wb = match self.max_size {
+            Some((width, height)) => wb.with_max_dimensions(glutin::dpi::LogicalSize::new(width, height)),
+            None => wb,
+        };
+        let (gl_request, gl_profile) = self.gl_version.request();
+        let context_builder = || {
+            let mut builder = glutin::ContextBuilder::new()
+                .with_gl(gl_request)
+                .with_srgb(self.srgb)
+                .with_vsync(self.vsync)
+                .with_gl_debug_flag(self.debug);
+            if let Some(context) = shared {
+                builder = builder.with_shared_lists(context);
+            }
+            match gl_profile {
+                Some(profile) => builder.with_gl_profile(profile),
+                None => builder,
+            }
+        };
+
+        let el = events.events_loop.borrow();
+        context_builder()
+            .with_multisampling(self.msaa)
+            .build_windowed(wb.clone(), &el)
+            .or_else(|err| {
+                if self.msaa == 0 { return Err(err) }
+                context_builder().build_windowed(wb, &el)
+            })
+    }
+
+    /// Makes a new context current, loads the GL functions from it and sets up the state that
+    /// the options ask for.
+    fn init_gl(&self, windowed_context: &WindowedContext) -> GlInfo {
         unsafe {
             windowed_context.make_current().unwrap();
             gl::load_with(|symbol| windowed_context.get_proc_address(symbol) as *const _);
         }
+        if self.debug {
+            // low severity messages are mostly performance hints that some drivers send for
+            // every draw call.
+            gl_debug::print_debug_messages(DebugSeverity::Medium, true);
+        }
 
-        Window {
-            events_loop: Some(el),
-            win_context: windowed_context,
+        let gl_info = GlInfo::query();
+        if gl_info.capabilities.srgb_framebuffer {
+            let srgb = self.srgb && windowed_context.get_pixel_format().srgb;
+            unsafe {
+                if srgb {
+                    gl::Enable(gl::FRAMEBUFFER_SRGB);
+                } else {
+                    gl::Disable(gl::FRAMEBUFFER_SRGB);
+                }
+            }
+        }
+        gl_info
+    }
+}
 
-            is_running: true,
-            win_size: (width as _, height as _),
+impl Default for WindowBuilder {
+    fn default() -> WindowBuilder {
+        WindowBuilder {
+            title: String::new(),
+            width: 800.0,
+            height: 600.0,
+            srgb: false,
+            msaa: 0,
+            debug: false,
+            gl_version: GlVersion::Latest,
+            vsync: false,
+            scale_policy: ScalePolicy::Physical,
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            aspect_ratio: None,
+            integer_scale: None,
+            position: None,
+            transparent: false,
+            decorations: true,
+            always_on_top: false,
         }
     }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.context_id.unregister();
+        let id = self.win_context.id();
+        self.events.pending.borrow_mut().retain(|&(window_id, _)| window_id != id);
+        self.events.track_raw_motion(Some(id), None);
+    }
+}
+
+impl Window {
+    pub fn new(title: &str, width: f64, height: f64) -> Window {
+        Window::builder().title(title).size(width, height).build()
+    }
+
+    pub fn builder() -> WindowBuilder {
+        WindowBuilder::default()
+    }
+
+    /// The GL version, renderer and pixel format the window's context was created with.
+    pub fn display_info(&self) -> &DisplayInfo {
+        &self.display_info
+    }
 
     pub fn running(&self) -> bool {
         self.is_running
     }
 
     pub fn handle_events(&mut self) {
-        if let Some(mut events_loop) = self.events_loop.take() {
-            events_loop.poll_events(|event| {
+        self.begin_input_frame();
+        self.poll_pending_events();
+        self.fire_due(Instant::now());
+    }
+
+    /// Calls the timers that are due and snaps the window once the user stopped resizing it.
+    fn fire_due(&mut self, now: Instant) {
+        self.timers.fire_due(now);
+        if self.snap_at.map(|snap_at| snap_at <= now).unwrap_or(false) {
+            self.snap_at = None;
+            self.constrain_size();
+        }
+    }
+
+    fn poll_pending_events(&mut self) {
+        let events = self.events.clone();
+        self.handle_held_events();
+        events.events_loop.borrow_mut().poll_events(|event| {
+            match event {
+                glutin::Event::WindowEvent { window_id, event } => {
+                    self.dispatch_window_event(window_id, event);
+                },
+
+                glutin::Event::DeviceEvent { event: glutin::DeviceEvent::MouseMotion { delta }, .. } => {
+                    events.add_raw_motion(delta.0, delta.1);
+                },
+
+                _ => { /* NOP */ }
+            }
+        });
+        // including motion that arrived while another window was handling its events.
+        let (dx, dy) = events.take_raw_motion(self.win_context.id());
+        self.mouse.handle_motion(dx, dy);
+    }
+
+    /// Handles the events that other windows received for this one.
+    fn handle_held_events(&mut self) {
+        let id = self.win_context.id();
+        let held: Vec<_> = {
+            let mut pending = self.events.pending.borrow_mut();
+            if pending.is_empty() { return }
+            let (held, others) = pending.drain(..).partition(|&(window_id, _)| window_id == id);
+            *pending = others;
+            held
+        };
+        for (_, event) in held {
+            self.handle_window_event(event);
+        }
+    }
+
+    /// Handles an event for this window, or holds it for the window it is for.
+    fn dispatch_window_event(&mut self, window_id: glutin::WindowId, event: glutin::WindowEvent) {
+        if window_id == self.win_context.id() {
+            self.handle_window_event(event);
+        } else {
+            self.events.pending.borrow_mut().push((window_id, event));
+        }
+    }
+
+    /// Like `handle_events` but blocks until at least one event is received or until the next
+    /// timer is due.
+    pub fn wait_events(&mut self) {
+        let deadline = self.next_deadline();
+        if deadline.map(|d| d <= Instant::now()).unwrap_or(false) {
+            self.handle_events();
+            return;
+        }
+
+        let events = self.events.clone();
+        if deadline.is_some() && self.deadline_waker.is_none() {
+            let proxy = events.events_loop.borrow().create_proxy();
+            self.deadline_waker = Some(DeadlineWaker::new(proxy));
+        }
+        if let Some(ref waker) = self.deadline_waker {
+            waker.set_deadline(deadline);
+        }
+
+        self.begin_input_frame();
+        // events that are already held for this window or another one don't need waiting for.
+        if events.pending.borrow().is_empty() {
+            events.events_loop.borrow_mut().run_forever(|event| {
                 match event {
-                    glutin::Event::WindowEvent { event, .. } => {
-                        self.handle_window_event(event);
+                    glutin::Event::WindowEvent { window_id, event } => {
+                        self.dispatch_window_event(window_id, event);
+                    },
+                    glutin::Event::DeviceEvent { event: glutin::DeviceEvent::MouseMotion { delta }, .. } => {
+                        events.add_raw_motion(delta.0, delta.1);
                     },
-
                     _ => { /* NOP */ }
                 }
+                glutin::ControlFlow::Break
             });
-            self.events_loop = Some(events_loop);
-        } else {
-            panic!("Attempted to run tick with no active events loop.");
         }
+
+        // pick up anything else that arrived along with the event that woke us up.
+        self.poll_pending_events();
+        self.fire_due(Instant::now());
+    }
+
+    /// Calls `callback` once from `handle_events` or `wait_events` after `delay` has passed.
+    pub fn after<F: FnMut() + 'static>(&mut self, delay: Duration, callback: F) -> TimerId {
+        self.timers.after(delay, callback)
+    }
+
+    /// Calls `callback` from `handle_events` or `wait_events` every `interval`, which is
+    /// clamped to at least `timer::MIN_INTERVAL`.
+    pub fn every<F: FnMut() + 'static>(&mut self, interval: Duration, callback: F) -> TimerId {
+        self.timers.every(interval, callback)
+    }
+
+    /// Returns true if the timer had not fired (or was repeating) and is now cancelled.
+    pub fn cancel_timer(&mut self, id: TimerId) -> bool {
+        self.timers.cancel(id)
+    }
+
+    /// The instant the next timer is due, for applications that drive their own waiting.
+    pub fn next_timer_deadline(&self) -> Option<Instant> {
+        self.timers.next_deadline()
+    }
+
+    /// The instant `wait_events` has to return at, for the next timer or snapping the window.
+    fn next_deadline(&self) -> Option<Instant> {
+        match (self.timers.next_deadline(), self.snap_at) {
+            (Some(timer), Some(snap_at)) => Some(timer.min(snap_at)),
+            (timer, snap_at) => timer.or(snap_at),
+        }
+    }
+
+    /// Forgets the input of the previous call to `handle_events` or `wait_events`.
+    fn begin_input_frame(&mut self) {
+        self.keyboard_input.clear();
+        self.text_input.clear();
+        self.received.clear();
+        self.resized = None;
+        self.dpi_changed = None;
+        self.dropped_files.clear();
+        self.mouse.begin_frame();
+        #[cfg(feature = "gamepad")]
+        self.gamepads.update();
+        self.touches.begin_frame();
     }
 
     fn handle_window_event(&mut self, event: glutin::WindowEvent) {
+        self.mouse.handle_event(&event, self.win_context.get_hidpi_factor() as f32);
+        self.touches.handle_event(&event);
         match event {
             glutin::WindowEvent::CloseRequested => self.is_running = false,
             glutin::WindowEvent::Resized(logical_size) => {
-                let dpi_factor = self.win_context.get_hidpi_factor();
-                let physical_size = logical_size.to_physical(dpi_factor);
-                self.win_context.resize(physical_size);
-                self.win_size = (physical_size.width as _, physical_size.height as _);
-                unsafe {
-                    gl::Viewport(0, 0, physical_size.width as _, physical_size.height as _);
+                self.resize(logical_size);
+                if self.aspect_ratio.is_some() || self.integer_scale.is_some() {
+                    self.snap_at = Some(Instant::now() + SNAP_DELAY);
                 }
             },
+            glutin::WindowEvent::HiDpiFactorChanged(dpi_factor) => {
+                self.dpi_factor = dpi_factor as f32;
+                self.dpi_changed = Some(self.dpi_factor);
+                // the integer scale's base size is in physical pixels.
+                self.apply_min_size();
+                // the logical size stays the same, so the framebuffer has to grow or shrink.
+                if let Some(size) = self.win_context.get_inner_size() {
+                    self.resize(size);
+                }
+            },
+            glutin::WindowEvent::KeyboardInput { input, .. } => self.keyboard_input.push(input),
+            glutin::WindowEvent::ReceivedCharacter(c) if !c.is_control() => self.text_input.push(c),
+            glutin::WindowEvent::HoveredFile(ref path) => self.hovered_files.push(path.clone()),
+            glutin::WindowEvent::HoveredFileCancelled => self.hovered_files.clear(),
+            glutin::WindowEvent::DroppedFile(ref path) => {
+                self.hovered_files.clear();
+                self.dropped_files.push(path.clone());
+            },
             _ => { /* NOP */ }
         }
+        // after resizing, so the event has the new size.
+        self.received.push(Event::from_window_event(&event, self.win_size));
+    }
+
+    /// Handles pending events like `handle_events` and returns them, oldest first.
+    pub fn poll_events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.handle_events();
+        self.received.drain(..)
+    }
+
+    /// The events received during the last call to `handle_events` or `wait_events`, oldest
+    /// first. Empty after they were taken with `poll_events`.
+    pub fn events(&self) -> &[Event] {
+        &self.received
+    }
+
+    /// The text typed during the last call to `handle_events` or `wait_events`, for text
+    /// fields. Unlike `keyboard_input` this is what the keyboard layout, dead keys and input
+    /// methods produced, so it's right on every layout. Control characters are left out; use
+    /// `keyboard_input` for backspace, enter and the like. Text that an input method is still
+    /// composing isn't reported, only what it commits.
+    pub fn text_input(&self) -> &str {
+        &self.text_input
+    }
+
+    /// Moves the input method's candidate box to a position in logical pixels relative to the
+    /// window's client area, usually the text cursor of the focused text field. Only has an
+    /// effect on Windows and X11.
+    pub fn set_ime_position(&self, x: f32, y: f32) {
+        self.win_context.set_ime_spot(glutin::dpi::LogicalPosition::new(x as f64, y as f64));
+    }
+
+    /// Files that were dropped on the window during the last call to `handle_events` or
+    /// `wait_events`, in the order they were reported.
+    pub fn dropped_files(&self) -> &[PathBuf] {
+        &self.dropped_files
+    }
+
+    /// Files that are being dragged over the window but haven't been dropped yet, e.g. to
+    /// highlight where they would go. Empty once they're dropped or dragged away.
+    pub fn hovered_files(&self) -> &[PathBuf] {
+        &self.hovered_files
+    }
+
+    /// The new width and height in physical pixels if the window was resized during the last
+    /// call to `handle_events` or `wait_events`, or by `set_fullscreen` since then.
+    pub fn was_resized(&self) -> Option<(f32, f32)> {
+        self.resized
+    }
+
+    /// Sets the display size of `context` to `display_size` if it isn't already, e.g. once per
+    /// frame after handling events. Returns true if it changed.
+    pub fn sync_display_size(&self, context: &mut Context) -> bool {
+        let (width, height) = self.display_size();
+        if context.display_size() == (width, height) { return false }
+        context.set_display_size(width, height);
+        true
+    }
+
+    /// The number of physical pixels per logical pixel, e.g. 2.0 on most HiDPI displays.
+    pub fn dpi_factor(&self) -> f32 {
+        self.dpi_factor
+    }
+
+    /// The new factor if it changed during the last call to `handle_events` or `wait_events`,
+    /// e.g. because the window was moved to another monitor. The window's size is updated
+    /// along with it.
+    pub fn was_dpi_factor_changed(&self) -> Option<f32> {
+        self.dpi_changed
+    }
+
+    pub fn scale_policy(&self) -> ScalePolicy {
+        self.scale_policy
+    }
+
+    pub fn set_scale_policy(&mut self, policy: ScalePolicy) {
+        self.scale_policy = policy;
+    }
+
+    /// The size of the client area in the pixels of the scale policy, which is what a `Context`
+    /// for the window should use as its display size.
+    pub fn display_size(&self) -> (f32, f32) {
+        match self.scale_policy {
+            ScalePolicy::Physical => self.win_size,
+            ScalePolicy::Logical => (self.win_size.0 / self.dpi_factor, self.win_size.1 / self.dpi_factor),
+        }
+    }
+
+    /// Makes the window's GL context the current one, so a `Context` created for this window
+    /// draws into it. Only needed with more than one window; each window's `Context` has to be
+    /// drawn and the window flipped while its context is current.
+    pub fn make_current(&self) {
+        if self.context_id.is_current() { return }
+        unsafe {
+            self.win_context.make_current().expect("Failed to make the window's context current.");
+        }
+        self.context_id.make_current();
+        // the viewport is part of the context and may have missed resizes while another one
+        // was current.
+        unsafe {
+            gl::Viewport(0, 0, self.win_size.0 as _, self.win_size.1 as _);
+        }
+    }
+
+    /// Resizes the context's framebuffer and the viewport to the client area.
+    fn resize(&mut self, logical_size: glutin::dpi::LogicalSize) {
+        let dpi_factor = self.win_context.get_hidpi_factor();
+        let physical_size = logical_size.to_physical(dpi_factor);
+        self.win_context.resize(physical_size);
+        self.win_size = (physical_size.width as _, physical_size.height as _);
+        self.resized = Some(self.win_size);
+        if self.context_id.is_current() {
+            unsafe {
+                gl::Viewport(0, 0, physical_size.width as _, physical_size.height as _);
+            }
+        }
+    }
+
+    /// The position of the window's top left corner including its decorations, in logical
+    /// pixels on the desktop. None if the window was closed or the platform doesn't tell.
+    pub fn position(&self) -> Option<(f64, f64)> {
+        self.win_context.get_position().map(|position| (position.x, position.y))
+    }
+
+    /// Moves the window's top left corner including its decorations to a position in logical
+    /// pixels on the desktop. Window managers may still adjust it, e.g. to keep it on screen.
+    pub fn set_position(&self, x: f64, y: f64) {
+        self.win_context.set_position(glutin::dpi::LogicalPosition::new(x, y));
+    }
+
+    /// Centers the window on the monitor it's mostly on.
+    pub fn center_on_monitor(&self) {
+        let monitor = self.win_context.get_current_monitor();
+        let dpi_factor = monitor.get_hidpi_factor();
+        let monitor_position = monitor.get_position().to_logical(dpi_factor);
+        let monitor_size = monitor.get_dimensions().to_logical(dpi_factor);
+        let size = match self.win_context.get_outer_size() {
+            Some(size) => size,
+            None => return,
+        };
+        self.set_position(
+            monitor_position.x + (monitor_size.width - size.width) / 2.0,
+            monitor_position.y + (monitor_size.height - size.height) / 2.0,
+        );
+    }
+
+    /// Shows or hides the title bar and border. Borderless fullscreen hides them regardless and
+    /// this takes effect once the window is windowed again.
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.decorations = decorations;
+        if self.fullscreen != FullscreenMode::Borderless {
+            self.win_context.set_decorations(decorations);
+        }
+    }
+
+    pub fn has_decorations(&self) -> bool {
+        self.decorations
+    }
+
+    /// Keeps the window above other windows or lets it be covered again.
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.always_on_top = always_on_top;
+        self.win_context.set_always_on_top(always_on_top);
+    }
+
+    pub fn is_always_on_top(&self) -> bool {
+        self.always_on_top
+    }
+
+    /// Sets the smallest size in logical pixels that the user can resize the window to.
+    pub fn set_min_size(&mut self, size: Option<(f64, f64)>) {
+        self.builder.min_size = size;
+        self.apply_min_size();
+    }
+
+    /// Sets the window's minimum size to the larger of `min_size` and one multiple of the
+    /// integer scale's base size.
+    fn apply_min_size(&mut self) {
+        let min_size = self.builder.min_size;
+        let scale_size = self.integer_scale.map(|(base_width, base_height)| {
+            let dpi_factor = self.win_context.get_hidpi_factor();
+            (base_width / dpi_factor, base_height / dpi_factor)
+        });
+        let size = match (min_size, scale_size) {
+            (Some((w, h)), Some((base_w, base_h))) => Some((w.max(base_w), h.max(base_h))),
+            (min_size, scale_size) => min_size.or(scale_size),
+        };
+        self.win_context.set_min_dimensions(size.map(|(w, h)| glutin::dpi::LogicalSize::new(w, h)));
+    }
+
+    /// Sets the largest size in logical pixels that the user can resize the window to.
+    pub fn set_max_size(&mut self, size: Option<(f64, f64)>) {
+        self.builder.max_size = size;
+        self.win_context.set_max_dimensions(size.map(|(w, h)| glutin::dpi::LogicalSize::new(w, h)));
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.builder.resizable = resizable;
+        self.win_context.set_resizable(resizable);
+    }
+
+    /// Keeps the client area at an aspect ratio of `width / height`. Window systems don't
+    /// support this directly, so once the user has stopped resizing the window it's shrunk to
+    /// the largest size with the ratio that fits. `handle_events` or `wait_events` has to keep
+    /// being called for that, and the new size arrives as a resize event like any other.
+    pub fn set_aspect_ratio(&mut self, ratio: Option<(f64, f64)>) {
+        self.aspect_ratio = ratio.map(|(width, height)| width / height);
+        self.constrain_size();
+    }
+
+    /// Keeps the client area at a whole multiple of `width` by `height` physical pixels, e.g.
+    /// the native resolution of an emulated system, so every one of its pixels covers the
+    /// same number of display pixels. Like `set_aspect_ratio` the window is shrunk to fit
+    /// after the user resizes it, and its minimum size keeps it from going below one multiple.
+    pub fn set_integer_scale(&mut self, base_size: Option<(f64, f64)>) {
+        self.integer_scale = base_size;
+        self.apply_min_size();
+        self.constrain_size();
+    }
+
+    /// Asks for the window to be snapped to the aspect ratio and integer scale, if it is
+    /// windowed. The window system resizes it whenever it gets to that, and the resize event
+    /// that follows finds it at a size that already fits.
+    fn constrain_size(&mut self) {
+        if self.fullscreen != FullscreenMode::Windowed { return }
+        if self.aspect_ratio.is_none() && self.integer_scale.is_none() { return }
+        let size = match self.win_context.get_inner_size() {
+            Some(size) => size,
+            None => return,
+        };
+
+        let (mut width, mut height) = (size.width, size.height);
+        if let Some(ratio) = self.aspect_ratio {
+            width = width.min(height * ratio);
+            height = width / ratio;
+        }
+        if let Some((base_width, base_height)) = self.integer_scale {
+            let dpi_factor = self.win_context.get_hidpi_factor();
+            let scale = (width * dpi_factor / base_width).floor().min((height * dpi_factor / base_height).floor()).max(1.0);
+            width = scale * base_width / dpi_factor;
+            height = scale * base_height / dpi_factor;
+        }
+
+        if (width - size.width).abs() >= 0.5 || (height - size.height).abs() >= 0.5 {
+            self.win_context.set_inner_size(glutin::dpi::LogicalSize::new(width, height));
+        }
+    }
+
+    /// Switches between windowed, borderless and exclusive fullscreen on the monitor the window
+    /// is on. Going back to `Windowed` restores the window's previous position and size. The
+    /// new size is available from `width` and `height` right away, to be passed on to
+    /// `Context::set_display_size` or with `sync_display_size`.
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        if mode == self.fullscreen { return }
+        let window = self.win_context.window();
+        if self.fullscreen == FullscreenMode::Windowed {
+            if let (Some(position), Some(size)) = (window.get_position(), window.get_inner_size()) {
+                self.windowed_rect = Some((position, size));
+            }
+        }
+
+        match self.fullscreen {
+            FullscreenMode::Exclusive => window.set_fullscreen(None),
+            FullscreenMode::Borderless => window.set_decorations(self.decorations),
+            FullscreenMode::Windowed => {},
+        }
+        match mode {
+            FullscreenMode::Exclusive => window.set_fullscreen(Some(window.get_current_monitor())),
+            FullscreenMode::Borderless => {
+                let monitor = window.get_current_monitor();
+                let dpi_factor = monitor.get_hidpi_factor();
+                window.set_decorations(false);
+                window.set_position(monitor.get_position().to_logical(dpi_factor));
+                window.set_inner_size(monitor.get_dimensions().to_logical(dpi_factor));
+            },
+            FullscreenMode::Windowed => {
+                if let Some((position, size)) = self.windowed_rect.take() {
+                    window.set_position(position);
+                    window.set_inner_size(size);
+                }
+            },
+        }
+        self.fullscreen = mode;
+
+        // the resize event arrives later, but the size should be right for this frame already.
+        if let Some(size) = self.win_context.window().get_inner_size() {
+            self.resize(size);
+        }
+    }
+
+    pub fn fullscreen(&self) -> FullscreenMode {
+        self.fullscreen
+    }
+
+    /// Switches between `Windowed` and `mode`, e.g. when F11 is pressed.
+    pub fn toggle_fullscreen(&mut self, mode: FullscreenMode) {
+        let mode = if self.fullscreen == FullscreenMode::Windowed { mode } else { FullscreenMode::Windowed };
+        self.set_fullscreen(mode);
+    }
+
+    /// Keyboard input received during the last call to `handle_events`, oldest first.
+    pub fn keyboard_input(&self) -> &[glutin::KeyboardInput] {
+        &self.keyboard_input
+    }
+
+    /// The cursor position and button state as of the last call to `handle_events`.
+    pub fn mouse(&self) -> &Mouse {
+        &self.mouse
+    }
+
+    /// The fingers on the window and where they moved, on platforms that report touches.
+    pub fn touches(&self) -> &Touches {
+        &self.touches
+    }
+
+    /// The connected gamepads and their input, see `Gamepads` for which platforms have them.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    /// Changes the system cursor shown over the window, e.g. `MouseCursor::Crosshair` for a
+    /// drawing tool.
+    pub fn set_cursor(&self, cursor: glutin::MouseCursor) {
+        self.win_context.set_cursor(cursor);
+    }
+
+    /// Hides the cursor while it's over the window, for applications that draw their own.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.win_context.hide_cursor(!visible);
+        self.cursor_visible = visible;
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Confines the cursor to the window while it has focus. Together with a hidden cursor and
+    /// `Mouse::raw_delta` this gives FPS-style camera control. Fails if the platform doesn't
+    /// allow it, e.g. because another application has grabbed the cursor.
+    pub fn set_cursor_grab(&mut self, grab: bool) -> Result<(), String> {
+        self.win_context.grab_cursor(grab)?;
+        self.cursor_grabbed = grab;
+        Ok(())
+    }
+
+    pub fn is_cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    /// Moves the cursor to a position in logical pixels relative to the window's client area.
+    pub fn set_cursor_position(&self, x: f32, y: f32) -> Result<(), String> {
+        self.win_context.set_cursor_position(glutin::dpi::LogicalPosition::new(x as f64, y as f64))
+    }
+
+    /// The text on the system clipboard, for pasting. None if the clipboard is empty, holds
+    /// something that isn't text or couldn't be opened.
+    pub fn clipboard_text(&self) -> Option<String> {
+        self.events.clipboard.text()
+    }
+
+    /// Puts `text` on the system clipboard. Returns false if the clipboard couldn't be opened
+    /// or refused the text.
+    pub fn set_clipboard_text(&self, text: &str) -> bool {
+        self.events.clipboard.set_text(text)
+    }
+
+    /// Changes whether buffer swaps wait for the display's vertical blank, through the swap
+    /// control extension of the platform (WGL_EXT_swap_control, GLX_EXT_swap_control or
+    /// GLX_MESA_swap_control, or EGL's swap interval). Makes the window's context current,
+    /// since the swap interval belongs to it.
+    ///
+    /// Without such an extension the window is built again with `WindowBuilder::vsync` set
+    /// accordingly. The new GL context shares objects with the old one, so textures, buffers and
+    /// shaders survive, but a `Context` created for the window has to be created again, since
+    /// its vertex arrays and framebuffers don't. Returns false if neither worked.
+    pub fn set_vsync(&mut self, vsync: bool) -> bool {
+        self.make_current();
+        let set = swap_control::set_interval(&self.win_context, if vsync { 1 } else { 0 })
+            || self.rebuild_with_vsync(vsync);
+        if set {
+            self.display_info.vsync = vsync;
+        }
+        set
+    }
+
+    /// Replaces the window and its GL context with ones that are created with `vsync`, in the
+    /// same place and state.
+    fn rebuild_with_vsync(&mut self, vsync: bool) -> bool {
+        let mut builder = self.builder.clone();
+        builder.vsync = vsync;
+        builder.decorations = self.decorations;
+        builder.always_on_top = self.always_on_top;
+        let windowed_rect = match self.fullscreen {
+            FullscreenMode::Windowed => match (self.win_context.get_position(), self.win_context.get_inner_size()) {
+                (Some(position), Some(size)) => Some((position, size)),
+                _ => None,
+            },
+            _ => self.windowed_rect,
+        };
+        if let Some((position, size)) = windowed_rect {
+            builder.position = Some((position.x, position.y));
+            builder.width = size.width;
+            builder.height = size.height;
+        }
+
+        let windowed_context = match builder.build_context(&self.events, Some(self.win_context.context())) {
+            Ok(windowed_context) => windowed_context,
+            Err(_) => {
+                // the old context stays current, since building another one may have changed it.
+                unsafe {
+                    self.win_context.make_current().expect("Failed to make the window's context current.");
+                }
+                self.context_id.make_current();
+                return false;
+            },
+        };
+        let gl_info = builder.init_gl(&windowed_context);
+        let context_id = ContextId::register_shared(self.context_id);
+
+        // the old window's held events won't be handled anymore.
+        let old_id = self.win_context.id();
+        self.events.pending.borrow_mut().retain(|&(window_id, _)| window_id != old_id);
+        self.events.track_raw_motion(Some(old_id), Some(windowed_context.id()));
+        self.context_id.unregister();
+        self.context_id = context_id;
+        self.display_info = DisplayInfo {
+            gl: gl_info,
+            api: windowed_context.get_api(),
+            pixel_format: windowed_context.get_pixel_format(),
+            vsync,
+        };
+        self.win_context = windowed_context;
+
+        if let Some((x, y)) = builder.position {
+            self.set_position(x, y);
+        }
+        self.builder = builder;
+        self.win_context.hide_cursor(!self.cursor_visible);
+        if self.cursor_grabbed && self.win_context.grab_cursor(true).is_err() {
+            self.cursor_grabbed = false;
+        }
+        let fullscreen = std::mem::replace(&mut self.fullscreen, FullscreenMode::Windowed);
+        self.set_fullscreen(fullscreen);
+        if let Some(size) = self.win_context.get_inner_size() {
+            self.resize(size);
+        }
+        true
     }
 
+    /// Shows what was drawn and ticks the frame clock. With vsync this waits for the display.
     pub fn flip(&self) {
         self.win_context.swap_buffers().unwrap();
+        let mut frame_clock = self.frame_clock.get();
+        frame_clock.tick();
+        self.frame_clock.set(frame_clock);
+    }
+
+    /// Timing of the frames shown with `flip`, as of the last one.
+    pub fn frame_clock(&self) -> FrameClock {
+        self.frame_clock.get()
+    }
+
+    /// Runs the main loop until the window is closed or `Frame::exit` is called: handles events,
+    /// syncs the display size of a `Context` created for the window, calls `frame`, then
+    /// flushes the context and flips. The context is dropped before the window.
+    pub fn run<F: FnMut(&mut Frame)>(mut self, mut frame: F) {
+        let mut context = Context::new();
+        while self.running() {
+            self.handle_events();
+            self.sync_display_size(&mut context);
+            let exit = {
+                let mut f = Frame { window: &mut self, context: &mut context, exit: false };
+                frame(&mut f);
+                f.exit
+            };
+            context.flush_verts();
+            self.flip();
+            if exit { break }
+        }
+    }
+
+    /// Runs a game loop until the window is closed: handles events, calls `update` `rate` times
+    /// per second with the step in seconds, then `render` once per frame with the interpolation
+    /// alpha of `FixedTimestep::advance`, and flips. Both get `state`, so they can share the
+    /// game state (and the `Context`) without fighting over borrows. Panics if `rate` isn't a
+    /// positive, finite number, like `FixedTimestep::new`.
+    pub fn run_fixed<S, U, R>(&mut self, rate: f64, state: &mut S, mut update: U, mut render: R)
+    where
+        U: FnMut(&mut S, &mut Window, f32),
+        R: FnMut(&mut S, &mut Window, f32),
+    {
+        let mut timestep = FixedTimestep::new(rate);
+        while self.running() {
+            self.handle_events();
+            let alpha = timestep.advance(self.frame_clock.get().delta(), |dt| update(state, self, dt));
+            render(state, self, alpha);
+            self.flip();
+        }
+    }
+
+    /// How long the last frame took in seconds, for scaling movement so it's the same at any
+    /// frame rate.
+    pub fn frame_time(&self) -> f32 {
+        self.frame_clock.get().delta_seconds()
     }
 
+    /// The width of the client area in physical pixels, regardless of the scale policy.
     pub fn width(&self) -> f32 {
         self.win_size.0
     }
 
+    /// The height of the client area in physical pixels, regardless of the scale policy.
     pub fn height(&self) -> f32 {
         self.win_size.1
     }